@@ -158,16 +158,18 @@ fn setup(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>
         });
     }
 
+    // Define the outer borders of the navmesh.
+    let outer_edges = vec![
+        vec2(0.0, 0.0),
+        vec2(MESH_WIDTH as f32, 0.0),
+        vec2(MESH_WIDTH as f32, MESH_HEIGHT as f32),
+        vec2(0.0, MESH_HEIGHT as f32),
+    ];
     // Spawn a new navmesh that will be automatically updated.
     commands.spawn(NavMeshBundle {
         settings: NavMeshSettings {
-            // Define the outer borders of the navmesh.
-            fixed: Triangulation::from_outer_edges(&vec![
-                vec2(0.0, 0.0),
-                vec2(MESH_WIDTH as f32, 0.0),
-                vec2(MESH_WIDTH as f32, MESH_HEIGHT as f32),
-                vec2(0.0, MESH_HEIGHT as f32),
-            ]),
+            fixed: Triangulation::from_outer_edges(&outer_edges),
+            outer_edges,
             simplify: 0.101,
             merge_steps: 3,
             build_timeout: Some(0.5),
@@ -265,7 +265,7 @@ pub fn update_stats<T: Component>(
     text.sections[1].style.color = match *status {
         NavMeshStatus::Building => palettes::tailwind::AMBER_500.into(),
         NavMeshStatus::Built => palettes::tailwind::GREEN_400.into(),
-        NavMeshStatus::Failed => palettes::tailwind::RED_600.into(),
+        NavMeshStatus::Failed | NavMeshStatus::Invalid => palettes::tailwind::RED_600.into(),
     };
     text.sections[3].value = format!("{}", obstacles.iter().len());
     text.sections[5].value = format!(
@@ -0,0 +1,79 @@
+//! Compare point queries on the aurora mesh with and without a spatial index.
+//!
+//! Run in release mode: `cargo run --release --example spatial_index`
+
+use std::time::{Duration, Instant};
+
+use bevy::math::Vec2;
+use polyanya::PolyanyaFile;
+use vleue_navigator::NavMesh;
+
+const QUERIES: usize = 100_000;
+
+fn main() {
+    let bytes = std::fs::read("assets/aurora-merged.polyanya.mesh").unwrap();
+    let navmesh = NavMesh::from_polyanya_mesh(
+        PolyanyaFile::from_bytes(bytes.as_slice())
+            .try_into()
+            .unwrap(),
+    );
+    let stats = navmesh.stats();
+    println!(
+        "aurora: {} polygons, {} vertices",
+        stats.polygons, stats.vertices
+    );
+
+    // Points on a deterministic pseudo random sequence, over the bounds of the mesh
+    let mesh = navmesh.get();
+    let (min, max) = mesh.vertices.iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), vertex| (min.min(vertex.coords), max.max(vertex.coords)),
+    );
+    let points = (0..QUERIES)
+        .map(|i| {
+            let t = Vec2::new(
+                (i as f32 * 0.618_034).fract(),
+                (i as f32 * 0.754_878).fract(),
+            );
+            min + (max - min) * t
+        })
+        .collect::<Vec<_>>();
+
+    let without = measure(&navmesh, &points);
+    let start = Instant::now();
+    navmesh.build_spatial_index();
+    println!("index built in {:?}", start.elapsed());
+    let with = measure(&navmesh, &points);
+
+    for (name, without, with) in [
+        ("is_in_mesh", without.0, with.0),
+        ("closest_point", without.1, with.1),
+    ] {
+        println!(
+            "{name}: {:?} without index, {:?} with index ({:.1}x)",
+            without,
+            with,
+            without.as_secs_f64() / with.as_secs_f64()
+        );
+    }
+}
+
+fn measure(navmesh: &NavMesh, points: &[Vec2]) -> (Duration, Duration) {
+    let start = Instant::now();
+    let inside = points
+        .iter()
+        .filter(|point| navmesh.is_in_mesh(**point))
+        .count();
+    let in_mesh = start.elapsed();
+
+    // Closest point is slower without the index, only use a part of the points
+    let start = Instant::now();
+    let closest = points[..points.len() / 10]
+        .iter()
+        .filter_map(|point| navmesh.closest_point(*point))
+        .count();
+    let closest_point = start.elapsed() * 10;
+
+    println!("{inside} points in mesh, {closest} closest points");
+    (in_mesh, closest_point)
+}
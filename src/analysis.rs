@@ -0,0 +1,585 @@
+use bevy::{
+    math::{Vec2, Vec3, Vec3Swizzles},
+    utils::HashMap,
+};
+
+use crate::{
+    geometry::{centroid, clip_convex, counter_clockwise, signed_area},
+    NavMesh,
+};
+
+/// An edge shared between two polygons of a [`NavMesh`], in the mesh local space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Portal {
+    /// Indices of the two polygons connected by this portal.
+    pub polygons: (usize, usize),
+    /// First point of the edge.
+    pub start: Vec2,
+    /// Second point of the edge.
+    pub end: Vec2,
+}
+
+impl Portal {
+    /// Width of the portal.
+    pub fn width(&self) -> f32 {
+        self.start.distance(self.end)
+    }
+
+    /// Middle of the portal.
+    pub fn center(&self) -> Vec2 {
+        (self.start + self.end) / 2.0
+    }
+}
+
+/// Identifier of a region of a [`NavMesh`]. See [`NavMesh::regions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RegionId(pub usize);
+
+/// Information about a region of a [`NavMesh`], a part of the mesh connected through portals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionInfo {
+    /// Identifier of the region.
+    pub id: RegionId,
+    /// Area of the region, in the mesh local space.
+    pub area: f32,
+    /// Number of polygons in the region.
+    pub polygons: usize,
+}
+
+/// Graph of the polygons of a [`NavMesh`] connected through their portals, in the mesh local space. See
+/// [`NavMesh::adjacency`].
+///
+/// Polygons are identified by their index, the same as in [`Portal::polygons`] and [`NavMesh::polygon_at`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavMeshAdjacency {
+    /// Polygons of the mesh.
+    pub polygons: Vec<AdjacencyNode>,
+    /// Portals between the polygons, each shared edge being listed once.
+    pub portals: Vec<Portal>,
+}
+
+impl NavMeshAdjacency {
+    /// Polygons connected to `polygon`, with the index of the portal to each of them.
+    pub fn neighbors(&self, polygon: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.polygons
+            .get(polygon)
+            .into_iter()
+            .flat_map(|node| node.portals.iter())
+            .map(move |portal| {
+                let (a, b) = self.portals[*portal].polygons;
+                (if a == polygon { b } else { a }, *portal)
+            })
+    }
+}
+
+/// A polygon in a [`NavMeshAdjacency`] graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjacencyNode {
+    /// Centroid of the polygon.
+    pub centroid: Vec2,
+    /// Area of the polygon.
+    pub area: f32,
+    /// Indices in [`NavMeshAdjacency::portals`] of the portals of the polygon.
+    pub portals: Vec<usize>,
+}
+
+/// Statistics of a [`NavMesh`], in the mesh local space. See [`NavMesh::stats`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NavMeshStats {
+    /// Number of polygons.
+    pub polygons: usize,
+    /// Number of vertices.
+    pub vertices: usize,
+    /// Total navigable area.
+    pub area: f32,
+    /// Total length of the edges on the boundary of the mesh, around it and around obstacles.
+    pub boundary_length: f32,
+}
+
+/// Difference of navigable area between two [`NavMesh`]es, in the mesh local space. See [`NavMesh::diff`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NavMeshDiff {
+    /// Area navigable in the other navmesh, but not in this one.
+    pub added_area: f32,
+    /// Area navigable in this navmesh, but not in the other one.
+    pub removed_area: f32,
+    /// Area navigable in both navmeshes.
+    pub common_area: f32,
+}
+
+impl NavMesh {
+    /// Vertices of each polygon, counter clockwise.
+    pub(crate) fn polygon_vertices(&self) -> impl Iterator<Item = Vec<Vec2>> + '_ {
+        self.mesh.polygons.iter().map(|polygon| {
+            counter_clockwise(
+                polygon
+                    .vertices
+                    .iter()
+                    .map(|index| self.mesh.vertices[*index as usize].coords)
+                    .collect(),
+            )
+        })
+    }
+
+    /// Compute statistics of the mesh, to check its shape in tests or tune its build.
+    pub fn stats(&self) -> NavMeshStats {
+        NavMeshStats {
+            polygons: self.mesh.polygons.len(),
+            vertices: self.mesh.vertices.len(),
+            area: self
+                .polygon_vertices()
+                .map(|polygon| signed_area(&polygon))
+                .sum(),
+            boundary_length: self
+                .boundary_edges()
+                .iter()
+                .map(|[start, end]| start.distance(*end))
+                .sum(),
+        }
+    }
+
+    /// Compare the navigable area of this navmesh with `other`, for example to check in tests that an obstacle
+    /// carved the expected area.
+    ///
+    /// Both navmeshes are compared in their local space, their transforms are ignored.
+    pub fn diff(&self, other: &NavMesh) -> NavMeshDiff {
+        let bounds = |polygon: &[Vec2]| {
+            polygon.iter().fold(
+                (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                |(min, max), point| (min.min(*point), max.max(*point)),
+            )
+        };
+        let mine = self
+            .polygon_vertices()
+            .map(|polygon| (bounds(&polygon), polygon))
+            .collect::<Vec<_>>();
+        let theirs = other
+            .polygon_vertices()
+            .map(|polygon| (bounds(&polygon), polygon))
+            .collect::<Vec<_>>();
+        let mut common_area = 0.0;
+        for ((min, max), polygon) in &mine {
+            for ((other_min, other_max), other_polygon) in &theirs {
+                if min.cmpgt(*other_max).any() || other_min.cmpgt(*max).any() {
+                    continue;
+                }
+                common_area += signed_area(&clip_convex(polygon, other_polygon)).abs();
+            }
+        }
+        let area = |polygons: &[(_, Vec<Vec2>)]| -> f32 {
+            polygons
+                .iter()
+                .map(|(_, polygon)| signed_area(polygon))
+                .sum()
+        };
+        NavMeshDiff {
+            added_area: (area(&theirs) - common_area).max(0.0),
+            removed_area: (area(&mine) - common_area).max(0.0),
+            common_area,
+        }
+    }
+
+    /// Extract the graph of polygons connected through their portals, to run custom graph algorithms like influence
+    /// maps, flood fills or territory analysis on the mesh.
+    pub fn adjacency(&self) -> NavMeshAdjacency {
+        let portals = self.portals();
+        let mut polygons = self
+            .polygon_vertices()
+            .map(|vertices| AdjacencyNode {
+                centroid: centroid(&vertices),
+                area: signed_area(&vertices),
+                portals: vec![],
+            })
+            .collect::<Vec<_>>();
+        for (index, portal) in portals.iter().enumerate() {
+            polygons[portal.polygons.0].portals.push(index);
+            polygons[portal.polygons.1].portals.push(index);
+        }
+        NavMeshAdjacency { polygons, portals }
+    }
+
+    /// List all portals of the mesh, each shared edge being listed once.
+    pub(crate) fn portals(&self) -> Vec<Portal> {
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::default();
+        let mut portals = vec![];
+        for (index, polygon) in self.mesh.polygons.iter().enumerate() {
+            for (a, b) in polygon
+                .vertices
+                .iter()
+                .zip(polygon.vertices.iter().cycle().skip(1))
+            {
+                let key = if a < b { (*a, *b) } else { (*b, *a) };
+                if let Some(other) = edges.remove(&key) {
+                    portals.push(Portal {
+                        polygons: (other, index),
+                        start: self.mesh.vertices[key.0 as usize].coords,
+                        end: self.mesh.vertices[key.1 as usize].coords,
+                    });
+                } else {
+                    edges.insert(key, index);
+                }
+            }
+        }
+        portals
+    }
+
+    /// List all edges of the mesh that are not shared between two polygons, oriented so that the mesh is on their left.
+    ///
+    /// They are computed on first use and cached.
+    pub(crate) fn boundary_edges(&self) -> &[[Vec2; 2]] {
+        self.boundary.get_or_init(|| {
+            let mut edges: HashMap<(u32, u32), (u32, u32)> = HashMap::default();
+            for polygon in &self.mesh.polygons {
+                let coords = |index: &u32| self.mesh.vertices[*index as usize].coords;
+                let clockwise = polygon
+                    .vertices
+                    .iter()
+                    .zip(polygon.vertices.iter().cycle().skip(1))
+                    .map(|(a, b)| coords(a).perp_dot(coords(b)))
+                    .sum::<f32>()
+                    < 0.0;
+                for (a, b) in polygon
+                    .vertices
+                    .iter()
+                    .zip(polygon.vertices.iter().cycle().skip(1))
+                {
+                    let key = if a < b { (*a, *b) } else { (*b, *a) };
+                    if edges.remove(&key).is_none() {
+                        edges.insert(key, if clockwise { (*b, *a) } else { (*a, *b) });
+                    }
+                }
+            }
+            edges
+                .into_values()
+                .map(|(a, b)| {
+                    [
+                        self.mesh.vertices[a as usize].coords,
+                        self.mesh.vertices[b as usize].coords,
+                    ]
+                })
+                .collect()
+        })
+    }
+
+    /// Index of the polygon containing `point`, in the mesh local space.
+    ///
+    /// Polygons are found with a grid index of their bounds, built on the first call.
+    pub fn polygon_at(&self, point: Vec2) -> Option<usize> {
+        self.indexed_polygon(point)
+    }
+
+    /// Island of each polygon. Polygons are in the same island if they are connected through portals.
+    ///
+    /// Islands are numbered from 0, in the order of their first polygon.
+    pub(crate) fn islands(&self) -> &[usize] {
+        self.islands.get_or_init(|| {
+            let mut parents = (0..self.mesh.polygons.len()).collect::<Vec<_>>();
+            for portal in self.portals() {
+                let a = root(&mut parents, portal.polygons.0);
+                let b = root(&mut parents, portal.polygons.1);
+                parents[a] = b;
+            }
+            let mut numbers = HashMap::default();
+            (0..parents.len())
+                .map(|index| {
+                    let root = root(&mut parents, index);
+                    let next = numbers.len();
+                    *numbers.entry(root).or_insert(next)
+                })
+                .collect()
+        })
+    }
+
+    /// Compute the data cached for queries, instead of computing it on the first query that needs it.
+    ///
+    /// This covers the regions used by [`NavMesh::is_reachable`], [`NavMesh::distance`] and [`NavMesh::regions`], and
+    /// the edges used by [`NavMesh::distance_to_boundary`], for this navmesh and its variants. The underlying Polyanya
+    /// mesh is already baked when built by the [`NavmeshUpdaterPlugin`](crate::prelude::NavmeshUpdaterPlugin).
+    pub fn precompute(&self) {
+        self.islands();
+        self.boundary_edges();
+        for variant in &self.variants {
+            variant.precompute();
+        }
+    }
+
+    /// Get the region containing `point`.
+    ///
+    /// Two points are in the same region if there is a path between them.
+    pub fn region_of(&self, point: Vec2) -> Option<RegionId> {
+        self.polygon_at(point)
+            .map(|polygon| RegionId(self.islands()[polygon]))
+    }
+
+    /// Get the region containing `point`.
+    ///
+    /// Inputs are transformed using the [`NavMesh::transform`].
+    pub fn transformed_region_of(&self, point: Vec3) -> Option<RegionId> {
+        self.region_of(self.transform.transform_point(point).xy())
+    }
+
+    /// List the regions of the mesh, parts of the mesh that are not connected to each other.
+    ///
+    /// Regions are ordered by their [`RegionId`].
+    pub fn regions(&self) -> Vec<RegionInfo> {
+        let mut regions: Vec<RegionInfo> = vec![];
+        for (polygon, island) in self.mesh.polygons.iter().zip(self.islands()) {
+            if *island == regions.len() {
+                regions.push(RegionInfo {
+                    id: RegionId(*island),
+                    area: 0.0,
+                    polygons: 0,
+                });
+            }
+            let vertices = polygon
+                .vertices
+                .iter()
+                .map(|index| self.mesh.vertices[*index as usize].coords)
+                .collect::<Vec<_>>();
+            let region = &mut regions[*island];
+            region.area += signed_area(&vertices).abs();
+            region.polygons += 1;
+        }
+        regions
+    }
+
+    /// Find the choke points of the walkable area: portals at most `max_width` wide that separate the walkable area
+    /// in two regions of at least `min_area` each, so that every path from one region to the other goes through them.
+    ///
+    /// A narrow passage that can be bypassed by another route is not a choke point, nor is the entrance of a dead end
+    /// smaller than `min_area`, like an alcove or a sliver along an obstacle. A corridor narrower than `max_width`
+    /// gives a choke point for each of its portals.
+    ///
+    /// Portals are returned in the mesh local space, use [`NavMesh::transform`] to convert them to world space.
+    pub fn choke_points(&self, max_width: f32, min_area: f32) -> Vec<Portal> {
+        let portals = self.portals();
+        let areas = self
+            .polygon_vertices()
+            .map(|vertices| signed_area(&vertices).abs())
+            .collect::<Vec<_>>();
+        let separated = bridges(&areas, &portals);
+        portals
+            .into_iter()
+            .zip(separated)
+            .filter(|(portal, separated)| {
+                portal.width() <= max_width
+                    && separated
+                        .map(|(first, second)| first.min(second) >= min_area)
+                        .unwrap_or(false)
+            })
+            .map(|(portal, _)| portal)
+            .collect()
+    }
+}
+
+/// For each portal, if it's a bridge of the graph of polygons, the areas on each side of it: removing it disconnects
+/// its two polygons. `areas` is the area of each polygon.
+fn bridges(areas: &[f32], portals: &[Portal]) -> Vec<Option<(f32, f32)>> {
+    let polygons = areas.len();
+    let mut neighbors = vec![vec![]; polygons];
+    for (index, portal) in portals.iter().enumerate() {
+        neighbors[portal.polygons.0].push((portal.polygons.1, index));
+        neighbors[portal.polygons.1].push((portal.polygons.0, index));
+    }
+    let mut bridges = vec![None; portals.len()];
+    // Order of discovery of each polygon, and the lowest order reachable from its subtree without going back through
+    // the portal it was reached from
+    let mut order = vec![usize::MAX; polygons];
+    let mut low = vec![0; polygons];
+    // Area of the subtree of each polygon, once it's visited
+    let mut subtree = areas.to_vec();
+    let mut next = 0;
+    for root in 0..polygons {
+        if order[root] != usize::MAX {
+            continue;
+        }
+        order[root] = next;
+        low[root] = next;
+        next += 1;
+        // Bridges found in this region, with the polygon on their side away from the root
+        let mut found = vec![];
+        // Polygon, portal it was reached from, and next neighbor to visit
+        let mut stack = vec![(root, usize::MAX, 0)];
+        while let Some((polygon, from, neighbor)) = stack.last_mut() {
+            let polygon = *polygon;
+            if let Some((other, portal)) = neighbors[polygon].get(*neighbor).copied() {
+                *neighbor += 1;
+                if portal == *from {
+                    continue;
+                }
+                if order[other] == usize::MAX {
+                    order[other] = next;
+                    low[other] = next;
+                    next += 1;
+                    stack.push((other, portal, 0));
+                } else {
+                    low[polygon] = low[polygon].min(order[other]);
+                }
+            } else {
+                let from = *from;
+                stack.pop();
+                if let Some((parent, _, _)) = stack.last() {
+                    low[*parent] = low[*parent].min(low[polygon]);
+                    subtree[*parent] += subtree[polygon];
+                    if low[polygon] > order[*parent] {
+                        found.push((from, polygon));
+                    }
+                }
+            }
+        }
+        for (portal, polygon) in found {
+            bridges[portal] = Some((subtree[polygon], subtree[root] - subtree[polygon]));
+        }
+    }
+    bridges
+}
+
+/// Root of `index` in a union-find forest.
+fn root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Navmesh triangulated from its edges, without merging polygons.
+    fn triangulated(edges: &[Vec2], obstacles: Vec<Vec<Vec2>>) -> NavMesh {
+        let mut triangulation = polyanya::Triangulation::from_outer_edges(edges);
+        triangulation.add_obstacles(obstacles);
+        NavMesh::from_polyanya_mesh(triangulation.as_navmesh())
+    }
+
+    fn rectangle(min: Vec2, max: Vec2) -> Vec<Vec2> {
+        vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+    }
+
+    #[test]
+    fn door_between_two_rooms_is_a_choke_point() {
+        // Two rooms linked by a corridor one unit wide
+        let navmesh = triangulated(
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 4.5),
+                Vec2::new(12.0, 4.5),
+                Vec2::new(12.0, 0.0),
+                Vec2::new(22.0, 0.0),
+                Vec2::new(22.0, 10.0),
+                Vec2::new(12.0, 10.0),
+                Vec2::new(12.0, 5.5),
+                Vec2::new(10.0, 5.5),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![],
+        );
+
+        let choke_points = navmesh.choke_points(1.5, 10.0);
+        assert!(!choke_points.is_empty());
+        for portal in choke_points {
+            assert!(portal.width() <= 1.5);
+            assert!((10.0..=12.0).contains(&portal.center().x));
+            assert!((4.5..=5.5).contains(&portal.center().y));
+        }
+    }
+
+    #[test]
+    fn passages_that_can_be_bypassed_are_not_choke_points() {
+        // A wall splitting the area in two, with a gap one unit wide at each end
+        let navmesh = triangulated(
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(11.0, 0.0),
+                Vec2::new(21.0, 0.0),
+                Vec2::new(21.0, 10.0),
+                Vec2::new(11.0, 10.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![rectangle(Vec2::new(10.0, 1.0), Vec2::new(11.0, 9.0))],
+        );
+        assert!(navmesh.portals().iter().any(|portal| portal.width() <= 1.5));
+
+        assert_eq!(navmesh.choke_points(1.5, 0.0), vec![]);
+    }
+
+    #[test]
+    fn entrance_of_a_small_alcove_is_not_a_choke_point() {
+        // A room with an alcove one unit wide and deep in its right wall
+        let navmesh = triangulated(
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 4.5),
+                Vec2::new(11.0, 4.5),
+                Vec2::new(11.0, 5.5),
+                Vec2::new(10.0, 5.5),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![],
+        );
+        assert!(!navmesh.choke_points(1.5, 0.0).is_empty());
+
+        assert_eq!(navmesh.choke_points(1.5, 10.0), vec![]);
+    }
+
+    #[test]
+    fn stats_of_a_square_with_a_hole() {
+        let navmesh = triangulated(
+            &rectangle(Vec2::ZERO, Vec2::splat(10.0)),
+            vec![rectangle(Vec2::splat(4.0), Vec2::splat(6.0))],
+        );
+
+        let stats = navmesh.stats();
+        assert_eq!(stats.vertices, 8);
+        assert_eq!(stats.polygons, 8);
+        assert!((stats.area - 96.0).abs() < 1e-3);
+        assert!((stats.boundary_length - 48.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn diff_of_carved_and_shifted_meshes() {
+        let square = triangulated(&rectangle(Vec2::ZERO, Vec2::splat(10.0)), vec![]);
+        let carved = triangulated(
+            &rectangle(Vec2::ZERO, Vec2::splat(10.0)),
+            vec![rectangle(Vec2::splat(4.0), Vec2::splat(6.0))],
+        );
+        let shifted = triangulated(
+            &rectangle(Vec2::new(5.0, 0.0), Vec2::new(15.0, 10.0)),
+            vec![],
+        );
+        let assert_diff = |diff: NavMeshDiff, added: f32, removed: f32, common: f32| {
+            assert!((diff.added_area - added).abs() < 1e-3, "{:?}", diff);
+            assert!((diff.removed_area - removed).abs() < 1e-3, "{:?}", diff);
+            assert!((diff.common_area - common).abs() < 1e-3, "{:?}", diff);
+        };
+
+        assert_diff(square.diff(&square), 0.0, 0.0, 100.0);
+        assert_diff(square.diff(&carved), 0.0, 4.0, 96.0);
+        assert_diff(carved.diff(&square), 4.0, 0.0, 96.0);
+        assert_diff(square.diff(&shifted), 50.0, 50.0, 50.0);
+    }
+
+    #[test]
+    fn bridges_of_a_graph() {
+        let portal = |a, b| Portal {
+            polygons: (a, b),
+            start: Vec2::ZERO,
+            end: Vec2::ZERO,
+        };
+        // A cycle of three polygons, linked to a fourth one
+        let portals = [portal(0, 1), portal(1, 2), portal(2, 0), portal(2, 3)];
+
+        assert_eq!(
+            bridges(&[1.0, 2.0, 3.0, 4.0], &portals),
+            vec![None, None, None, Some((4.0, 6.0))]
+        );
+    }
+}
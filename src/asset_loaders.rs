@@ -1,14 +1,15 @@
 //! Asset loaders that can load a [`NavMesh`] from a file
 
-use std::{error::Error, fmt::Display, sync::Arc};
+use std::{error::Error, fmt::Display};
 
 use bevy::{
     asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
-    prelude::{Transform, Vec3},
+    prelude::{Quat, Transform, Vec2, Vec3},
 };
-use polyanya::PolyanyaFile;
+use polyanya::{PolyanyaFile, Polygon, Vertex};
+use serde::{Deserialize, Serialize};
 
-use crate::NavMesh;
+use crate::{NavMesh, OffMeshLink};
 
 /// Error that can happen while reading a `NavMesh` from a file
 #[derive(Debug)]
@@ -17,6 +18,17 @@ pub enum NavMeshLoaderError {
     Io(std::io::Error),
     /// Error converting to a mesh
     MeshError(polyanya::MeshError),
+    /// The file was saved with a different version of the format, and no migration was able to read it
+    VersionMismatch {
+        /// Format version found in the file
+        found: u32,
+        /// Format version supported by this version of the crate
+        expected: u32,
+        /// Version of `vleue_navigator` that saved the file
+        crate_version: String,
+    },
+    /// The file is not a valid navmesh file
+    InvalidData(&'static str),
 }
 
 impl Display for NavMeshLoaderError {
@@ -24,6 +36,16 @@ impl Display for NavMeshLoaderError {
         match self {
             NavMeshLoaderError::Io(io_error) => write!(f, "IO error: {}", io_error),
             NavMeshLoaderError::MeshError(mesh_error) => write!(f, "Mesh error: {}", mesh_error),
+            NavMeshLoaderError::VersionMismatch {
+                found,
+                expected,
+                crate_version,
+            } => write!(
+                f,
+                "Version mismatch: found format version {} (saved by vleue_navigator {}), expected {}",
+                found, crate_version, expected
+            ),
+            NavMeshLoaderError::InvalidData(reason) => write!(f, "Invalid data: {}", reason),
         }
     }
 }
@@ -33,24 +55,97 @@ impl Error for NavMeshLoaderError {
         match self {
             NavMeshLoaderError::Io(io_error) => Some(io_error),
             NavMeshLoaderError::MeshError(mesh_error) => Some(mesh_error),
+            NavMeshLoaderError::VersionMismatch { .. } | NavMeshLoaderError::InvalidData(_) => None,
         }
     }
 }
+
+/// Settings of the [`NavMeshPolyanyaLoader`], that can be set in the `.meta` file of the mesh or with
+/// [`AssetServer::load_with_settings`](bevy::asset::AssetServer::load_with_settings).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct NavMeshPolyanyaLoaderSettings {
+    /// [Search delta](NavMesh::set_delta) of the navmesh. The default from Polyanya is kept when `None`.
+    pub search_delta: Option<f32>,
+    /// Number of times to merge neighbouring polygons after loading, stopping early when no polygons can be merged.
+    ///
+    /// Loading a triangle mesh with merge steps gives a mesh with fewer, larger polygons, which is faster to search.
+    pub merge_steps: usize,
+    /// [Transform](NavMesh::transform) of the navmesh.
+    #[serde(with = "transform_arrays")]
+    pub transform: Transform,
+}
+
+/// (De)serialize a [`Transform`] as arrays of its translation, rotation and scale, without requiring the `serialize`
+/// feature of Bevy.
+mod transform_arrays {
+    use bevy::prelude::{Quat, Transform, Vec3};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        transform: &Transform,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (
+            transform.translation.to_array(),
+            transform.rotation.to_array(),
+            transform.scale.to_array(),
+        )
+            .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Transform, D::Error> {
+        let (translation, rotation, scale) =
+            <([f32; 3], [f32; 4], [f32; 3])>::deserialize(deserializer)?;
+        Ok(Transform {
+            translation: Vec3::from_array(translation),
+            rotation: Quat::from_array(rotation),
+            scale: Vec3::from_array(scale),
+        })
+    }
+}
+
+impl Default for NavMeshPolyanyaLoaderSettings {
+    fn default() -> Self {
+        Self {
+            search_delta: None,
+            merge_steps: 0,
+            transform: Transform::IDENTITY,
+        }
+    }
+}
+
 /// Asset loader for a mesh in the `mesh 2` format with a `.polyanya.mesh` extension.
 ///
-/// See <https://github.com/vleue/polyanya/blob/main/meshes/format.txt> for format description.
+/// See <https://github.com/vleue/polyanya/blob/main/meshes/format.txt> for format description. The loaded navmesh can
+/// be configured with [`NavMeshPolyanyaLoaderSettings`]:
+///
+/// ```rust,no_run
+/// # use bevy::prelude::*;
+/// # use vleue_navigator::{asset_loaders::*, prelude::*};
+/// fn setup(asset_server: Res<AssetServer>) {
+///     let navmesh: Handle<NavMesh> = asset_server.load_with_settings(
+///         "aurora-merged.polyanya.mesh",
+///         |settings: &mut NavMeshPolyanyaLoaderSettings| {
+///             settings.search_delta = Some(0.1);
+///             settings.transform = Transform::from_scale(Vec3::splat(0.5));
+///         },
+///     );
+/// }
+/// ```
 #[derive(Default, Debug, Clone, Copy)]
 pub struct NavMeshPolyanyaLoader;
 
 impl AssetLoader for NavMeshPolyanyaLoader {
     type Asset = NavMesh;
-    type Settings = ();
+    type Settings = NavMeshPolyanyaLoaderSettings;
     type Error = NavMeshLoaderError;
 
     async fn load<'a>(
         &'a self,
         reader: &'a mut Reader<'_>,
-        _settings: &'a Self::Settings,
+        settings: &'a Self::Settings,
         _load_context: &'a mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
@@ -58,14 +153,23 @@ impl AssetLoader for NavMeshPolyanyaLoader {
             .read_to_end(&mut bytes)
             .await
             .map_err(NavMeshLoaderError::Io)?;
-        let navmesh = NavMesh {
-            mesh: Arc::new(
-                PolyanyaFile::from_bytes(bytes.as_slice())
-                    .try_into()
-                    .map_err(NavMeshLoaderError::MeshError)?,
-            ),
-            transform: Transform::from_scale(Vec3::splat(1.)),
-        };
+        let mut mesh: polyanya::Mesh = PolyanyaFile::from_bytes(bytes.as_slice())
+            .try_into()
+            .map_err(NavMeshLoaderError::MeshError)?;
+        if settings.merge_steps > 0 {
+            for _ in 0..settings.merge_steps {
+                if !mesh.merge_polygons() {
+                    break;
+                }
+            }
+            // Merging polygons invalidates the baked data used to find points in the mesh
+            mesh.bake();
+        }
+        if let Some(delta) = settings.search_delta {
+            mesh.set_delta(delta);
+        }
+        let mut navmesh = NavMesh::from_polyanya_mesh(mesh);
+        navmesh.set_transform(settings.transform);
         Ok(navmesh)
     }
 
@@ -73,3 +177,460 @@ impl AssetLoader for NavMeshPolyanyaLoader {
         &["polyanya.mesh"]
     }
 }
+
+/// Magic bytes at the start of a navmesh file.
+const MAGIC: &[u8; 4] = b"VNAV";
+
+/// Version of the navmesh file format written by [`NavMesh::to_bytes`].
+///
+/// It is increased every time the format changes in a way that can't be read by previous versions. Files store the
+/// vertices and polygons of the navmesh, so they don't depend on the version of Polyanya.
+pub const NAVMESH_FORMAT_VERSION: u32 = 1;
+
+/// Header of a navmesh file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavMeshFileHeader {
+    /// Version of the file format
+    pub format_version: u32,
+    /// Version of `vleue_navigator` that saved the file
+    pub crate_version: String,
+}
+
+/// Migration hook called when loading a navmesh file with an unsupported format version.
+///
+/// It receives the header of the file and the complete file content, and can return the migrated navmesh.
+pub type NavMeshMigration = fn(&NavMeshFileHeader, &[u8]) -> Result<NavMesh, NavMeshLoaderError>;
+
+impl NavMesh {
+    /// Serialize this navmesh in the `vleue_navigator` file format.
+    ///
+    /// The file starts with a header containing the [`NAVMESH_FORMAT_VERSION`], and the version of `vleue_navigator`
+    /// used. It can be read back with [`NavMesh::from_bytes`] or loaded with the [`NavMeshLoader`].
+    ///
+    /// The navmesh is saved with its transform, search delta, [agent radius](NavMesh::agent_radius),
+    /// [links](NavMesh::links) and [agent radius variants](NavMesh::for_agent_radius). The
+    /// [spatial index](NavMesh::build_spatial_index) is not saved.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&NAVMESH_FORMAT_VERSION.to_le_bytes());
+        write_str(&mut bytes, env!("CARGO_PKG_VERSION"));
+
+        let transform = self.transform;
+        for value in transform
+            .translation
+            .to_array()
+            .into_iter()
+            .chain(transform.rotation.to_array())
+            .chain(transform.scale.to_array())
+        {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        self.write_mesh(&mut bytes);
+        // Variants share the transform of the main mesh
+        bytes.extend_from_slice(&(self.variants.len() as u32).to_le_bytes());
+        for variant in &self.variants {
+            variant.write_mesh(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Write the search delta, agent radius, mesh and links of this navmesh, without its transform and variants.
+    fn write_mesh(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.mesh.delta().to_le_bytes());
+        bytes.extend_from_slice(&self.agent_radius.to_le_bytes());
+        bytes.extend_from_slice(&(self.mesh.vertices.len() as u32).to_le_bytes());
+        for vertex in &self.mesh.vertices {
+            bytes.extend_from_slice(&vertex.coords.x.to_le_bytes());
+            bytes.extend_from_slice(&vertex.coords.y.to_le_bytes());
+            bytes.extend_from_slice(&(vertex.polygons.len() as u32).to_le_bytes());
+            for polygon in &vertex.polygons {
+                bytes.extend_from_slice(&(*polygon as i32).to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&(self.mesh.polygons.len() as u32).to_le_bytes());
+        for polygon in &self.mesh.polygons {
+            bytes.extend_from_slice(&(polygon.vertices.len() as u32).to_le_bytes());
+            for vertex in &polygon.vertices {
+                bytes.extend_from_slice(&vertex.to_le_bytes());
+            }
+            bytes.push(polygon.is_one_way as u8);
+        }
+        bytes.extend_from_slice(&(self.links.len() as u32).to_le_bytes());
+        for link in &self.links {
+            for value in [
+                link.start.x,
+                link.start.y,
+                link.end.x,
+                link.end.y,
+                link.cost,
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.push(link.bidirectional as u8);
+        }
+    }
+
+    /// Read the header of a navmesh file, without reading the navmesh itself.
+    pub fn read_header(bytes: &[u8]) -> Result<NavMeshFileHeader, NavMeshLoaderError> {
+        let mut reader = ByteReader(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(NavMeshLoaderError::InvalidData("not a navmesh file"));
+        }
+        Ok(NavMeshFileHeader {
+            format_version: reader.u32()?,
+            crate_version: reader.string()?,
+        })
+    }
+
+    /// Deserialize a navmesh from the `vleue_navigator` file format, as written by [`NavMesh::to_bytes`].
+    ///
+    /// Returns [`NavMeshLoaderError::VersionMismatch`] if the file was written with a different format version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<NavMesh, NavMeshLoaderError> {
+        let header = Self::read_header(bytes)?;
+        if header.format_version != NAVMESH_FORMAT_VERSION {
+            return Err(NavMeshLoaderError::VersionMismatch {
+                found: header.format_version,
+                expected: NAVMESH_FORMAT_VERSION,
+                crate_version: header.crate_version,
+            });
+        }
+        let mut reader = ByteReader(bytes);
+        reader.take(MAGIC.len() + 4)?;
+        reader.string()?;
+
+        let mut values = [0.0; 10];
+        for value in values.iter_mut() {
+            *value = reader.f32()?;
+        }
+        let transform = Transform {
+            translation: Vec3::from_slice(&values[0..3]),
+            rotation: Quat::from_slice(&values[3..7]),
+            scale: Vec3::from_slice(&values[7..10]),
+        };
+
+        let mut navmesh = Self::read_mesh(&mut reader)?;
+        let variant_count = reader.u32()?;
+        for _ in 0..variant_count {
+            navmesh.variants.push(Self::read_mesh(&mut reader)?);
+        }
+        if !reader.0.is_empty() {
+            return Err(NavMeshLoaderError::InvalidData("trailing data"));
+        }
+        navmesh.set_transform(transform);
+        Ok(navmesh)
+    }
+
+    /// Read a navmesh as written by [`NavMesh::write_mesh`].
+    fn read_mesh(reader: &mut ByteReader) -> Result<NavMesh, NavMeshLoaderError> {
+        let (delta, agent_radius) = (reader.f32()?, reader.f32()?);
+
+        let vertex_count = reader.u32()?;
+        let mut vertices = Vec::new();
+        for _ in 0..vertex_count {
+            let coords = Vec2::new(reader.f32()?, reader.f32()?);
+            let polygon_count = reader.u32()?;
+            let polygons = (0..polygon_count)
+                .map(|_| reader.i32().map(|polygon| polygon as isize))
+                .collect::<Result<Vec<_>, _>>()?;
+            vertices.push(Vertex::new(coords, polygons));
+        }
+        let polygon_count = reader.u32()?;
+        let mut polygons = Vec::new();
+        for _ in 0..polygon_count {
+            let polygon_vertex_count = reader.u32()?;
+            let polygon_vertices = (0..polygon_vertex_count)
+                .map(|_| reader.u32())
+                .collect::<Result<Vec<_>, _>>()?;
+            let is_one_way = reader.take(1)?[0] != 0;
+            polygons.push(Polygon::new(polygon_vertices, is_one_way));
+        }
+        let link_count = reader.u32()?;
+        let mut links = Vec::new();
+        for _ in 0..link_count {
+            let mut values = [0.0; 5];
+            for value in values.iter_mut() {
+                *value = reader.f32()?;
+            }
+            links.push(OffMeshLink {
+                start: Vec2::new(values[0], values[1]),
+                end: Vec2::new(values[2], values[3]),
+                cost: values[4],
+                bidirectional: reader.take(1)?[0] != 0,
+            });
+        }
+        // Polyanya indexes vertices and polygons directly with those, an invalid index would panic while baking
+        if vertices.iter().any(|vertex| {
+            vertex
+                .polygons
+                .iter()
+                .any(|polygon| *polygon < -1 || *polygon >= polygon_count as isize)
+        }) {
+            return Err(NavMeshLoaderError::InvalidData(
+                "polygon index out of range",
+            ));
+        }
+        if polygons.iter().any(|polygon| {
+            polygon
+                .vertices
+                .iter()
+                .any(|vertex| *vertex >= vertex_count)
+        }) {
+            return Err(NavMeshLoaderError::InvalidData("vertex index out of range"));
+        }
+
+        let mut mesh =
+            polyanya::Mesh::new(vertices, polygons).map_err(NavMeshLoaderError::MeshError)?;
+        mesh.set_delta(delta);
+        let mut navmesh = NavMesh::from_polyanya_mesh(mesh);
+        navmesh.agent_radius = agent_radius;
+        navmesh.links = links;
+        Ok(navmesh)
+    }
+}
+
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], NavMeshLoaderError> {
+        if self.0.len() < count {
+            return Err(NavMeshLoaderError::InvalidData("unexpected end of file"));
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, NavMeshLoaderError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, NavMeshLoaderError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, NavMeshLoaderError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, NavMeshLoaderError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| NavMeshLoaderError::InvalidData("invalid string"))
+    }
+}
+
+/// Asset loader for a navmesh saved with [`NavMesh::to_bytes`], with a `.navmesh` extension.
+///
+/// Files saved with a different [`NAVMESH_FORMAT_VERSION`] fail to load with
+/// [`NavMeshLoaderError::VersionMismatch`], unless a migration is provided. To use a migration, register the loader
+/// after adding [`VleueNavigatorPlugin`](crate::VleueNavigatorPlugin):
+///
+/// ```rust,no_run
+/// # use bevy::prelude::*;
+/// # use vleue_navigator::{asset_loaders::*, prelude::*};
+/// fn migrate(header: &NavMeshFileHeader, bytes: &[u8]) -> Result<NavMesh, NavMeshLoaderError> {
+///     // read the old format, then build the navmesh from it
+///     Err(NavMeshLoaderError::InvalidData("unsupported navmesh file"))
+/// }
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, VleueNavigatorPlugin))
+///     .register_asset_loader(NavMeshLoader::with_migration(migrate));
+/// ```
+#[derive(Default, Debug, Clone, Copy)]
+pub struct NavMeshLoader {
+    migration: Option<NavMeshMigration>,
+}
+
+impl NavMeshLoader {
+    /// Create a loader that will call `migration` for files with an unsupported format version.
+    pub fn with_migration(migration: NavMeshMigration) -> Self {
+        Self {
+            migration: Some(migration),
+        }
+    }
+}
+
+impl AssetLoader for NavMeshLoader {
+    type Asset = NavMesh;
+    type Settings = ();
+    type Error = NavMeshLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(NavMeshLoaderError::Io)?;
+        match NavMesh::from_bytes(&bytes) {
+            Err(NavMeshLoaderError::VersionMismatch { .. }) if self.migration.is_some() => {
+                let header = NavMesh::read_header(&bytes)?;
+                (self.migration.unwrap())(&header, &bytes)
+            }
+            result => result,
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["navmesh"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polyanya::Trimesh;
+
+    use super::*;
+
+    fn navmesh() -> NavMesh {
+        let mut navmesh = NavMesh::from_polyanya_mesh(
+            Trimesh {
+                vertices: vec![
+                    Vec2::new(0., 0.),
+                    Vec2::new(2., 0.),
+                    Vec2::new(2., 2.),
+                    Vec2::new(0., 2.),
+                ],
+                triangles: vec![[0, 1, 2], [0, 2, 3]],
+            }
+            .try_into()
+            .unwrap(),
+        );
+        navmesh.set_transform(Transform::from_translation(Vec3::new(1.0, 2.0, 3.0)));
+        navmesh.agent_radius = 0.5;
+        navmesh
+            .add_link(OffMeshLink::new(Vec2::new(0.5, 0.5), Vec2::new(1.5, 1.5)).bidirectional());
+        navmesh
+    }
+
+    #[test]
+    fn navmesh_survives_serialization() {
+        let navmesh = navmesh();
+        let read = NavMesh::from_bytes(&navmesh.to_bytes()).unwrap();
+
+        assert_eq!(read.transform(), navmesh.transform());
+        assert_eq!(read.delta(), navmesh.delta());
+        assert_eq!(read.agent_radius(), navmesh.agent_radius());
+        assert_eq!(read.links(), navmesh.links());
+        assert_eq!(read.get().polygons, navmesh.get().polygons);
+        assert_eq!(read.get().vertices, navmesh.get().vertices);
+    }
+
+    #[test]
+    fn variants_survive_serialization() {
+        let mut navmesh = navmesh();
+        let mut variant = NavMesh::from_polyanya_mesh(
+            Trimesh {
+                vertices: vec![
+                    Vec2::new(0.5, 0.5),
+                    Vec2::new(1.5, 0.5),
+                    Vec2::new(1.0, 1.5),
+                ],
+                triangles: vec![[0, 1, 2]],
+            }
+            .try_into()
+            .unwrap(),
+        );
+        variant.agent_radius = 1.0;
+        navmesh.variants.push(variant);
+        navmesh.set_transform(navmesh.transform());
+        let read = NavMesh::from_bytes(&navmesh.to_bytes()).unwrap();
+
+        let variant = read.for_agent_radius(1.0).unwrap();
+        assert_eq!(variant.agent_radius(), 1.0);
+        assert_eq!(variant.transform(), navmesh.transform());
+        assert_eq!(variant.get().polygons, navmesh.variants[0].get().polygons);
+        assert_eq!(variant.get().vertices, navmesh.variants[0].get().vertices);
+        assert_eq!(read.to_bytes(), navmesh.to_bytes());
+    }
+
+    #[test]
+    fn other_format_version_is_rejected() {
+        let mut bytes = navmesh().to_bytes();
+        bytes[4..8].copy_from_slice(&(NAVMESH_FORMAT_VERSION + 1).to_le_bytes());
+
+        assert!(matches!(
+            NavMesh::from_bytes(&bytes),
+            Err(NavMeshLoaderError::VersionMismatch { found, .. }) if found == NAVMESH_FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn truncated_file_with_huge_counts_is_rejected() {
+        let bytes = navmesh().to_bytes();
+        let header = NavMesh::read_header(&bytes).unwrap();
+        let vertex_count_at = MAGIC.len() + 4 + 4 + header.crate_version.len() + 48;
+        let mut bytes = bytes[..vertex_count_at].to_vec();
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+
+        assert!(matches!(
+            NavMesh::from_bytes(&bytes),
+            Err(NavMeshLoaderError::InvalidData("unexpected end of file"))
+        ));
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        let bytes = navmesh().to_bytes();
+        let navmesh = navmesh();
+        let mesh = navmesh.get();
+
+        // Polygons are followed by the links and the variant count, the last polygon ends with its vertices, then its
+        // one way flag
+        let links_size = 4 + 21 * navmesh.links().len() + 4;
+        let mut bad_vertex = bytes.clone();
+        let at = bad_vertex.len() - links_size - 5;
+        bad_vertex[at..at + 4].copy_from_slice(&(mesh.vertices.len() as u32).to_le_bytes());
+        assert!(matches!(
+            NavMesh::from_bytes(&bad_vertex),
+            Err(NavMeshLoaderError::InvalidData("vertex index out of range"))
+        ));
+
+        // The polygon index of the last vertex is just before the polygon count and the polygons
+        let polygons_size = 4 + mesh
+            .polygons
+            .iter()
+            .map(|polygon| 4 + 4 * polygon.vertices.len() + 1)
+            .sum::<usize>();
+        let mut bad_polygon = bytes.clone();
+        let at = bad_polygon.len() - links_size - polygons_size - 4;
+        bad_polygon[at..at + 4].copy_from_slice(&(mesh.polygons.len() as i32).to_le_bytes());
+        assert!(matches!(
+            NavMesh::from_bytes(&bad_polygon),
+            Err(NavMeshLoaderError::InvalidData(
+                "polygon index out of range"
+            ))
+        ));
+
+        let mut negative_polygon = bytes;
+        negative_polygon[at..at + 4].copy_from_slice(&(-2i32).to_le_bytes());
+        assert!(matches!(
+            NavMesh::from_bytes(&negative_polygon),
+            Err(NavMeshLoaderError::InvalidData(
+                "polygon index out of range"
+            ))
+        ));
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let bytes = navmesh().to_bytes();
+        for len in [bytes.len() - 1, bytes.len() / 2, MAGIC.len() + 2] {
+            assert!(matches!(
+                NavMesh::from_bytes(&bytes[..len]),
+                Err(NavMeshLoaderError::InvalidData("unexpected end of file"))
+            ));
+        }
+    }
+}
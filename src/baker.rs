@@ -0,0 +1,173 @@
+//! Build navmeshes without an [`App`](bevy::app::App), for example to pre-bake them in a build pipeline.
+
+use bevy::prelude::*;
+use polyanya::Triangulation;
+
+use crate::{
+    obstacles::ObstacleSource,
+    updater::{build_from_polygons, NavMeshBuildError, NavMeshSettings},
+    NavMesh,
+};
+
+/// Build a navmesh from outer edges and obstacles, with the same process as the
+/// [`NavmeshUpdaterPlugin`](crate::prelude::NavmeshUpdaterPlugin), without an `App` or an asset server.
+///
+/// The baked navmesh can be saved with [`NavMesh::to_bytes`], and loaded at runtime with the
+/// [`NavMeshLoader`](crate::asset_loaders::NavMeshLoader).
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use vleue_navigator::{NavMeshBaker, prelude::NavMeshSettings};
+/// let navmesh = NavMeshBaker::new(NavMeshSettings {
+///     agent_radius: 0.5,
+///     ..default()
+/// })
+/// .with_outer_edges(&[
+///     Vec2::new(0.0, 0.0),
+///     Vec2::new(10.0, 0.0),
+///     Vec2::new(10.0, 10.0),
+///     Vec2::new(0.0, 10.0),
+/// ])
+/// .with_obstacle(vec![
+///     Vec2::new(4.0, 4.0),
+///     Vec2::new(6.0, 4.0),
+///     Vec2::new(6.0, 6.0),
+///     Vec2::new(4.0, 6.0),
+/// ])
+/// .bake()
+/// .unwrap();
+/// let bytes = navmesh.to_bytes();
+/// ```
+#[derive(Debug, Clone)]
+pub struct NavMeshBaker {
+    /// Settings used for the build. The outer edges are the [`fixed`](NavMeshSettings::fixed) triangulation, and must
+    /// also be set in [`outer_edges`](NavMeshSettings::outer_edges).
+    pub settings: NavMeshSettings,
+    /// Transform of the navmesh, used to project obstacles from [`ObstacleSource`]s.
+    pub transform: Transform,
+    obstacles: Vec<Vec<Vec2>>,
+}
+
+impl NavMeshBaker {
+    /// Create a new baker with the given settings.
+    pub fn new(settings: NavMeshSettings) -> Self {
+        Self {
+            settings,
+            transform: Transform::IDENTITY,
+            obstacles: vec![],
+        }
+    }
+
+    /// Set the outer edges of the navmesh, replacing the [`fixed`](NavMeshSettings::fixed) triangulation.
+    pub fn with_outer_edges(mut self, edges: &[Vec2]) -> Self {
+        self.settings.fixed = Triangulation::from_outer_edges(edges);
+        self.settings.outer_edges = edges.to_vec();
+        self
+    }
+
+    /// Set the transform of the navmesh.
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Add an obstacle polygon, in the mesh local space.
+    pub fn with_obstacle(mut self, polygon: Vec<Vec2>) -> Self {
+        self.add_obstacle(polygon);
+        self
+    }
+
+    /// Add an obstacle polygon, in the mesh local space.
+    pub fn add_obstacle(&mut self, polygon: Vec<Vec2>) {
+        if !polygon.is_empty() {
+            self.obstacles.push(polygon);
+        }
+    }
+
+    /// Add the polygons of an obstacle at `transform`, projected on the navmesh using its
+    /// [`transform`](NavMeshBaker::transform).
+    pub fn add_obstacle_source<T: ObstacleSource>(
+        &mut self,
+        obstacle: &T,
+        transform: &GlobalTransform,
+    ) -> Result<(), NavMeshBuildError> {
+        let polygons = self
+            .settings
+            .obstacle_polygons(obstacle, transform, &self.transform);
+        if polygons.iter().flatten().any(|point| !point.is_finite()) {
+            return Err(NavMeshBuildError::NonFiniteObstacle {
+                translation: transform.translation(),
+            });
+        }
+        polygons
+            .into_iter()
+            .for_each(|polygon| self.add_obstacle(polygon));
+        Ok(())
+    }
+
+    /// Build the navmesh, and its [agent radius variants](NavMeshSettings::agent_radius_variants).
+    pub fn bake(&self) -> Result<NavMesh, NavMeshBuildError> {
+        build_from_polygons(&self.obstacles, &self.settings, self.transform)
+    }
+}
+
+impl NavMesh {
+    /// Build a navmesh from obstacle polygons, in the mesh local space, without an `App`.
+    ///
+    /// This is the same process as the [`NavmeshUpdaterPlugin`](crate::prelude::NavmeshUpdaterPlugin), for games that
+    /// drive the generation themselves, for example when loading a saved level. The outer edges are the
+    /// [`fixed`](NavMeshSettings::fixed) triangulation of the settings, also set in their
+    /// [`outer_edges`](NavMeshSettings::outer_edges), and `transform` is the transform of the navmesh.
+    pub fn rebuild_with_obstacles(
+        settings: &NavMeshSettings,
+        obstacles: impl IntoIterator<Item = Vec<Vec2>>,
+        transform: Transform,
+    ) -> Result<NavMesh, NavMeshBuildError> {
+        let mut baker = NavMeshBaker::new(settings.clone()).with_transform(transform);
+        obstacles
+            .into_iter()
+            .for_each(|polygon| baker.add_obstacle(polygon));
+        baker.bake()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuild_with_obstacles_matches_the_baker() {
+        let outer_edges = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ];
+        let settings = NavMeshSettings {
+            fixed: Triangulation::from_outer_edges(&outer_edges),
+            outer_edges,
+            agent_radius: 0.5,
+            ..default()
+        };
+        let obstacle = vec![
+            Vec2::new(4.0, 4.0),
+            Vec2::new(6.0, 4.0),
+            Vec2::new(6.0, 6.0),
+            Vec2::new(4.0, 6.0),
+        ];
+
+        let rebuilt = NavMesh::rebuild_with_obstacles(
+            &settings,
+            [obstacle.clone(), vec![]],
+            Transform::IDENTITY,
+        )
+        .unwrap();
+        let baked = NavMeshBaker::new(settings)
+            .with_obstacle(obstacle)
+            .bake()
+            .unwrap();
+        assert_eq!(rebuilt.to_bytes(), baked.to_bytes());
+        assert!(!rebuilt.is_in_mesh(Vec2::new(5.0, 5.0)));
+        assert!(rebuilt.is_in_mesh(Vec2::new(2.0, 2.0)));
+    }
+}
@@ -0,0 +1,391 @@
+//! Ready-made navmesh aware behaviors for agents.
+//!
+//! Behaviors pick targets for a [`PlayerNavigator`] on the same entity, that moves the entity along the navmesh.
+
+use std::f32::consts::TAU;
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    click_to_move::{move_navigators, PlayerNavigator, PlayerNavigatorPlugin},
+    NavMesh,
+};
+
+/// Duration in seconds between two updates of the targets of [`FleeFrom`] and [`FollowEntity`].
+const REPLAN_INTERVAL: f32 = 0.5;
+
+/// Number of points tried when looking for a random point or a point to flee to.
+const SAMPLES: usize = 8;
+
+/// Plugin running the [`Wander`], [`FleeFrom`] and [`FollowEntity`] behaviors.
+///
+/// Also adds the [`PlayerNavigatorPlugin`] if needed, to move the entities.
+#[derive(Debug, Clone, Copy)]
+pub struct BehaviorsPlugin;
+
+impl Plugin for BehaviorsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<PlayerNavigatorPlugin>() {
+            app.add_plugins(PlayerNavigatorPlugin);
+        }
+        app.add_systems(Update, (wander, flee, follow).before(move_navigators));
+    }
+}
+
+/// Move to a random point of the navmesh around the entity at regular intervals.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Wander {
+    /// Maximum distance to the next point, in navmesh units.
+    pub radius: f32,
+    /// Duration in seconds between two points.
+    pub interval: f32,
+    since: f32,
+}
+
+impl Wander {
+    /// Wander to points up to `radius` away, changing every `interval` seconds.
+    pub fn new(radius: f32, interval: f32) -> Self {
+        Self {
+            radius,
+            interval,
+            since: interval,
+        }
+    }
+}
+
+/// Move away from another entity, to points as far as possible from it along the navmesh.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FleeFrom {
+    /// Entity to flee from.
+    pub threat: Entity,
+    /// Distance of the points to flee to, in navmesh units.
+    pub distance: f32,
+    since: f32,
+}
+
+impl FleeFrom {
+    /// Flee from `threat`, to points `distance` away.
+    pub fn new(threat: Entity, distance: f32) -> Self {
+        Self {
+            threat,
+            distance,
+            since: REPLAN_INTERVAL,
+        }
+    }
+}
+
+/// Follow another entity, stopping when close enough.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FollowEntity {
+    /// Entity to follow.
+    pub target: Entity,
+    /// Distance to keep from the followed entity, in navmesh units.
+    pub min_distance: f32,
+    since: f32,
+}
+
+impl FollowEntity {
+    /// Follow `target`, up to `min_distance`.
+    pub fn new(target: Entity, min_distance: f32) -> Self {
+        Self {
+            target,
+            min_distance,
+            since: REPLAN_INTERVAL,
+        }
+    }
+}
+
+/// Position of a [`GlobalTransform`] in the mesh local space.
+fn local_position(navmesh: &NavMesh, transform: &GlobalTransform) -> Vec3 {
+    navmesh.transform().transform_point(transform.translation())
+}
+
+/// Target for a [`PlayerNavigator`] from a point in the mesh local space, at the height of `position`.
+fn world_target(navmesh: &NavMesh, point: Vec2, position: Vec3) -> Vec3 {
+    navmesh
+        .inverse_transform()
+        .transform_point(point.extend(position.z))
+}
+
+fn wander(
+    time: Res<Time>,
+    navmeshes: Res<Assets<NavMesh>>,
+    mut agents: Query<(&GlobalTransform, &mut Wander, &mut PlayerNavigator)>,
+) {
+    for (transform, mut wander, mut navigator) in &mut agents {
+        wander.since += time.delta_seconds();
+        if wander.since < wander.interval {
+            continue;
+        }
+        let Some(navmesh) = navmeshes.get(&navigator.navmesh) else {
+            continue;
+        };
+        wander.since = 0.0;
+        let position = local_position(navmesh, transform);
+        let target = (0..SAMPLES)
+            .map(|_| {
+                let direction = Vec2::from_angle(fastrand::f32() * TAU);
+                position.xy() + direction * fastrand::f32().sqrt() * wander.radius
+            })
+            .find(|point| {
+                navmesh.is_in_mesh(*point) && navmesh.is_reachable(position.xy(), *point)
+            });
+        if let Some(target) = target {
+            navigator.set_target(world_target(navmesh, target, position));
+        }
+    }
+}
+
+fn flee(
+    time: Res<Time>,
+    navmeshes: Res<Assets<NavMesh>>,
+    threats: Query<&GlobalTransform>,
+    mut agents: Query<(&GlobalTransform, &mut FleeFrom, &mut PlayerNavigator)>,
+) {
+    for (transform, mut flee, mut navigator) in &mut agents {
+        flee.since += time.delta_seconds();
+        if flee.since < REPLAN_INTERVAL {
+            continue;
+        }
+        let (Some(navmesh), Ok(threat)) =
+            (navmeshes.get(&navigator.navmesh), threats.get(flee.threat))
+        else {
+            continue;
+        };
+        flee.since = 0.0;
+        let position = local_position(navmesh, transform);
+        let threat = navmesh
+            .transform()
+            .transform_point(threat.translation())
+            .xy();
+        // Keep the point that is the furthest from the threat along the navmesh, unreachable points are the safest
+        let offset = fastrand::f32() * TAU;
+        let target = (0..SAMPLES)
+            .filter_map(|i| {
+                let direction = Vec2::from_angle(offset + i as f32 / SAMPLES as f32 * TAU);
+                navmesh.closest_point(position.xy() + direction * flee.distance)
+            })
+            .filter(|point| navmesh.is_reachable(position.xy(), *point))
+            .map(|point| {
+                (
+                    point,
+                    navmesh.distance(threat, point).unwrap_or(f32::INFINITY),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(point, _)| point);
+        if let Some(target) = target {
+            navigator.set_target(world_target(navmesh, target, position));
+        }
+    }
+}
+
+fn follow(
+    time: Res<Time>,
+    navmeshes: Res<Assets<NavMesh>>,
+    targets: Query<&GlobalTransform>,
+    mut agents: Query<(&GlobalTransform, &mut FollowEntity, &mut PlayerNavigator)>,
+) {
+    for (transform, mut follow, mut navigator) in &mut agents {
+        follow.since += time.delta_seconds();
+        if follow.since < REPLAN_INTERVAL {
+            continue;
+        }
+        let (Some(navmesh), Ok(target)) = (
+            navmeshes.get(&navigator.navmesh),
+            targets.get(follow.target),
+        ) else {
+            continue;
+        };
+        follow.since = 0.0;
+        let position = local_position(navmesh, transform);
+        let target = navmesh
+            .transform()
+            .transform_point(target.translation())
+            .xy();
+        let Some(path) = navmesh
+            .closest_point(target)
+            .and_then(|target| navmesh.path(position.xy(), target))
+        else {
+            continue;
+        };
+        if path.length <= follow.min_distance {
+            navigator.stop();
+            continue;
+        }
+        // Aim `min_distance` short of the followed entity, so that the agent doesn't walk into it between two updates
+        let target = point_along(position.xy(), &path.path, path.length - follow.min_distance);
+        navigator.set_target(world_target(navmesh, target, position));
+    }
+}
+
+/// Point at `distance` along the path from `from` through `path`, or the end of the path if it's shorter.
+fn point_along(from: Vec2, path: &[Vec2], mut distance: f32) -> Vec2 {
+    let mut current = from;
+    for next in path {
+        let length = current.distance(*next);
+        if length >= distance {
+            return current.lerp(*next, distance / length.max(f32::EPSILON));
+        }
+        distance -= length;
+        current = *next;
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    /// World with a square navmesh 10 units wide, and time advanced past the replan interval.
+    fn world() -> (World, Handle<NavMesh>) {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(REPLAN_INTERVAL));
+        world.insert_resource(time);
+        let mut navmeshes = Assets::<NavMesh>::default();
+        let handle = navmeshes.add(NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![],
+        ));
+        world.insert_resource(navmeshes);
+        (world, handle)
+    }
+
+    /// Agent whose [`Transform`] is relative to a parent, away from its [`GlobalTransform`].
+    fn spawn_child_agent(
+        world: &mut World,
+        navmesh: &Handle<NavMesh>,
+        position: Vec2,
+        behavior: impl Component,
+    ) -> Entity {
+        world
+            .spawn((
+                Transform::from_xyz(1.0, 1.0, 0.0),
+                GlobalTransform::from_translation(position.extend(0.0)),
+                PlayerNavigator::new(navmesh.clone(), 1.0),
+                behavior,
+            ))
+            .id()
+    }
+
+    #[test]
+    fn wander_around_the_global_position() {
+        let (mut world, navmesh) = world();
+        let agent = spawn_child_agent(
+            &mut world,
+            &navmesh,
+            Vec2::new(8.0, 8.0),
+            Wander::new(1.0, 0.0),
+        );
+
+        world.run_system_once(wander);
+        let target = world
+            .get::<PlayerNavigator>(agent)
+            .unwrap()
+            .target()
+            .unwrap();
+        assert!(target.xy().distance(Vec2::new(8.0, 8.0)) <= 1.0);
+    }
+
+    #[test]
+    fn flee_away_from_the_threat() {
+        let (mut world, navmesh) = world();
+        let threat = world.spawn(GlobalTransform::from_xyz(1.0, 5.0, 0.0)).id();
+        let agent = spawn_child_agent(
+            &mut world,
+            &navmesh,
+            Vec2::new(3.0, 5.0),
+            FleeFrom::new(threat, 4.0),
+        );
+
+        world.run_system_once(flee);
+        let target = world
+            .get::<PlayerNavigator>(agent)
+            .unwrap()
+            .target()
+            .unwrap();
+        assert!(target.x > 3.0);
+    }
+
+    #[test]
+    fn follow_stops_close_to_the_target() {
+        let (mut world, navmesh) = world();
+        let target = world.spawn(GlobalTransform::from_xyz(8.5, 8.0, 0.0)).id();
+        let agent = spawn_child_agent(
+            &mut world,
+            &navmesh,
+            Vec2::new(8.0, 8.0),
+            FollowEntity::new(target, 1.0),
+        );
+        world
+            .get_mut::<PlayerNavigator>(agent)
+            .unwrap()
+            .set_target(Vec3::new(5.0, 5.0, 0.0));
+
+        world.run_system_once(follow);
+        assert_eq!(world.get::<PlayerNavigator>(agent).unwrap().target(), None);
+
+        *world.get_mut::<GlobalTransform>(target).unwrap() =
+            GlobalTransform::from_xyz(2.0, 2.0, 0.0);
+        world.run_system_once(follow);
+        let moving_to = world
+            .get::<PlayerNavigator>(agent)
+            .unwrap()
+            .target()
+            .unwrap();
+        // Stops one unit before the target, along the path
+        let expected = Vec2::new(2.0, 2.0) + Vec2::ONE.normalize();
+        assert!(moving_to.xy().distance(expected) < 1e-3);
+    }
+
+    #[test]
+    fn follow_keeps_its_distance_along_the_path() {
+        let (mut world, _) = world();
+        // A wall between the agent and the followed entity
+        let navmesh =
+            world
+                .resource_mut::<Assets<NavMesh>>()
+                .add(NavMesh::from_edge_and_obstacles(
+                    vec![
+                        Vec2::new(0.0, 0.0),
+                        Vec2::new(10.0, 0.0),
+                        Vec2::new(10.0, 10.0),
+                        Vec2::new(0.0, 10.0),
+                    ],
+                    vec![vec![
+                        Vec2::new(4.5, 0.0),
+                        Vec2::new(5.5, 0.0),
+                        Vec2::new(5.5, 8.0),
+                        Vec2::new(4.5, 8.0),
+                    ]],
+                ));
+        let target = world.spawn(GlobalTransform::from_xyz(6.0, 1.0, 0.0)).id();
+        let agent = spawn_child_agent(
+            &mut world,
+            &navmesh,
+            Vec2::new(4.0, 1.0),
+            FollowEntity::new(target, 2.0),
+        );
+
+        // Close in a straight line, but far around the wall
+        world.run_system_once(follow);
+        let moving_to = world
+            .get::<PlayerNavigator>(agent)
+            .unwrap()
+            .target()
+            .unwrap();
+        let expected =
+            Vec2::new(6.0, 1.0) + (Vec2::new(5.5, 8.0) - Vec2::new(6.0, 1.0)).normalize() * 2.0;
+        assert!(moving_to.xy().distance(expected) < 1e-3);
+    }
+}
@@ -0,0 +1,441 @@
+use std::{error::Error, fmt::Display, ops::Range};
+
+use bevy::prelude::*;
+use polyanya::Triangulation;
+
+use crate::{geometry::signed_area, prelude::NavMeshSettings, OffMeshLink};
+
+/// Misconfiguration of [`NavMeshSettings`], found by [`NavMeshSettings::validate`] or the
+/// [`NavMeshSettingsBuilder`].
+///
+/// Also present on navmesh entities whose status is [`NavMeshStatus::Invalid`](crate::prelude::NavMeshStatus::Invalid).
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum NavMeshSettingsError {
+    /// The outer edges have less than three points, points that are not finite, or no area.
+    InvalidOuterEdges {
+        /// Number of points of the outer edges.
+        points: usize,
+    },
+    /// A fixed obstacle has less than three points, or points that are not finite.
+    InvalidObstacle {
+        /// Index of the obstacle, in the order they were added to the builder.
+        index: usize,
+    },
+    /// The agent radius, or one of its variants, is negative or not finite.
+    InvalidAgentRadius {
+        /// The invalid radius.
+        radius: f32,
+    },
+    /// The agent height is negative or not finite.
+    InvalidAgentHeight {
+        /// The invalid height.
+        height: f32,
+    },
+    /// The quantization grid is not strictly positive and finite.
+    InvalidQuantization {
+        /// The invalid grid size.
+        grid: f32,
+    },
+    /// The build timeout is not strictly positive.
+    InvalidBuildTimeout {
+        /// The invalid timeout, in seconds.
+        seconds: f32,
+    },
+    /// An off-mesh link has a length of zero, a negative cost, or points that are not finite.
+    InvalidLink {
+        /// Index of the link in [`NavMeshSettings::links`].
+        index: usize,
+    },
+    /// The build gave no polygons, for example because obstacles cover the whole area. Set by the build, along with
+    /// [`NavMeshBuildError::EmptyMesh`](crate::prelude::NavMeshBuildError::EmptyMesh).
+    EmptyTriangulation,
+}
+
+impl Display for NavMeshSettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavMeshSettingsError::InvalidOuterEdges { points } => {
+                write!(f, "Outer edges with {} points are invalid", points)
+            }
+            NavMeshSettingsError::InvalidObstacle { index } => {
+                write!(f, "Fixed obstacle {} is invalid", index)
+            }
+            NavMeshSettingsError::InvalidAgentRadius { radius } => {
+                write!(f, "Agent radius {} is invalid", radius)
+            }
+            NavMeshSettingsError::InvalidAgentHeight { height } => {
+                write!(f, "Agent height {} is invalid", height)
+            }
+            NavMeshSettingsError::InvalidQuantization { grid } => {
+                write!(f, "Quantization grid {} is invalid", grid)
+            }
+            NavMeshSettingsError::InvalidBuildTimeout { seconds } => {
+                write!(f, "Build timeout of {} seconds is invalid", seconds)
+            }
+            NavMeshSettingsError::InvalidLink { index } => {
+                write!(f, "Off-mesh link {} is invalid", index)
+            }
+            NavMeshSettingsError::EmptyTriangulation => {
+                write!(f, "Triangulation has no polygons")
+            }
+        }
+    }
+}
+
+impl Error for NavMeshSettingsError {}
+
+fn is_valid_polygon(points: &[Vec2]) -> bool {
+    points.len() >= 3 && points.iter().all(|point| point.is_finite())
+}
+
+/// Check that a polygon is valid and not flat, so that it can be triangulated.
+pub(crate) fn has_area(points: &[Vec2]) -> bool {
+    is_valid_polygon(points) && signed_area(points).abs() > f32::EPSILON
+}
+
+impl NavMeshSettings {
+    /// Start building settings, checked when calling [`NavMeshSettingsBuilder::build`].
+    pub fn builder() -> NavMeshSettingsBuilder {
+        NavMeshSettingsBuilder::default()
+    }
+
+    /// Check these settings for values that would fail or give unexpected results during the build.
+    ///
+    /// The [`outer_edges`](NavMeshSettings::outer_edges) are checked, but not the obstacles of the
+    /// [`fixed`](NavMeshSettings::fixed) triangulation, as they need to be triangulated. A build that gives no polygons
+    /// sets the navmesh as [`Invalid`](crate::prelude::NavMeshStatus::Invalid), use the [`NavMeshSettingsBuilder`] to
+    /// also check the fixed obstacles.
+    pub fn validate(&self) -> Result<(), NavMeshSettingsError> {
+        if !has_area(&self.outer_edges) {
+            return Err(NavMeshSettingsError::InvalidOuterEdges {
+                points: self.outer_edges.len(),
+            });
+        }
+        if let Some(radius) = [self.agent_radius]
+            .iter()
+            .chain(&self.agent_radius_variants)
+            .find(|radius| !radius.is_finite() || **radius < 0.0)
+        {
+            return Err(NavMeshSettingsError::InvalidAgentRadius { radius: *radius });
+        }
+        if let Some(height) = self
+            .agent_height
+            .filter(|height| !height.is_finite() || *height < 0.0)
+        {
+            return Err(NavMeshSettingsError::InvalidAgentHeight { height });
+        }
+        if let Some(grid) = self
+            .quantization
+            .filter(|grid| !grid.is_finite() || *grid <= 0.0)
+        {
+            return Err(NavMeshSettingsError::InvalidQuantization { grid });
+        }
+        if let Some(seconds) = self
+            .build_timeout
+            .filter(|seconds| seconds.is_nan() || *seconds <= 0.0)
+        {
+            return Err(NavMeshSettingsError::InvalidBuildTimeout { seconds });
+        }
+        if let Some(index) = self.links.iter().position(|link| {
+            !link.start.is_finite()
+                || !link.end.is_finite()
+                || link.start == link.end
+                || link.cost.is_nan()
+                || link.cost < 0.0
+        }) {
+            return Err(NavMeshSettingsError::InvalidLink { index });
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`NavMeshSettings`], checking them when built.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use vleue_navigator::prelude::NavMeshSettings;
+/// let settings = NavMeshSettings::builder()
+///     .outer_edges(&[
+///         Vec2::new(0.0, 0.0),
+///         Vec2::new(10.0, 0.0),
+///         Vec2::new(10.0, 10.0),
+///         Vec2::new(0.0, 10.0),
+///     ])
+///     .agent_radius(0.5)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NavMeshSettingsBuilder {
+    settings: NavMeshSettings,
+    outer_edges: Vec<Vec2>,
+    obstacles: Vec<Vec<Vec2>>,
+}
+
+impl NavMeshSettingsBuilder {
+    /// Set the outer edges of the navmesh.
+    pub fn outer_edges(mut self, edges: &[Vec2]) -> Self {
+        self.outer_edges = edges.to_vec();
+        self
+    }
+
+    /// Add a fixed obstacle to the navmesh.
+    pub fn obstacle(mut self, obstacle: Vec<Vec2>) -> Self {
+        self.obstacles.push(obstacle);
+        self
+    }
+
+    /// Set the [agent radius](NavMeshSettings::agent_radius).
+    pub fn agent_radius(mut self, radius: f32) -> Self {
+        self.settings.agent_radius = radius;
+        self
+    }
+
+    /// Add an [agent radius variant](NavMeshSettings::agent_radius_variants).
+    pub fn agent_radius_variant(mut self, radius: f32) -> Self {
+        self.settings.agent_radius_variants.push(radius);
+        self
+    }
+
+    /// Set the [simplification](NavMeshSettings::simplify).
+    pub fn simplify(mut self, simplify: f32) -> Self {
+        self.settings.simplify = simplify;
+        self
+    }
+
+    /// Set the [number of merge steps](NavMeshSettings::merge_steps).
+    pub fn merge_steps(mut self, merge_steps: usize) -> Self {
+        self.settings.merge_steps = merge_steps;
+        self
+    }
+
+    /// Set the [default delta](NavMeshSettings::default_delta).
+    pub fn default_delta(mut self, delta: f32) -> Self {
+        self.settings.default_delta = delta;
+        self
+    }
+
+    /// Set the [build timeout](NavMeshSettings::build_timeout), in seconds.
+    pub fn build_timeout(mut self, seconds: f32) -> Self {
+        self.settings.build_timeout = Some(seconds);
+        self
+    }
+
+    /// Set the [quantization grid](NavMeshSettings::quantization).
+    pub fn quantization(mut self, grid: f32) -> Self {
+        self.settings.quantization = Some(grid);
+        self
+    }
+
+    /// Set the [up direction](NavMeshSettings::up_override) of the navmesh.
+    #[cfg(feature = "3d")]
+    pub fn up(mut self, up: Dir3) -> Self {
+        self.settings.up_override = Some(up);
+        self
+    }
+
+    /// Set if [cached data is precomputed](NavMeshSettings::precompute) during the build.
+    pub fn precompute(mut self, precompute: bool) -> Self {
+        self.settings.precompute = precompute;
+        self
+    }
+
+    /// Add an [off-mesh link](NavMeshSettings::links).
+    pub fn link(mut self, link: OffMeshLink) -> Self {
+        self.settings.links.push(link);
+        self
+    }
+
+    /// Set the [mask of obstacle layers](NavMeshSettings::obstacle_layers).
+    pub fn obstacle_layers(mut self, mask: u32) -> Self {
+        self.settings.obstacle_layers = mask;
+        self
+    }
+
+    /// Set the [range of heights of the obstacles](NavMeshSettings::obstacle_height_range) used by the navmesh.
+    pub fn obstacle_height_range(mut self, range: Range<f32>) -> Self {
+        self.settings.obstacle_height_range = Some(range);
+        self
+    }
+
+    /// Set the [height at which 3D obstacles are cut](NavMeshSettings::slice_height).
+    pub fn slice_height(mut self, height: f32) -> Self {
+        self.settings.slice_height = Some(height);
+        self
+    }
+
+    /// Set the [agent height](NavMeshSettings::agent_height).
+    pub fn agent_height(mut self, height: f32) -> Self {
+        self.settings.agent_height = Some(height);
+        self
+    }
+
+    /// Set if builds are [deterministic](NavMeshSettings::deterministic).
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.settings.deterministic = deterministic;
+        self
+    }
+
+    /// Set if a [spatial index](NavMeshSettings::spatial_index) is built with the navmesh.
+    pub fn spatial_index(mut self, spatial_index: bool) -> Self {
+        self.settings.spatial_index = spatial_index;
+        self
+    }
+
+    /// Check the settings, and build them.
+    pub fn build(self) -> Result<NavMeshSettings, NavMeshSettingsError> {
+        let Self {
+            mut settings,
+            outer_edges,
+            obstacles,
+        } = self;
+        settings.outer_edges = outer_edges;
+        if let Some(index) = obstacles
+            .iter()
+            .position(|obstacle| !is_valid_polygon(obstacle))
+        {
+            return Err(NavMeshSettingsError::InvalidObstacle { index });
+        }
+        settings.validate()?;
+        settings.fixed = Triangulation::from_outer_edges(&settings.outer_edges);
+        settings.fixed.add_obstacles(obstacles);
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> NavMeshSettingsBuilder {
+        NavMeshSettings::builder().outer_edges(&[
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ])
+    }
+
+    #[test]
+    fn valid_settings_are_built() {
+        let settings = square()
+            .obstacle(vec![
+                Vec2::new(4.0, 4.0),
+                Vec2::new(6.0, 4.0),
+                Vec2::new(6.0, 6.0),
+            ])
+            .agent_radius(0.5)
+            .agent_radius_variant(1.0)
+            .quantization(0.01)
+            .build()
+            .unwrap();
+        assert_eq!(settings.agent_radius, 0.5);
+        assert_eq!(settings.agent_radius_variants, vec![1.0]);
+    }
+
+    #[test]
+    fn invalid_outer_edges() {
+        assert_eq!(
+            NavMeshSettings::builder()
+                .outer_edges(&[Vec2::ZERO, Vec2::X])
+                .build()
+                .unwrap_err(),
+            NavMeshSettingsError::InvalidOuterEdges { points: 2 }
+        );
+        assert_eq!(
+            NavMeshSettings::builder()
+                .outer_edges(&[Vec2::ZERO, Vec2::X, Vec2::splat(f32::NAN)])
+                .build()
+                .unwrap_err(),
+            NavMeshSettingsError::InvalidOuterEdges { points: 3 }
+        );
+        assert_eq!(
+            NavMeshSettings::builder()
+                .outer_edges(&[Vec2::ZERO, Vec2::X, Vec2::X * 2.0])
+                .build()
+                .unwrap_err(),
+            NavMeshSettingsError::InvalidOuterEdges { points: 3 }
+        );
+        assert_eq!(
+            NavMeshSettings::default().validate().unwrap_err(),
+            NavMeshSettingsError::InvalidOuterEdges { points: 0 }
+        );
+    }
+
+    #[test]
+    fn invalid_obstacle() {
+        assert_eq!(
+            square()
+                .obstacle(vec![Vec2::ONE, Vec2::new(2.0, 1.0), Vec2::splat(2.0)])
+                .obstacle(vec![
+                    Vec2::ONE,
+                    Vec2::splat(f32::INFINITY),
+                    Vec2::splat(2.0)
+                ])
+                .build()
+                .unwrap_err(),
+            NavMeshSettingsError::InvalidObstacle { index: 1 }
+        );
+    }
+
+    #[test]
+    fn invalid_agent_radius() {
+        assert_eq!(
+            square().agent_radius(-1.0).build().unwrap_err(),
+            NavMeshSettingsError::InvalidAgentRadius { radius: -1.0 }
+        );
+        assert_eq!(
+            square()
+                .agent_radius_variant(f32::INFINITY)
+                .build()
+                .unwrap_err(),
+            NavMeshSettingsError::InvalidAgentRadius {
+                radius: f32::INFINITY
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_agent_height() {
+        assert_eq!(
+            square().agent_height(-2.0).build().unwrap_err(),
+            NavMeshSettingsError::InvalidAgentHeight { height: -2.0 }
+        );
+    }
+
+    #[test]
+    fn invalid_quantization() {
+        assert_eq!(
+            square().quantization(0.0).build().unwrap_err(),
+            NavMeshSettingsError::InvalidQuantization { grid: 0.0 }
+        );
+    }
+
+    #[test]
+    fn invalid_build_timeout() {
+        assert_eq!(
+            square().build_timeout(-1.0).build().unwrap_err(),
+            NavMeshSettingsError::InvalidBuildTimeout { seconds: -1.0 }
+        );
+    }
+
+    #[test]
+    fn invalid_link() {
+        let link = |start: Vec2, end: Vec2, cost: f32| OffMeshLink::new(start, end).with_cost(cost);
+        assert_eq!(
+            square()
+                .link(link(Vec2::ONE, Vec2::splat(2.0), 1.0))
+                .link(link(Vec2::ONE, Vec2::ONE, 1.0))
+                .build()
+                .unwrap_err(),
+            NavMeshSettingsError::InvalidLink { index: 1 }
+        );
+        assert_eq!(
+            square()
+                .link(link(Vec2::ONE, Vec2::splat(2.0), -1.0))
+                .build()
+                .unwrap_err(),
+            NavMeshSettingsError::InvalidLink { index: 0 }
+        );
+    }
+}
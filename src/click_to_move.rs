@@ -0,0 +1,292 @@
+//! High level "click to move" navigation.
+//!
+//! Entities with a [`PlayerNavigator`] move to the point of their navmesh clicked with the left mouse button, as seen
+//! by the camera marked with the `CameraMarker` component of the [`ClickToMovePlugin`].
+
+use std::marker::PhantomData;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::NavMesh;
+
+/// Plugin moving [`PlayerNavigator`]s to the clicked point of their navmesh.
+///
+/// `CameraMarker` is the component marking the camera used to find the clicked point.
+#[derive(Debug)]
+pub struct ClickToMovePlugin<CameraMarker: Component> {
+    marker: PhantomData<CameraMarker>,
+}
+
+impl<CameraMarker: Component> Default for ClickToMovePlugin<CameraMarker> {
+    fn default() -> Self {
+        Self {
+            marker: Default::default(),
+        }
+    }
+}
+
+impl<CameraMarker: Component> Plugin for ClickToMovePlugin<CameraMarker> {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<PlayerNavigatorPlugin>() {
+            app.add_plugins(PlayerNavigatorPlugin);
+        }
+        app.add_systems(Update, pick_target::<CameraMarker>.before(move_navigators));
+    }
+}
+
+/// Plugin moving [`PlayerNavigator`]s to their target, without picking targets.
+///
+/// Added by [`ClickToMovePlugin`]. Add it directly to set targets from your own systems.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerNavigatorPlugin;
+
+impl Plugin for PlayerNavigatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, move_navigators);
+    }
+}
+
+/// An entity moving on a navmesh to the point clicked by the player.
+///
+/// Its [`Transform`] is moved so that its [`GlobalTransform`] follows the path, the entity can have a parent.
+#[derive(Component, Debug, Clone)]
+pub struct PlayerNavigator {
+    /// Navmesh to move on.
+    pub navmesh: Handle<NavMesh>,
+    /// Speed of the entity, in navmesh units per second.
+    pub speed: f32,
+    /// The entity stops once it's closer than this distance to its target, in navmesh units.
+    pub stop_distance: f32,
+    /// Duration in seconds after which the path is computed again, to follow navmesh updates. `None` to never
+    /// compute it again.
+    pub repath_interval: Option<f32>,
+    target: Option<Vec3>,
+    path: Vec<Vec2>,
+    since_repath: f32,
+}
+
+impl PlayerNavigator {
+    /// Create a new navigator on `navmesh`, moving at `speed`.
+    pub fn new(navmesh: Handle<NavMesh>, speed: f32) -> Self {
+        Self {
+            navmesh,
+            speed,
+            stop_distance: 0.1,
+            repath_interval: Some(1.0),
+            target: None,
+            path: vec![],
+            since_repath: 0.0,
+        }
+    }
+
+    /// Current target of the entity, if it's moving.
+    pub fn target(&self) -> Option<Vec3> {
+        self.target
+    }
+
+    /// Move to `target`. The path is computed on the next update.
+    pub fn set_target(&mut self, target: Vec3) {
+        self.target = Some(target);
+        self.path.clear();
+    }
+
+    /// Stop moving.
+    pub fn stop(&mut self) {
+        self.target = None;
+        self.path.clear();
+    }
+}
+
+/// Intersection of `ray` with the plane of the navmesh, in the mesh local space.
+///
+/// Returns `None` if the ray is parallel to the plane, or if the plane is behind the ray origin.
+fn ray_hit(navmesh: &NavMesh, ray: Ray3d) -> Option<Vec2> {
+    let origin = navmesh.transform.transform_point(ray.origin);
+    let direction = navmesh
+        .transform
+        .transform_point(ray.origin + *ray.direction)
+        - origin;
+    if direction.z.abs() < f32::EPSILON {
+        return None;
+    }
+    let distance = -origin.z / direction.z;
+    if distance <= 0.0 {
+        return None;
+    }
+    Some((origin + direction * distance).truncate())
+}
+
+fn pick_target<CameraMarker: Component>(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<CameraMarker>>,
+    navmeshes: Res<Assets<NavMesh>>,
+    mut navigators: Query<&mut PlayerNavigator>,
+) {
+    if !mouse_buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(cursor) = primary_window
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    let Some(ray) = cameras
+        .iter()
+        .find_map(|(camera, transform)| camera.viewport_to_world(transform, cursor))
+    else {
+        return;
+    };
+    for mut navigator in &mut navigators {
+        let Some(navmesh) = navmeshes.get(&navigator.navmesh) else {
+            continue;
+        };
+        let Some(clicked) = ray_hit(navmesh, ray) else {
+            continue;
+        };
+        if let Some(target) = navmesh.closest_point(clicked) {
+            navigator.set_target(
+                navmesh
+                    .inverse_transform()
+                    .transform_point(target.extend(0.0)),
+            );
+        }
+    }
+}
+
+pub(crate) fn move_navigators(
+    time: Res<Time>,
+    navmeshes: Res<Assets<NavMesh>>,
+    mut navigators: Query<(&mut Transform, &GlobalTransform, &mut PlayerNavigator)>,
+) {
+    for (mut transform, global_transform, mut navigator) in &mut navigators {
+        let Some(target) = navigator.target else {
+            continue;
+        };
+        let Some(navmesh) = navmeshes.get(&navigator.navmesh) else {
+            continue;
+        };
+        // Move in the mesh local space, to keep the height of the entity above the navmesh
+        let position = navmesh
+            .transform
+            .transform_point(global_transform.translation());
+        let inner_target = navmesh.transform.transform_point(target).truncate();
+        if position.truncate().distance(inner_target) < navigator.stop_distance {
+            navigator.stop();
+            continue;
+        }
+
+        navigator.since_repath += time.delta_seconds();
+        if navigator.path.is_empty()
+            || navigator
+                .repath_interval
+                .map(|interval| navigator.since_repath >= interval)
+                .unwrap_or(false)
+        {
+            navigator.since_repath = 0.0;
+            match navmesh.path(position.truncate(), inner_target) {
+                Some(path) => navigator.path = path.path,
+                None => {
+                    navigator.stop();
+                    continue;
+                }
+            }
+        }
+
+        let mut step = navigator.speed * time.delta_seconds();
+        let mut current = position.truncate();
+        while let Some(next) = navigator.path.first().copied() {
+            let distance = current.distance(next);
+            if distance > step {
+                current += (next - current) / distance * step;
+                break;
+            }
+            step -= distance;
+            current = next;
+            navigator.path.remove(0);
+        }
+        let world = navmesh
+            .inverse_transform()
+            .transform_point(current.extend(position.z));
+        // The translation is relative to the parent of the entity, if any
+        let parent = global_transform.affine() * transform.compute_affine().inverse();
+        transform.translation = parent.inverse().transform_point3(world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn square() -> NavMesh {
+        NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn ray_hits_the_navmesh_plane_in_front_of_it() {
+        let navmesh = square();
+
+        let hit = ray_hit(
+            &navmesh,
+            Ray3d::new(Vec3::new(2.0, 3.0, 10.0), Vec3::new(1.0, 0.0, -1.0)),
+        )
+        .unwrap();
+        assert!(hit.distance(Vec2::new(12.0, 3.0)) < 1e-3, "{hit}");
+        // The plane is behind the ray
+        assert_eq!(
+            ray_hit(&navmesh, Ray3d::new(Vec3::new(2.0, 3.0, 10.0), Vec3::Z)),
+            None
+        );
+        // The ray starts on the plane
+        assert_eq!(
+            ray_hit(&navmesh, Ray3d::new(Vec3::new(2.0, 3.0, 0.0), Vec3::NEG_Z)),
+            None
+        );
+        assert_eq!(
+            ray_hit(&navmesh, Ray3d::new(Vec3::new(2.0, 3.0, 10.0), Vec3::X)),
+            None
+        );
+    }
+
+    #[test]
+    fn move_a_child_navigator_along_its_global_position() {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs(1));
+        world.insert_resource(time);
+        let mut navmeshes = Assets::<NavMesh>::default();
+        let navmesh = navmeshes.add(square());
+        world.insert_resource(navmeshes);
+        // Its parent is at (2, 4)
+        let mut navigator = PlayerNavigator::new(navmesh, 1.0);
+        navigator.set_target(Vec3::new(8.0, 5.0, 0.0));
+        let agent = world
+            .spawn((
+                Transform::from_xyz(1.0, 1.0, 0.0),
+                GlobalTransform::from_xyz(3.0, 5.0, 0.0),
+                navigator,
+            ))
+            .id();
+
+        world.run_system_once(move_navigators);
+        let translation = world.get::<Transform>(agent).unwrap().translation;
+        assert!(
+            translation.distance(Vec3::new(2.0, 1.0, 0.0)) < 1e-3,
+            "{translation}"
+        );
+    }
+}
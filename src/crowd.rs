@@ -0,0 +1,572 @@
+//! Local avoidance for agents following navmesh paths.
+//!
+//! Each [`CrowdAgent`] follows its waypoints, and a desired velocity avoiding other agents is computed every frame
+//! using reciprocal velocity obstacles. The result is stored in [`CrowdDesiredVelocity`], it's up to the user to
+//! move the agent.
+//!
+//! Agents can also be given a destination on a navmesh with [`CrowdAgent::navigate`], their path is then computed
+//! and kept up to date with the navmesh according to [`CrowdSettings::replan_on_rebuild`].
+
+use std::{f32::consts::TAU, marker::PhantomData};
+
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+
+use crate::{
+    geometry::contains_point,
+    obstacles::{ObstacleLayers, ObstacleSource},
+    updater::NavMeshSettings,
+    NavMesh,
+};
+
+/// Plugin computing the desired velocity of each [`CrowdAgent`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrowdPlugin;
+
+impl Plugin for CrowdPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CrowdSettings>().add_systems(
+            Update,
+            (plan_paths, compute_desired_velocities.after(plan_paths)),
+        );
+    }
+}
+
+/// Plugin pausing agents about to walk into an obstacle that is not yet part of their navmesh, for example while a
+/// [`NavMeshUpdateMode::Debounced`](crate::prelude::NavMeshUpdateMode::Debounced) rebuild is pending.
+///
+/// Only agents navigating with [`CrowdAgent::navigate`] are checked. Use the same `Obstacle` and `Marker` as the
+/// [`NavmeshUpdaterPlugin`](crate::prelude::NavmeshUpdaterPlugin) updating their navmesh. Obstacles are filtered with
+/// the [`NavMeshSettings`] of the entity holding that navmesh, so obstacles it ignores never pause agents. Requires
+/// the [`CrowdPlugin`].
+#[derive(Debug)]
+pub struct CrowdObstaclePlugin<Obstacle: ObstacleSource, Marker: Component = Obstacle> {
+    marker1: PhantomData<Marker>,
+    marker2: PhantomData<Obstacle>,
+}
+
+impl<Obstacle: ObstacleSource, Marker: Component> Default
+    for CrowdObstaclePlugin<Obstacle, Marker>
+{
+    fn default() -> Self {
+        Self {
+            marker1: Default::default(),
+            marker2: Default::default(),
+        }
+    }
+}
+
+impl<Obstacle: ObstacleSource, Marker: Component> Plugin for CrowdObstaclePlugin<Obstacle, Marker> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            pause_before_new_obstacles::<Marker, Obstacle>
+                .after(plan_paths)
+                .before(compute_desired_velocities),
+        );
+    }
+}
+
+/// When to compute again the path of an agent after its navmesh was rebuilt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplanPolicy {
+    /// After every rebuild.
+    Always,
+    /// After a rebuild, if the remaining path goes through a part that is no longer in the navmesh.
+    #[default]
+    IfPathInvalid,
+    /// Never, the path is only computed when calling [`CrowdAgent::navigate`].
+    Never,
+}
+
+/// Settings for the local avoidance.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct CrowdSettings {
+    /// Normal of the plane in which agents are moving. [`Dir3::Z`] for 2d games, [`Dir3::Y`] for most 3d games.
+    pub up: Dir3,
+    /// Agents further away than this distance are ignored.
+    pub neighbour_distance: f32,
+    /// Collisions further away in the future than this duration, in seconds, are ignored.
+    pub time_horizon: f32,
+    /// Number of directions sampled when looking for a velocity.
+    pub samples: usize,
+    /// Weight given to avoiding collisions, compared to keeping the preferred velocity.
+    pub avoidance_weight: f32,
+    /// When to compute again the path of a navigating agent after its navmesh was rebuilt.
+    pub replan_on_rebuild: ReplanPolicy,
+    /// Duration in seconds of movement checked ahead of an agent by the [`CrowdObstaclePlugin`].
+    pub look_ahead: f32,
+}
+
+impl Default for CrowdSettings {
+    fn default() -> Self {
+        Self {
+            up: Dir3::Z,
+            neighbour_distance: 10.0,
+            time_horizon: 2.0,
+            samples: 16,
+            avoidance_weight: 1.0,
+            replan_on_rebuild: ReplanPolicy::default(),
+            look_ahead: 0.5,
+        }
+    }
+}
+
+/// An agent taking part in the local avoidance.
+#[derive(Component, Debug, Clone, Default)]
+pub struct CrowdAgent {
+    /// Radius of the agent.
+    pub radius: f32,
+    /// Maximum speed of the agent.
+    pub max_speed: f32,
+    /// Remaining waypoints to follow, usually taken from a [`TransformedPath`](crate::TransformedPath).
+    /// Waypoints are removed once reached.
+    pub waypoints: Vec<Vec3>,
+    /// Navmesh and destination of the agent, set with [`CrowdAgent::navigate`].
+    pub navigation: Option<CrowdNavigation>,
+    /// Set when the agent stopped before an obstacle that is not yet part of its navmesh.
+    pub paused: bool,
+}
+
+/// Destination of an agent on a navmesh.
+#[derive(Debug, Clone)]
+pub struct CrowdNavigation {
+    /// Navmesh used to compute the path.
+    pub navmesh: Handle<NavMesh>,
+    /// Destination of the agent.
+    pub destination: Vec3,
+    /// [Version](NavMesh::version) of the navmesh the path was last checked against.
+    version: Option<u32>,
+}
+
+impl CrowdAgent {
+    /// Create a new agent with the given radius and maximum speed, without any waypoints.
+    pub fn new(radius: f32, max_speed: f32) -> Self {
+        Self {
+            radius,
+            max_speed,
+            waypoints: vec![],
+            navigation: None,
+            paused: false,
+        }
+    }
+
+    /// Set the waypoints to follow.
+    ///
+    /// This stops navigating to the destination set with [`CrowdAgent::navigate`].
+    pub fn follow(&mut self, waypoints: Vec<Vec3>) {
+        self.waypoints = waypoints;
+        self.navigation = None;
+    }
+
+    /// Navigate to `destination` on `navmesh`.
+    ///
+    /// The path is computed on the next update, using the navmesh built for the radius of the agent if available.
+    pub fn navigate(&mut self, navmesh: Handle<NavMesh>, destination: Vec3) {
+        self.navigation = Some(CrowdNavigation {
+            navmesh,
+            destination,
+            version: None,
+        });
+    }
+}
+
+/// Velocity an agent should use this frame to follow its path while avoiding other agents.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct CrowdDesiredVelocity(pub Vec3);
+
+fn plan_paths(
+    settings: Res<CrowdSettings>,
+    navmeshes: Res<Assets<NavMesh>>,
+    mut agents: Query<(&GlobalTransform, &mut CrowdAgent)>,
+) {
+    for (transform, mut agent) in &mut agents {
+        let agent = agent.as_mut();
+        agent.paused = false;
+        let Some(navigation) = agent.navigation.as_mut() else {
+            continue;
+        };
+        let Some(navmesh) = navmeshes.get(&navigation.navmesh) else {
+            continue;
+        };
+        let navmesh = navmesh.for_agent_radius(agent.radius).unwrap_or(navmesh);
+        let position = transform.translation();
+        let replan = match navigation.version {
+            None => true,
+            Some(version) if version == navmesh.version() => false,
+            Some(_) => match settings.replan_on_rebuild {
+                ReplanPolicy::Always => true,
+                ReplanPolicy::IfPathInvalid => {
+                    !is_path_valid(navmesh, position, &agent.waypoints, agent.radius)
+                }
+                ReplanPolicy::Never => false,
+            },
+        };
+        navigation.version = Some(navmesh.version());
+        if replan {
+            agent.waypoints = navmesh
+                .transformed_path(position, navigation.destination)
+                .map(|path| path.path)
+                .unwrap_or_default();
+        }
+    }
+}
+
+/// Check that the path from `position` through `waypoints` stays in the navmesh, sampling every `step`.
+fn is_path_valid(navmesh: &NavMesh, position: Vec3, waypoints: &[Vec3], step: f32) -> bool {
+    std::iter::once(&position)
+        .chain(waypoints)
+        .zip(waypoints)
+        .all(|(from, to)| {
+            let samples =
+                ((from.distance(*to) / step.max(f32::EPSILON)).ceil() as usize).clamp(1, 64);
+            (0..=samples)
+                .all(|i| navmesh.transformed_is_in_mesh(from.lerp(*to, i as f32 / samples as f32)))
+        })
+}
+
+fn pause_before_new_obstacles<Marker: Component, Obstacle: ObstacleSource>(
+    settings: Res<CrowdSettings>,
+    navmeshes: Res<Assets<NavMesh>>,
+    navmesh_settings: Query<(&Handle<NavMesh>, &NavMeshSettings)>,
+    obstacles: Query<(&GlobalTransform, &Obstacle, Option<&ObstacleLayers>), With<Marker>>,
+    mut agents: Query<(&GlobalTransform, &mut CrowdAgent)>,
+) {
+    let mut footprints: HashMap<AssetId<NavMesh>, Vec<Vec<Vec2>>> = HashMap::default();
+    for (transform, mut agent) in &mut agents {
+        let (Some(navigation), Some(next)) = (&agent.navigation, agent.waypoints.first()) else {
+            continue;
+        };
+        let Some(navmesh) = navmeshes.get(&navigation.navmesh) else {
+            continue;
+        };
+        // Navmeshes not built by an updater never carve obstacles
+        let Some((_, navmesh_settings)) = navmesh_settings
+            .iter()
+            .find(|(handle, _)| handle.id() == navigation.navmesh.id())
+        else {
+            continue;
+        };
+        let navmesh_transform = navmesh.transform();
+        let footprints = footprints
+            .entry(navigation.navmesh.id())
+            .or_insert_with(|| {
+                // Only the obstacles the navmesh will carve, with the same filters as the updater
+                obstacles
+                    .iter()
+                    .filter(|(transform, _, layers)| {
+                        navmesh_settings.uses_obstacle(&navmesh_transform, *layers, transform)
+                    })
+                    .flat_map(|(transform, obstacle, _)| {
+                        navmesh_settings.obstacle_polygons(obstacle, transform, &navmesh_transform)
+                    })
+                    .collect()
+            });
+
+        let position = transform.translation();
+        let ahead =
+            position + (*next - position).clamp_length_max(agent.max_speed * settings.look_ahead);
+        let from = navmesh_transform.transform_point(position).xy();
+        let to = navmesh_transform.transform_point(ahead).xy();
+        let samples =
+            ((from.distance(to) / agent.radius.max(f32::EPSILON)).ceil() as usize).clamp(1, 64);
+        // An obstacle is not yet carved if parts of it are still in the navmesh
+        agent.paused = (1..=samples)
+            .map(|i| from.lerp(to, i as f32 / samples as f32))
+            .any(|point| {
+                navmesh.is_in_mesh(point)
+                    && footprints
+                        .iter()
+                        .any(|footprint| contains_point(footprint, point))
+            });
+    }
+}
+
+struct AgentState {
+    entity: Entity,
+    position: Vec2,
+    velocity: Vec2,
+    preferred: Vec2,
+    radius: f32,
+    max_speed: f32,
+}
+
+fn compute_desired_velocities(
+    mut commands: Commands,
+    settings: Res<CrowdSettings>,
+    mut agents: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut CrowdAgent,
+        Option<&CrowdDesiredVelocity>,
+    )>,
+) {
+    let (axis_x, axis_y) = settings.up.any_orthonormal_pair();
+    let to_plane = |v: Vec3| Vec2::new(v.dot(axis_x), v.dot(axis_y));
+    let from_plane = |v: Vec2| axis_x * v.x + axis_y * v.y;
+
+    let states = agents
+        .iter_mut()
+        .map(|(entity, transform, mut agent, velocity)| {
+            let position = transform.translation();
+            while let Some(next) = agent.waypoints.first() {
+                if to_plane(*next - position).length() > agent.radius {
+                    break;
+                }
+                agent.waypoints.remove(0);
+            }
+            let preferred = agent
+                .waypoints
+                .first()
+                .filter(|_| !agent.paused)
+                .map(|next| to_plane(*next - position).clamp_length_max(agent.max_speed))
+                .unwrap_or_default();
+            AgentState {
+                entity,
+                position: to_plane(position),
+                velocity: velocity.map(|v| to_plane(v.0)).unwrap_or(preferred),
+                preferred,
+                radius: agent.radius,
+                max_speed: agent.max_speed,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for agent in &states {
+        let neighbours = states
+            .iter()
+            .filter(|other| {
+                other.entity != agent.entity
+                    && other.position.distance(agent.position) < settings.neighbour_distance
+            })
+            .collect::<Vec<_>>();
+
+        let velocity = if neighbours.is_empty() {
+            agent.preferred
+        } else {
+            candidate_velocities(agent, settings.samples)
+                .map(|candidate| {
+                    let time_to_collision = neighbours
+                        .iter()
+                        .filter_map(|other| {
+                            // Reciprocal velocity obstacle: each agent takes half the responsibility
+                            time_to_collision(
+                                other.position - agent.position,
+                                2.0 * candidate - agent.velocity - other.velocity,
+                                agent.radius + other.radius,
+                            )
+                        })
+                        .fold(f32::INFINITY, f32::min);
+                    let penalty = if time_to_collision < settings.time_horizon {
+                        settings.avoidance_weight / time_to_collision.max(MIN_TIME_TO_COLLISION)
+                    } else {
+                        0.0
+                    };
+                    (candidate, penalty + candidate.distance(agent.preferred))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(candidate, _)| candidate)
+                .unwrap_or(agent.preferred)
+        };
+
+        commands
+            .entity(agent.entity)
+            .insert(CrowdDesiredVelocity(from_plane(velocity)));
+    }
+}
+
+fn candidate_velocities(agent: &AgentState, samples: usize) -> impl Iterator<Item = Vec2> + '_ {
+    let samples = samples.max(1);
+    [agent.preferred, Vec2::ZERO]
+        .into_iter()
+        .chain((0..samples).flat_map(move |i| {
+            let direction = Vec2::from_angle(i as f32 * TAU / samples as f32);
+            [0.5, 1.0].map(|speed| direction * speed * agent.max_speed)
+        }))
+}
+
+/// Collisions sooner than this, in seconds, are penalized the same, so that the distance to the preferred velocity
+/// still ranks the candidates.
+const MIN_TIME_TO_COLLISION: f32 = 0.01;
+
+/// Time before two circles separated by `offset` and combined `radius` collide when moving at `relative_velocity`.
+///
+/// Circles already overlapping are colliding now, unless they are moving apart.
+fn time_to_collision(offset: Vec2, relative_velocity: Vec2, radius: f32) -> Option<f32> {
+    let c = offset.length_squared() - radius * radius;
+    let b = offset.dot(relative_velocity);
+    if c < 0.0 {
+        return (b > 0.0).then_some(0.0);
+    }
+    let a = relative_velocity.length_squared();
+    if a < f32::EPSILON || b <= 0.0 {
+        return None;
+    }
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    Some((b - discriminant.sqrt()) / a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::PrimitiveObstacle, VleueNavigatorPlugin};
+
+    /// An app with a navmesh from (0, 0) to (10, 10), and an agent at (1, 5) navigating to (9, 5).
+    fn setup(max_speed: f32) -> (App, Handle<NavMesh>, Entity) {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            CrowdPlugin,
+            CrowdObstaclePlugin::<PrimitiveObstacle>::default(),
+        ));
+        let navmesh = NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![],
+        );
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<NavMesh>>()
+            .add(navmesh);
+        app.world_mut()
+            .spawn((handle.clone(), NavMeshSettings::default()));
+        let mut agent = CrowdAgent::new(0.1, max_speed);
+        agent.navigate(handle.clone(), Vec3::new(9.0, 5.0, 0.0));
+        let agent = app
+            .world_mut()
+            .spawn((GlobalTransform::from_xyz(1.0, 5.0, 0.0), agent))
+            .id();
+        (app, handle, agent)
+    }
+
+    fn spawn_obstacle(app: &mut App, position: Vec3) -> Entity {
+        app.world_mut()
+            .spawn((
+                PrimitiveObstacle::Rectangle(Rectangle::new(1.0, 1.0)),
+                GlobalTransform::from_translation(position),
+            ))
+            .id()
+    }
+
+    fn paused(app: &App, agent: Entity) -> bool {
+        app.world().get::<CrowdAgent>(agent).unwrap().paused
+    }
+
+    #[test]
+    fn overlapping_agents_move_apart() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            CrowdPlugin,
+        ));
+        // Overlapping agents, each heading through the other
+        let agents = [(5.0, 9.0), (5.1, 1.0)].map(|(x, destination)| {
+            let mut agent = CrowdAgent::new(0.1, 1.0);
+            agent.follow(vec![Vec3::new(destination, 5.0, 0.0)]);
+            app.world_mut()
+                .spawn((GlobalTransform::from_xyz(x, 5.0, 0.0), agent))
+                .id()
+        });
+        app.update();
+
+        let velocity = |agent| app.world().get::<CrowdDesiredVelocity>(agent).unwrap().0;
+        let (first, second) = (velocity(agents[0]), velocity(agents[1]));
+        assert!(first.x <= 0.0, "{:?}", first);
+        assert!(second.x >= 0.0, "{:?}", second);
+    }
+
+    #[test]
+    fn pauses_before_obstacle_not_yet_carved() {
+        let (mut app, _, agent) = setup(10.0);
+        app.update();
+        assert!(!paused(&app, agent));
+
+        let obstacle = spawn_obstacle(&mut app, Vec3::new(4.0, 5.0, 0.0));
+        app.update();
+        assert!(paused(&app, agent));
+        assert_eq!(
+            app.world().get::<CrowdDesiredVelocity>(agent).unwrap().0,
+            Vec3::ZERO
+        );
+
+        // Obstacles the navmesh ignores are never carved, agents shouldn't wait for them
+        app.world_mut()
+            .entity_mut(obstacle)
+            .insert(ObstacleLayers::NONE);
+        app.update();
+        assert!(!paused(&app, agent));
+    }
+
+    #[test]
+    fn ignores_obstacles_out_of_height_range() {
+        let (mut app, _, agent) = setup(10.0);
+        let mut settings = app
+            .world_mut()
+            .query::<&mut NavMeshSettings>()
+            .single_mut(app.world_mut());
+        settings.obstacle_height_range = Some(-1.0..1.0);
+        spawn_obstacle(&mut app, Vec3::new(4.0, 5.0, 5.0));
+        app.update();
+        assert!(!paused(&app, agent));
+
+        spawn_obstacle(&mut app, Vec3::new(4.0, 5.0, 0.0));
+        app.update();
+        assert!(paused(&app, agent));
+    }
+
+    #[test]
+    fn look_ahead_limits_the_checked_distance() {
+        let (mut app, _, agent) = setup(2.0);
+        spawn_obstacle(&mut app, Vec3::new(6.0, 5.0, 0.0));
+        app.update();
+        // Only 1 unit is checked ahead of the agent
+        assert!(!paused(&app, agent));
+
+        app.world_mut().resource_mut::<CrowdSettings>().look_ahead = 5.0;
+        app.update();
+        assert!(paused(&app, agent));
+    }
+
+    /// Check if an agent following `waypoints` computes its path again after its navmesh is rebuilt.
+    fn replans(policy: ReplanPolicy, waypoints: Vec<Vec3>) -> bool {
+        let (mut app, handle, agent) = setup(10.0);
+        app.world_mut()
+            .resource_mut::<CrowdSettings>()
+            .replan_on_rebuild = policy;
+        app.update();
+        app.world_mut()
+            .get_mut::<CrowdAgent>(agent)
+            .unwrap()
+            .waypoints = waypoints.clone();
+        app.world_mut()
+            .resource_mut::<Assets<NavMesh>>()
+            .get_mut(&handle)
+            .unwrap()
+            .set_version(1);
+        app.update();
+        app.world().get::<CrowdAgent>(agent).unwrap().waypoints != waypoints
+    }
+
+    #[test]
+    fn replan_policy_after_rebuild() {
+        let valid = vec![Vec3::new(5.0, 8.0, 0.0), Vec3::new(9.0, 5.0, 0.0)];
+        let invalid = vec![Vec3::new(5.0, 20.0, 0.0), Vec3::new(9.0, 5.0, 0.0)];
+
+        assert!(replans(ReplanPolicy::Always, valid.clone()));
+        assert!(replans(ReplanPolicy::Always, invalid.clone()));
+        assert!(!replans(ReplanPolicy::IfPathInvalid, valid.clone()));
+        assert!(replans(ReplanPolicy::IfPathInvalid, invalid.clone()));
+        assert!(!replans(ReplanPolicy::Never, valid));
+        assert!(!replans(ReplanPolicy::Never, invalid));
+    }
+}
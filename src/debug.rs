@@ -0,0 +1,149 @@
+use bevy::{color::palettes, prelude::*};
+
+use crate::{
+    obstacles::{ObstacleLayers, ObstacleSource, TransformProvider},
+    updater::{mesh_transform, navmesh_transform, NavMeshSettings},
+    NavMesh,
+};
+
+/// Draw the navmesh of this entity with gizmos.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct NavMeshDebug {
+    /// Color of the edges on the boundary of the navmesh.
+    pub boundary_color: Color,
+    /// Color of the edges between two polygons, `None` to not draw them.
+    pub inner_color: Option<Color>,
+    /// Radius of the dots drawn on vertices, `None` to not draw them.
+    pub vertex_radius: Option<f32>,
+    /// Length of the axes of the navmesh transform, `None` to not draw them.
+    pub axes_length: Option<f32>,
+}
+
+impl Default for NavMeshDebug {
+    fn default() -> Self {
+        Self {
+            boundary_color: palettes::css::RED.into(),
+            inner_color: Some(palettes::css::BLUE.into()),
+            vertex_radius: None,
+            axes_length: None,
+        }
+    }
+}
+
+impl NavMeshDebug {
+    /// Draw only the boundary of the navmesh, with the given color.
+    pub fn boundary(color: Color) -> Self {
+        Self {
+            boundary_color: color,
+            inner_color: None,
+            ..default()
+        }
+    }
+}
+
+pub(crate) fn display_navmesh(
+    mut gizmos: Gizmos,
+    navmeshes: Res<Assets<NavMesh>>,
+    debugs: Query<(&Handle<NavMesh>, &NavMeshDebug)>,
+) {
+    for (handle, debug) in &debugs {
+        let Some(navmesh) = navmeshes.get(handle) else {
+            continue;
+        };
+        let to_world = navmesh.transform().compute_affine().inverse();
+        let to_world_point = |point: Vec2| to_world.transform_point3(point.extend(0.0));
+
+        for &[start, end] in navmesh.boundary_edges() {
+            gizmos.line(
+                to_world_point(start),
+                to_world_point(end),
+                debug.boundary_color,
+            );
+        }
+        if let Some(color) = debug.inner_color {
+            for portal in navmesh.portals() {
+                gizmos.line(
+                    to_world_point(portal.start),
+                    to_world_point(portal.end),
+                    color,
+                );
+            }
+        }
+        if let Some(radius) = debug.vertex_radius {
+            let normal = Dir3::new(to_world.transform_vector3(Vec3::Z)).unwrap_or(Dir3::Z);
+            for vertex in &navmesh.get().vertices {
+                gizmos.circle(
+                    to_world_point(vertex.coords),
+                    normal,
+                    radius,
+                    debug.boundary_color,
+                );
+            }
+        }
+        if let Some(length) = debug.axes_length {
+            gizmos.axes(GlobalTransform::from(to_world), length);
+        }
+    }
+}
+
+/// Draw the polygons of the obstacles as they are projected on this navmesh, with gizmos.
+///
+/// Polygons are the ones used to build the navmesh, cut at the [`slice_height`](NavMeshSettings::slice_height) and
+/// [`agent_height`](NavMeshSettings::agent_height), before being inflated by the agent radius. They are drawn on the
+/// plane of the navmesh, which helps finding mistakes in the transforms or the up axis of obstacles.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DebugObstaclePolygons {
+    /// Color of the polygons.
+    pub color: Color,
+}
+
+impl Default for DebugObstaclePolygons {
+    fn default() -> Self {
+        Self {
+            color: palettes::css::ORANGE.into(),
+        }
+    }
+}
+
+type DebugNavMeshQuery<'world, 'state, 'a, 'b, 'c> = Query<
+    'world,
+    'state,
+    (
+        &'a NavMeshSettings,
+        (Ref<'b, Transform>, Option<Ref<'b, GlobalTransform>>),
+        &'c DebugObstaclePolygons,
+    ),
+>;
+
+pub(crate) fn display_obstacle_polygons<
+    Marker: Component,
+    Obstacle: ObstacleSource,
+    Frame: TransformProvider,
+>(
+    mut gizmos: Gizmos,
+    navmeshes: DebugNavMeshQuery,
+    obstacles: Query<(&Frame, &Obstacle, Option<&ObstacleLayers>), With<Marker>>,
+) {
+    for (settings, transform, debug) in &navmeshes {
+        let transform = mesh_transform(settings, navmesh_transform(&transform).0);
+        let to_world = transform.compute_affine().inverse();
+        for (frame, obstacle, layers) in &obstacles {
+            let obstacle_transform = frame.global_transform();
+            if !settings.uses_obstacle(&transform, layers, &obstacle_transform) {
+                continue;
+            }
+            for polygon in settings.obstacle_polygons(obstacle, &obstacle_transform, &transform) {
+                let Some(first) = polygon.first() else {
+                    continue;
+                };
+                gizmos.linestrip(
+                    polygon
+                        .iter()
+                        .chain([first])
+                        .map(|point| to_world.transform_point3(point.extend(0.0))),
+                    debug.color,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,740 @@
+use std::{error::Error, fmt::Display, sync::Arc};
+
+use bevy::math::{Rect, Vec2};
+
+use crate::{
+    geometry, obstacles::offset::offset_polygon, spatial::GridIndex, updater::INFLATION_QUALITY,
+    NavMesh,
+};
+
+/// Tolerance to weld vertices, and to find vertices on the edges of polygons, after an edit.
+const EDIT_TOLERANCE: f32 = 1e-4;
+
+/// Number of times to merge polygons after an edit or an extraction, stopping early when no polygons can be merged.
+const MERGE_STEPS: usize = 3;
+
+/// Error that can happen while merging navmeshes with [`NavMesh::merge`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeError {
+    /// No navmesh to merge
+    Empty,
+    /// The navmesh at this index doesn't have the same transform as the first one
+    TransformMismatch(usize),
+    /// The stitch at this index doesn't match vertices from at least two navmeshes
+    StitchMismatch {
+        /// Index of the stitch
+        index: usize,
+        /// Stitch segment that didn't match
+        segment: [Vec2; 2],
+    },
+    /// The merged polygons don't form a valid mesh
+    InvalidMesh,
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::Empty => write!(f, "No navmesh to merge"),
+            MergeError::TransformMismatch(index) => {
+                write!(f, "Navmesh {} has a different transform", index)
+            }
+            MergeError::StitchMismatch { index, segment } => write!(
+                f,
+                "Stitch {} ({} - {}) doesn't match at least two navmeshes",
+                index, segment[0], segment[1]
+            ),
+            MergeError::InvalidMesh => write!(f, "Merged polygons don't form a valid mesh"),
+        }
+    }
+}
+
+impl Error for MergeError {}
+
+/// Error that can happen while editing a navmesh with [`NavMesh::carve`] or [`NavMesh::fill`], or extracting a part
+/// of it with [`NavMesh::extract_region`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditError {
+    /// The region is not a convex polygon with at least 3 points
+    InvalidRegion,
+    /// The edited polygons don't form a valid mesh, for example when carving the whole navmesh
+    InvalidMesh,
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::InvalidRegion => write!(f, "Region is not a convex polygon"),
+            EditError::InvalidMesh => write!(f, "Edited polygons don't form a valid mesh"),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+fn bounds(polygon: &[Vec2]) -> Rect {
+    polygon.iter().fold(
+        Rect {
+            min: Vec2::splat(f32::INFINITY),
+            max: Vec2::splat(f32::NEG_INFINITY),
+        },
+        |bounds, point| bounds.union_point(*point),
+    )
+}
+
+fn overlaps(a: Rect, b: Rect) -> bool {
+    a.min.cmple(b.max).all() && b.min.cmple(a.max).all()
+}
+
+/// Insert the `points` that are on the edges of `polygon`, and split it in triangles around its center if there were
+/// some, as they are aligned with their neighbours.
+fn split_and_fan(polygon: Vec<Vec2>, points: &[Vec2], tolerance: f32) -> Vec<Vec<Vec2>> {
+    let split = geometry::split_edges(&polygon, points, tolerance);
+    if split.len() == polygon.len() {
+        return vec![polygon];
+    }
+    let center = geometry::centroid(&split);
+    split
+        .iter()
+        .zip(split.iter().cycle().skip(1))
+        .map(|(a, b)| vec![center, *a, *b])
+        .collect()
+}
+
+/// Part of the segment from `c` to `d` that is along the segment from `a` to `b`, closer than `tolerance`.
+fn overlapping_segment(a: Vec2, b: Vec2, c: Vec2, d: Vec2, tolerance: f32) -> Option<[Vec2; 2]> {
+    let direction = (b - a).normalize_or_zero();
+    if direction == Vec2::ZERO
+        || direction.perp_dot(c - a).abs() > tolerance
+        || direction.perp_dot(d - a).abs() > tolerance
+    {
+        return None;
+    }
+    let (from, to) = {
+        let (tc, td) = (direction.dot(c - a), direction.dot(d - a));
+        (tc.min(td).max(0.0), tc.max(td).min(a.distance(b)))
+    };
+    (to - from > tolerance).then(|| [a + direction * from, a + direction * to])
+}
+
+impl NavMesh {
+    /// Extract the part of the navmesh inside `region` as a standalone [`NavMesh`].
+    ///
+    /// `region` must be a convex polygon, in the mesh local space, otherwise this fails with
+    /// [`EditError::InvalidRegion`]. The new navmesh keeps the same [`NavMesh::transform`],
+    /// [`delta`](polyanya::Mesh::delta) and [agent radius](NavMesh::agent_radius), so it can be used with the same world
+    /// coordinates. Its [variants](NavMesh::for_agent_radius) are extracted from the variants of this navmesh, and
+    /// the [links](crate::OffMeshLink) with both ends inside the region are kept.
+    ///
+    /// Fails with [`EditError::InvalidMesh`] if the region doesn't intersect the navmesh.
+    pub fn extract_region(&self, region: &[Vec2]) -> Result<NavMesh, EditError> {
+        let region = geometry::counter_clockwise(region.to_vec());
+        if !geometry::is_convex(&region) {
+            return Err(EditError::InvalidRegion);
+        }
+        let mut navmesh = self.extract_mesh(&region).ok_or(EditError::InvalidMesh)?;
+        // Larger agents can have nothing left in the region
+        navmesh.variants = self
+            .variants
+            .iter()
+            .filter_map(|variant| variant.extract_mesh(&region))
+            .collect();
+        navmesh.set_transform(self.transform);
+        Ok(navmesh)
+    }
+
+    /// Extract the part of the navmesh inside the rectangle between `min` and `max` as a standalone [`NavMesh`].
+    ///
+    /// See [`NavMesh::extract_region`] for details.
+    pub fn extract_rect(&self, min: Vec2, max: Vec2) -> Result<NavMesh, EditError> {
+        self.extract_region(&[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)])
+    }
+
+    /// Clip the polygons of this navmesh, without its variants, to the convex counter clockwise `region`, keeping the
+    /// links inside it.
+    fn extract_mesh(&self, region: &[Vec2]) -> Option<NavMesh> {
+        let polygons = self
+            .polygon_vertices()
+            .map(|polygon| geometry::clip_convex(&polygon, region));
+        let mut mesh = geometry::mesh_from_convex_polygons(polygons)?;
+        for _ in 0..MERGE_STEPS {
+            if !mesh.merge_polygons() {
+                break;
+            }
+        }
+        mesh.bake();
+        mesh.set_delta(self.mesh.delta());
+
+        let mut navmesh = NavMesh::from_polyanya_mesh(mesh);
+        navmesh.agent_radius = self.agent_radius;
+        navmesh.links = self
+            .links
+            .iter()
+            .filter(|link| {
+                geometry::contains_point(region, link.start)
+                    && geometry::contains_point(region, link.end)
+            })
+            .copied()
+            .collect();
+        Some(navmesh)
+    }
+
+    /// Merge several navmeshes into one.
+    ///
+    /// All navmeshes must have the same [`NavMesh::transform`]. Vertices closer than `tolerance` are welded together,
+    /// so that polygons along the seams between navmeshes are connected.
+    ///
+    /// `stitches` are the segments where navmeshes are expected to connect. Each end of a stitch must be a vertex of
+    /// at least two of the merged navmeshes, otherwise merging fails with [`MergeError::StitchMismatch`]. Along the
+    /// stitches, vertices of a navmesh are inserted in the edges of the others, so that seams connect even when their
+    /// vertices don't match. Polygons collapsed by welding are dropped.
+    ///
+    /// The merged navmesh keeps the [agent radius](NavMesh::agent_radius) of the first navmesh, and the
+    /// [links](crate::OffMeshLink) of all of them. Its [variants](NavMesh::for_agent_radius) are merged from the
+    /// variants with the same radius in all navmeshes: variants missing from one of the navmeshes, or that can't be
+    /// merged, are dropped.
+    pub fn merge(
+        navmeshes: &[&NavMesh],
+        stitches: &[[Vec2; 2]],
+        tolerance: f32,
+    ) -> Result<NavMesh, MergeError> {
+        Self::merge_with(navmeshes, stitches, tolerance, false)
+    }
+
+    /// Merge several navmeshes into one, stitching them where their boundary edges overlap.
+    ///
+    /// The stitches are found with [`NavMesh::find_stitches`], so they don't need to be listed by hand. Declared
+    /// `stitches` are still checked like in [`NavMesh::merge`], and kept when they are not found.
+    pub fn merge_with_stitch_discovery(
+        navmeshes: &[&NavMesh],
+        stitches: &[[Vec2; 2]],
+        tolerance: f32,
+    ) -> Result<NavMesh, MergeError> {
+        Self::merge_with(navmeshes, stitches, tolerance, true)
+    }
+
+    /// Find the stitches between navmeshes: the parts of the boundary edges of different navmeshes that overlap, closer
+    /// than `tolerance`, with the navmeshes on opposite sides.
+    ///
+    /// All navmeshes must have the same [`NavMesh::transform`], stitches are in their local space.
+    pub fn find_stitches(navmeshes: &[&NavMesh], tolerance: f32) -> Vec<[Vec2; 2]> {
+        let edges = navmeshes
+            .iter()
+            .enumerate()
+            .flat_map(|(index, navmesh)| {
+                navmesh
+                    .boundary_edges()
+                    .iter()
+                    .map(move |[start, end]| (index, *start, *end))
+            })
+            .collect::<Vec<_>>();
+        let bounds = edges
+            .iter()
+            .map(|(_, start, end)| {
+                let bounds = Rect::from_corners(*start, *end).inflate(tolerance);
+                (bounds.min, bounds.max)
+            })
+            .collect::<Vec<_>>();
+        GridIndex::new(&bounds)
+            .overlapping_pairs(&bounds)
+            .into_iter()
+            .filter_map(|(first, second)| {
+                let (navmesh, a, b) = edges[first as usize];
+                let (other, c, d) = edges[second as usize];
+                // Boundary edges have their navmesh on the left, they go in opposite directions along a seam
+                if navmesh == other || (b - a).dot(d - c) >= 0.0 {
+                    return None;
+                }
+                overlapping_segment(a, b, c, d, tolerance)
+            })
+            .collect()
+    }
+
+    fn merge_with(
+        navmeshes: &[&NavMesh],
+        stitches: &[[Vec2; 2]],
+        tolerance: f32,
+        discover_stitches: bool,
+    ) -> Result<NavMesh, MergeError> {
+        let first = navmeshes.first().ok_or(MergeError::Empty)?;
+        if let Some(index) = navmeshes
+            .iter()
+            .position(|navmesh| navmesh.transform != first.transform)
+        {
+            return Err(MergeError::TransformMismatch(index));
+        }
+
+        for (index, segment) in stitches.iter().enumerate() {
+            let matches = |point: Vec2| {
+                navmeshes
+                    .iter()
+                    .filter(|navmesh| {
+                        navmesh
+                            .mesh
+                            .vertices
+                            .iter()
+                            .any(|vertex| vertex.coords.distance(point) <= tolerance)
+                    })
+                    .count()
+                    >= 2
+            };
+            if !matches(segment[0]) || !matches(segment[1]) {
+                return Err(MergeError::StitchMismatch {
+                    index,
+                    segment: *segment,
+                });
+            }
+        }
+
+        let mut welder = geometry::Welder::new(tolerance);
+        let polygons = navmeshes
+            .iter()
+            .flat_map(|navmesh| {
+                navmesh.mesh.polygons.iter().map(|polygon| {
+                    polygon
+                        .vertices
+                        .iter()
+                        .map(|index| navmesh.mesh.vertices[*index as usize].coords)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .map(|polygon| {
+                let mut polygon = polygon
+                    .into_iter()
+                    .map(|vertex| welder.weld(vertex))
+                    .collect::<Vec<_>>();
+                polygon.dedup();
+                if polygon.len() > 1 && polygon.first() == polygon.last() {
+                    polygon.pop();
+                }
+                polygon
+            })
+            .filter(|polygon| {
+                polygon.len() >= 3 && geometry::signed_area(polygon).abs() > tolerance * tolerance
+            })
+            .collect::<Vec<_>>();
+        let seams = if discover_stitches {
+            Self::find_stitches(navmeshes, tolerance)
+                .into_iter()
+                .chain(stitches.iter().copied())
+                .collect::<Vec<_>>()
+        } else {
+            stitches.to_vec()
+        };
+        let on_stitches = polygons
+            .iter()
+            .flatten()
+            .filter(|vertex| {
+                seams.iter().any(|[start, end]| {
+                    geometry::closest_point_on_segment(**vertex, *start, *end).distance(**vertex)
+                        <= tolerance
+                })
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        let polygons = polygons
+            .into_iter()
+            .flat_map(|polygon| split_and_fan(polygon, &on_stitches, tolerance));
+        let mut mesh =
+            geometry::mesh_from_convex_polygons(polygons).ok_or(MergeError::InvalidMesh)?;
+        mesh.bake();
+        mesh.set_delta(first.mesh.delta());
+
+        let mut navmesh = NavMesh::from_polyanya_mesh(mesh);
+        navmesh.agent_radius = first.agent_radius;
+        navmesh.links = navmeshes
+            .iter()
+            .flat_map(|navmesh| navmesh.links.iter().copied())
+            .collect();
+        navmesh.variants = first
+            .variants
+            .iter()
+            .filter_map(|variant| {
+                let variants = navmeshes
+                    .iter()
+                    .map(|navmesh| {
+                        navmesh
+                            .variants
+                            .iter()
+                            .find(|other| other.agent_radius == variant.agent_radius)
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Self::merge_with(&variants, stitches, tolerance, discover_stitches).ok()
+            })
+            .collect();
+        navmesh.set_transform(first.transform);
+        Ok(navmesh)
+    }
+}
+
+impl NavMesh {
+    /// Remove the convex `region` from the navmesh, in the mesh local space, like a new obstacle.
+    ///
+    /// This is for changes driven by scripts, like a collapsing floor, without spawning obstacles and waiting for the
+    /// updater. Only the polygons overlapping the region are cut. The region is inflated by the
+    /// [agent radius](NavMesh::agent_radius) of this navmesh and of each of its
+    /// [variants](NavMesh::for_agent_radius), like obstacles. The [version](NavMesh::version) is increased, and the
+    /// [changed area](NavMesh::changed_area) is set to the bounds of the region.
+    ///
+    /// The polygons are rebuilt, so polygon indices change with the edit: data indexed by polygon, like a
+    /// [`DistanceField`](crate::DistanceField), a [`NavMeshAdjacency`](crate::NavMeshAdjacency) or the result of
+    /// [`NavMesh::reachable_within`], must be computed again. The navmesh is left unchanged if there is an error. Edits
+    /// are lost when the navmesh is built again by the updater.
+    pub fn carve(&mut self, region: &[Vec2]) -> Result<(), EditError> {
+        self.edit(region, false)
+    }
+
+    /// Add the convex `region` to the navmesh, in the mesh local space, connected to the polygons it touches.
+    ///
+    /// This is for changes driven by scripts, like an explosion opening a wall or a bridge being built. The region
+    /// replaces the polygons it overlaps, and is added as is to this navmesh and each of its
+    /// [variants](NavMesh::for_agent_radius). See [`NavMesh::carve`] for the other effects of an edit.
+    pub fn fill(&mut self, region: &[Vec2]) -> Result<(), EditError> {
+        self.edit(region, true)
+    }
+
+    fn edit(&mut self, region: &[Vec2], fill: bool) -> Result<(), EditError> {
+        let region = geometry::counter_clockwise(region.to_vec());
+        if !geometry::is_convex(&region) {
+            return Err(EditError::InvalidRegion);
+        }
+        let inflated = |agent_radius: f32| {
+            if fill {
+                region.clone()
+            } else {
+                offset_polygon(&region, agent_radius, INFLATION_QUALITY)
+            }
+        };
+
+        let mut edited = self.clone();
+        edited.edit_mesh(&inflated(self.agent_radius), fill)?;
+        let mut changed_area = bounds(&inflated(self.agent_radius));
+        for variant in edited.variants.iter_mut() {
+            let region = inflated(variant.agent_radius);
+            variant.edit_mesh(&region, fill)?;
+            changed_area = changed_area.union(bounds(&region));
+        }
+        edited.set_version(self.version.wrapping_add(1));
+        edited.changed_area = Some(changed_area);
+        for variant in edited.variants.iter_mut() {
+            variant.changed_area = Some(changed_area);
+        }
+        *self = edited;
+        Ok(())
+    }
+
+    /// Replace the polygons overlapping the convex counter clockwise `region` by the parts outside of it, and add the
+    /// region itself when filling.
+    fn edit_mesh(&mut self, region: &[Vec2], fill: bool) -> Result<(), EditError> {
+        let region_bounds = bounds(region);
+        let mut affected = region_bounds;
+        let mut kept = vec![];
+        let mut pieces = vec![];
+        for polygon in self.polygon_vertices() {
+            let polygon_bounds = bounds(&polygon);
+            if overlaps(polygon_bounds, region_bounds)
+                && geometry::signed_area(&geometry::clip_convex(&polygon, region)) > f32::EPSILON
+            {
+                affected = affected.union(polygon_bounds);
+                pieces.extend(geometry::subtract_convex(&polygon, region));
+            } else {
+                kept.push((polygon, polygon_bounds));
+            }
+        }
+        if fill {
+            pieces.push(region.to_vec());
+        }
+
+        // Only the polygons close to the edited ones can have vertices on their edges, creating T-junctions
+        let affected = affected.inflate(EDIT_TOLERANCE);
+        let (near, far): (Vec<_>, Vec<_>) = kept
+            .into_iter()
+            .partition(|(_, polygon_bounds)| overlaps(*polygon_bounds, affected));
+        let mut welder = geometry::Welder::new(EDIT_TOLERANCE);
+        // Existing vertices are welded first so that they don't move
+        let local = near
+            .into_iter()
+            .map(|(polygon, _)| polygon)
+            .chain(pieces)
+            .map(|polygon| {
+                polygon
+                    .into_iter()
+                    .map(|vertex| welder.weld(vertex))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let points = local.iter().flatten().copied().collect::<Vec<_>>();
+        let local = local
+            .into_iter()
+            .flat_map(|polygon| split_and_fan(polygon, &points, EDIT_TOLERANCE));
+
+        let mut mesh = geometry::mesh_from_convex_polygons(
+            far.into_iter().map(|(polygon, _)| polygon).chain(local),
+        )
+        .ok_or(EditError::InvalidMesh)?;
+        for _ in 0..MERGE_STEPS {
+            if !mesh.merge_polygons() {
+                break;
+            }
+        }
+        mesh.bake();
+        mesh.set_delta(self.mesh.delta());
+
+        let indexed = self.spatial_index.get().is_some();
+        self.mesh = Arc::new(mesh);
+        self.islands = Default::default();
+        self.boundary = Default::default();
+        self.spatial_index = Default::default();
+        self.polygon_index = Default::default();
+        if indexed {
+            self.build_spatial_index();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OffMeshLink;
+
+    fn navmesh(polygons: &[&[(f32, f32)]]) -> NavMesh {
+        NavMesh::from_polyanya_mesh(
+            geometry::mesh_from_convex_polygons(polygons.iter().map(|polygon| {
+                polygon
+                    .iter()
+                    .map(|(x, y)| Vec2::new(*x, *y))
+                    .collect::<Vec<_>>()
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn left_square() -> NavMesh {
+        navmesh(&[&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]])
+    }
+
+    /// Unit square starting at `x`, shrunk by `radius` except on its side at `x = 1`.
+    fn navmesh_at(x: f32, radius: f32) -> NavMesh {
+        let (min, max) = if x < 1.0 {
+            (x + radius, x + 1.0)
+        } else {
+            (x, x + 1.0 - radius)
+        };
+        navmesh(&[&[
+            (min, radius),
+            (max, radius),
+            (max, 1.0 - radius),
+            (min, 1.0 - radius),
+        ]])
+    }
+
+    fn points(points: &[(f32, f32)]) -> Vec<Vec2> {
+        points.iter().map(|(x, y)| Vec2::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn carve_a_hole() {
+        let mut navmesh = navmesh(&[&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]]);
+
+        navmesh
+            .carve(&points(&[(4.0, 4.0), (6.0, 4.0), (6.0, 6.0), (4.0, 6.0)]))
+            .unwrap();
+        assert_eq!(navmesh.version(), 1);
+        assert!(!navmesh.is_in_mesh(Vec2::new(5.0, 5.0)));
+        assert!(navmesh.is_in_mesh(Vec2::new(3.0, 5.0)));
+        let path = navmesh
+            .path(Vec2::new(2.0, 5.0), Vec2::new(8.0, 5.0))
+            .unwrap();
+        assert!(path.length > 6.1);
+        // Turns at the corners of the hole
+        assert!(path.path[..path.path.len() - 1]
+            .iter()
+            .all(|point| (point.y - 5.0).abs() >= 1.0 - 1e-3));
+    }
+
+    #[test]
+    fn fill_a_gap() {
+        let mut navmesh = navmesh(&[
+            &[(0.0, 0.0), (4.0, 0.0), (4.0, 10.0), (0.0, 10.0)],
+            &[(6.0, 0.0), (10.0, 0.0), (10.0, 10.0), (6.0, 10.0)],
+        ]);
+        assert!(navmesh
+            .path(Vec2::new(2.0, 5.0), Vec2::new(8.0, 5.0))
+            .is_none());
+
+        navmesh
+            .fill(&points(&[(4.0, 2.0), (6.0, 2.0), (6.0, 8.0), (4.0, 8.0)]))
+            .unwrap();
+        assert!(navmesh.is_in_mesh(Vec2::new(5.0, 5.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(5.0, 9.0)));
+        let path = navmesh
+            .path(Vec2::new(2.0, 5.0), Vec2::new(8.0, 5.0))
+            .unwrap();
+        assert!((path.length - 6.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn extract_keeps_radius_variants_and_links_inside() {
+        let square = &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)][..];
+        let mut source = navmesh(&[square]);
+        source.agent_radius = 0.5;
+        let mut variant = navmesh(&[&[(2.0, 2.0), (8.0, 2.0), (8.0, 8.0), (2.0, 8.0)]]);
+        variant.agent_radius = 2.0;
+        source.variants.push(variant);
+        let inside = OffMeshLink::new(Vec2::new(1.0, 1.0), Vec2::new(4.0, 4.0));
+        source.add_link(inside);
+        source.add_link(OffMeshLink::new(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0)));
+
+        let extracted = source
+            .extract_rect(Vec2::new(0.0, 0.0), Vec2::new(5.0, 5.0))
+            .unwrap();
+        assert_eq!(extracted.agent_radius(), 0.5);
+        assert!(extracted.is_in_mesh(Vec2::new(1.0, 4.0)));
+        assert!(!extracted.is_in_mesh(Vec2::new(6.0, 4.0)));
+        assert_eq!(extracted.links(), &[inside]);
+        let large = extracted.for_agent_radius(1.0).unwrap();
+        assert_eq!(large.agent_radius(), 2.0);
+        assert!(large.is_in_mesh(Vec2::new(3.0, 3.0)));
+        assert!(!large.is_in_mesh(Vec2::new(1.0, 1.0)));
+        assert!(extracted
+            .path_for_agent_radius(1.0, Vec2::new(3.0, 3.0), Vec2::new(4.5, 2.5))
+            .is_some());
+
+        // The variant has nothing left in this corner
+        let corner = source
+            .extract_rect(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0))
+            .unwrap();
+        assert!(corner.for_agent_radius(1.0).is_none());
+    }
+
+    #[test]
+    fn extract_rejects_invalid_regions() {
+        let source = navmesh(&[&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]]);
+
+        let concave = points(&[(1.0, 1.0), (9.0, 1.0), (5.0, 3.0), (9.0, 9.0), (1.0, 9.0)]);
+        assert_eq!(
+            source.extract_region(&concave).unwrap_err(),
+            EditError::InvalidRegion
+        );
+        assert_eq!(
+            source
+                .extract_rect(Vec2::new(20.0, 20.0), Vec2::new(30.0, 30.0))
+                .unwrap_err(),
+            EditError::InvalidMesh
+        );
+    }
+
+    #[test]
+    fn merge_squares_sharing_an_edge() {
+        let right = navmesh(&[&[(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)]]);
+
+        let merged = NavMesh::merge(&[&left_square(), &right], &[], 1e-3).unwrap();
+        assert_eq!(merged.get().vertices.len(), 6);
+        assert!(merged
+            .path(Vec2::new(0.5, 0.5), Vec2::new(1.5, 0.5))
+            .is_some());
+    }
+
+    #[test]
+    fn merge_keeps_radius_shared_variants_and_links() {
+        let mut left = left_square();
+        left.agent_radius = 0.1;
+        let mut right = navmesh(&[&[(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)]]);
+        right.agent_radius = 0.1;
+        for (navmesh, x) in [(&mut left, 0.0), (&mut right, 1.0)] {
+            for radius in [0.2, 0.3] {
+                let mut variant = navmesh_at(x, radius);
+                variant.agent_radius = radius;
+                navmesh.variants.push(variant);
+            }
+        }
+        // Only the left navmesh has this variant, it can't be merged
+        let mut variant = navmesh_at(0.0, 0.4);
+        variant.agent_radius = 0.4;
+        left.variants.push(variant);
+        let jump = OffMeshLink::new(Vec2::new(0.2, 0.2), Vec2::new(0.8, 0.8));
+        let ladder = OffMeshLink::new(Vec2::new(1.2, 0.2), Vec2::new(1.8, 0.8));
+        left.add_link(jump);
+        right.add_link(ladder);
+
+        let merged = NavMesh::merge(&[&left, &right], &[], 1e-3).unwrap();
+        assert_eq!(merged.agent_radius(), 0.1);
+        assert_eq!(merged.links(), &[jump, ladder]);
+        assert_eq!(merged.variants.len(), 2);
+        for (variant, radius) in merged.variants.iter().zip([0.2, 0.3]) {
+            assert_eq!(variant.agent_radius(), radius);
+            assert!(variant
+                .path(Vec2::new(0.5, 0.5), Vec2::new(1.5, 0.5))
+                .is_some());
+        }
+        assert!(merged.for_agent_radius(0.4).is_none());
+    }
+
+    #[test]
+    fn merge_squares_with_mismatched_vertices_on_their_shared_edge() {
+        // The right square has a vertex in the middle of the edge shared with the left one
+        let right = navmesh(&[
+            &[(1.0, 0.0), (2.0, 0.0), (2.0, 0.5), (1.0, 0.5)],
+            &[(1.0, 0.5), (2.0, 0.5), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+        let stitch = [Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)];
+
+        let merged = NavMesh::merge(&[&left_square(), &right], &[stitch], 1e-3).unwrap();
+        for (from, to) in [((0.5, 0.25), (1.5, 0.25)), ((0.5, 0.75), (1.5, 0.75))] {
+            let path = merged.path(Vec2::new(from.0, from.1), Vec2::new(to.0, to.1));
+            assert!(path.is_some(), "no path from {:?} to {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn stitches_are_discovered_on_seams_with_mismatched_vertices() {
+        let right = navmesh(&[
+            &[(1.0, 0.0), (2.0, 0.0), (2.0, 0.5), (1.0, 0.5)],
+            &[(1.0, 0.5), (2.0, 0.5), (2.0, 1.0), (1.0, 1.0)],
+        ]);
+        let left = left_square();
+
+        let mut stitches = NavMesh::find_stitches(&[&left, &right], 1e-3);
+        stitches.iter_mut().for_each(|stitch| {
+            stitch.sort_by(|a, b| a.y.total_cmp(&b.y));
+        });
+        stitches.sort_by(|a, b| a[0].y.total_cmp(&b[0].y));
+        assert_eq!(
+            stitches,
+            vec![
+                [Vec2::new(1.0, 0.0), Vec2::new(1.0, 0.5)],
+                [Vec2::new(1.0, 0.5), Vec2::new(1.0, 1.0)]
+            ]
+        );
+
+        let merged = NavMesh::merge_with_stitch_discovery(&[&left, &right], &[], 1e-3).unwrap();
+        let path = merged
+            .path(Vec2::new(0.5, 0.75), Vec2::new(1.5, 0.25))
+            .unwrap();
+        assert!((path.length - Vec2::new(1.0, -0.5).length()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn overlapping_navmeshes_on_the_same_side_are_not_stitched() {
+        // Same square twice: edges overlap, but the navmeshes are on the same side of them
+        assert!(NavMesh::find_stitches(&[&left_square(), &left_square()], 1e-3).is_empty());
+    }
+
+    #[test]
+    fn merge_drops_polygons_collapsed_by_welding() {
+        // Two vertices of the triangle are welded together, and one is welded to a corner of the square
+        let right = navmesh(&[&[(1.0, 0.0), (1.0005, 0.0), (2.0, 1.0), (1.0, 1.0)]]);
+
+        let merged = NavMesh::merge(&[&left_square(), &right], &[], 1e-3).unwrap();
+        let mesh = merged.get();
+        for polygon in &mesh.polygons {
+            let mut vertices = polygon.vertices.clone();
+            vertices.sort_unstable();
+            vertices.dedup();
+            assert_eq!(vertices.len(), polygon.vertices.len());
+        }
+        assert!(merged
+            .path(Vec2::new(0.5, 0.5), Vec2::new(1.2, 0.6))
+            .is_some());
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::BinaryHeap;
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    geometry::{centroid, closest_point_on_segment},
+    links::Candidate,
+    NavMesh, Portal,
+};
+
+/// Geodesic distances from sources to each portal of a navmesh, approximated through the center of the portals.
+#[derive(Debug, Clone)]
+pub(crate) struct PortalDistances {
+    pub(crate) portals: Vec<Portal>,
+    /// Indices of the portals of each polygon.
+    pub(crate) polygon_portals: Vec<Vec<usize>>,
+    /// Distance from the closest source to the center of each portal.
+    pub(crate) distances: Vec<f32>,
+    /// Sources and the polygon containing them. Sources not in the mesh are ignored.
+    pub(crate) sources: Vec<(Vec2, usize)>,
+}
+
+impl PortalDistances {
+    /// Compute the distances from `sources` with Dijkstra's algorithm over the portals.
+    pub(crate) fn new(navmesh: &NavMesh, sources: &[Vec2]) -> Self {
+        let portals = navmesh.portals();
+        let mut polygon_portals = vec![vec![]; navmesh.mesh.polygons.len()];
+        for (index, portal) in portals.iter().enumerate() {
+            polygon_portals[portal.polygons.0].push(index);
+            polygon_portals[portal.polygons.1].push(index);
+        }
+        let sources = sources
+            .iter()
+            .filter_map(|source| Some((*source, navmesh.polygon_at(*source)?)))
+            .collect::<Vec<_>>();
+
+        let mut distances = vec![f32::INFINITY; portals.len()];
+        let mut queue = BinaryHeap::new();
+        for (source, polygon) in &sources {
+            for portal in &polygon_portals[*polygon] {
+                let cost = source.distance(portals[*portal].center());
+                if cost < distances[*portal] {
+                    distances[*portal] = cost;
+                    queue.push(Candidate {
+                        cost,
+                        node: *portal,
+                    });
+                }
+            }
+        }
+        while let Some(Candidate { cost, node }) = queue.pop() {
+            if cost > distances[node] {
+                continue;
+            }
+            let (a, b) = portals[node].polygons;
+            for other in polygon_portals[a].iter().chain(&polygon_portals[b]) {
+                let other_cost = cost + portals[node].center().distance(portals[*other].center());
+                if other_cost < distances[*other] {
+                    distances[*other] = other_cost;
+                    queue.push(Candidate {
+                        cost: other_cost,
+                        node: *other,
+                    });
+                }
+            }
+        }
+
+        Self {
+            portals,
+            polygon_portals,
+            distances,
+            sources,
+        }
+    }
+
+    /// Portal of `polygon` to go through to get closer to a source from `point`, with the estimated distance.
+    pub(crate) fn best_portal(&self, polygon: usize, point: Vec2) -> Option<(usize, f32)> {
+        self.polygon_portals[polygon]
+            .iter()
+            .map(|portal| {
+                (
+                    *portal,
+                    point.distance(self.portals[*portal].center()) + self.distances[*portal],
+                )
+            })
+            .filter(|(_, distance)| distance.is_finite())
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Distance from `point` in `polygon` to the closest source, in a straight line to the sources in the same polygon
+    /// or through its portals.
+    pub(crate) fn distance_from(&self, polygon: usize, point: Vec2) -> f32 {
+        self.sources
+            .iter()
+            .filter(|(_, source_polygon)| *source_polygon == polygon)
+            .map(|(source, _)| source.distance(point))
+            .chain(
+                self.best_portal(polygon, point)
+                    .map(|(_, distance)| distance),
+            )
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+/// Aim for the closest point of a portal to `point`, away from its ends to not graze corners.
+fn portal_target(portal: &Portal, point: Vec2) -> Vec2 {
+    let margin = (portal.end - portal.start) * 0.1;
+    closest_point_on_segment(point, portal.start + margin, portal.end - margin)
+}
+
+/// Directions toward a goal from anywhere on a [`NavMesh`], for many agents going to the same place. See
+/// [`NavMesh::flow_field`].
+///
+/// Directions are computed per polygon of the navmesh, through the portal on the shortest route to the goal. Flow
+/// fields are computed for one version of the navmesh, use [`FlowField::is_stale`] to know when to compute them again.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    navmesh: NavMesh,
+    distances: PortalDistances,
+    goal: Vec2,
+    /// [Version](NavMesh::version) of the navmesh this flow field was computed on.
+    pub version: u32,
+}
+
+impl FlowField {
+    /// Goal of the flow field, in the mesh local space.
+    pub fn goal(&self) -> Vec2 {
+        self.goal
+    }
+
+    /// Check if this flow field was computed on an older version of `navmesh`, and should be computed again.
+    pub fn is_stale(&self, navmesh: &NavMesh) -> bool {
+        self.version != navmesh.version()
+    }
+
+    /// Direction to move in at `point` to reach the goal, in the mesh local space.
+    ///
+    /// Returns `None` if `point` is not in the navmesh, if the goal can't be reached from it, or at the goal.
+    pub fn direction_at(&self, point: Vec2) -> Option<Dir2> {
+        let polygon = self.navmesh.polygon_at(point)?;
+        let target = if self
+            .distances
+            .sources
+            .iter()
+            .any(|(_, source)| *source == polygon)
+        {
+            self.goal
+        } else {
+            let (portal, _) = self.distances.best_portal(polygon, point)?;
+            portal_target(&self.distances.portals[portal], point)
+        };
+        Dir2::new(target - point).ok()
+    }
+
+    /// Direction to move in at `point` to reach the goal.
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`]. See [`FlowField::direction_at`].
+    pub fn transformed_direction_at(&self, point: Vec3) -> Option<Dir3> {
+        let transform = self.navmesh.transform();
+        let direction = self.direction_at(transform.transform_point(point).xy())?;
+        Dir3::new(transform.rotation.inverse() * direction.extend(0.0)).ok()
+    }
+}
+
+/// Geodesic distances to the closest of several sources, from anywhere on a [`NavMesh`]. See
+/// [`NavMesh::distance_field`].
+///
+/// Useful for AI heatmaps, like the danger around enemies or the proximity to players, or to flee from the sources by
+/// following [`DistanceField::ascending_direction_at`]. Distances are approximated through the centers of the portals
+/// between polygons.
+#[derive(Debug, Clone)]
+pub struct DistanceField {
+    navmesh: NavMesh,
+    distances: PortalDistances,
+    polygons: Vec<f32>,
+    /// [Version](NavMesh::version) of the navmesh this distance field was computed on.
+    pub version: u32,
+}
+
+impl DistanceField {
+    /// Distance from the centroid of each polygon to the closest source, indexed like [`NavMesh::polygon_at`].
+    ///
+    /// Polygons that can't reach any source have an infinite distance.
+    pub fn polygon_distances(&self) -> &[f32] {
+        &self.polygons
+    }
+
+    /// Check if this distance field was computed on an older version of `navmesh`, and should be computed again.
+    pub fn is_stale(&self, navmesh: &NavMesh) -> bool {
+        self.version != navmesh.version()
+    }
+
+    /// Distance from `point` to the closest source, in the mesh local space.
+    ///
+    /// Returns `None` if `point` is not in the navmesh or can't reach any source.
+    pub fn distance_at(&self, point: Vec2) -> Option<f32> {
+        let polygon = self.navmesh.polygon_at(point)?;
+        let distance = self.distances.distance_from(polygon, point);
+        distance.is_finite().then_some(distance)
+    }
+
+    /// Direction to move in at `point` to get further from the sources, toward the neighbor polygon with the largest
+    /// distance.
+    ///
+    /// Returns `None` if `point` is not in the navmesh, or if no neighbor is further from the sources.
+    pub fn ascending_direction_at(&self, point: Vec2) -> Option<Dir2> {
+        let polygon = self.navmesh.polygon_at(point)?;
+        let (portal, _) = self.distances.polygon_portals[polygon]
+            .iter()
+            .map(|portal| {
+                let (a, b) = self.distances.portals[*portal].polygons;
+                let other = if a == polygon { b } else { a };
+                (*portal, self.polygons[other])
+            })
+            .filter(|(_, distance)| distance.is_finite() && *distance > self.polygons[polygon])
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        Dir2::new(portal_target(&self.distances.portals[portal], point) - point).ok()
+    }
+}
+
+impl NavMesh {
+    /// Compute a [`DistanceField`] from `sources`, in the mesh local space. Sources not in the navmesh are ignored.
+    pub fn distance_field(&self, sources: &[Vec2]) -> DistanceField {
+        let distances = PortalDistances::new(self, sources);
+        let polygons = self
+            .polygon_vertices()
+            .enumerate()
+            .map(|(polygon, vertices)| distances.distance_from(polygon, centroid(&vertices)))
+            .collect();
+        let mut navmesh = self.clone();
+        navmesh.variants.clear();
+        DistanceField {
+            navmesh,
+            distances,
+            polygons,
+            version: self.version(),
+        }
+    }
+
+    /// List the polygons that can be reached from `from` by walking at most `max_cost`, in the mesh local space.
+    ///
+    /// Polygons are indexed like [`NavMesh::polygon_at`], and a polygon is listed when one of its portals can be
+    /// reached. Unlike a euclidean range, this goes around obstacles, for movement ranges in tactics games. Distances
+    /// are approximated through the centers of the portals.
+    pub fn reachable_within(&self, from: Vec2, max_cost: f32) -> Vec<usize> {
+        let distances = PortalDistances::new(self, &[from]);
+        let Some((_, start)) = distances.sources.first() else {
+            return vec![];
+        };
+        let mut reachable = vec![false; distances.polygon_portals.len()];
+        reachable[*start] = true;
+        for (portal, distance) in distances.portals.iter().zip(&distances.distances) {
+            if *distance <= max_cost {
+                reachable[portal.polygons.0] = true;
+                reachable[portal.polygons.1] = true;
+            }
+        }
+        reachable
+            .into_iter()
+            .enumerate()
+            .filter_map(|(polygon, reachable)| reachable.then_some(polygon))
+            .collect()
+    }
+
+    /// List the polygons that can be reached from `from` by walking at most `max_cost`.
+    ///
+    /// Inputs are transformed using the [`NavMesh::transform`], `max_cost` is in the mesh local space. See
+    /// [`NavMesh::reachable_within`].
+    pub fn transformed_reachable_within(&self, from: Vec3, max_cost: f32) -> Vec<usize> {
+        self.reachable_within(self.transform.transform_point(from).xy(), max_cost)
+    }
+
+    /// Compute a [`FlowField`] toward `goal`, in the mesh local space.
+    ///
+    /// This is cheaper than a path per agent when many agents go to the same goal. The directions are coarser than
+    /// paths, as they follow the shortest route between the centers of the portals.
+    pub fn flow_field(&self, goal: Vec2) -> FlowField {
+        let mut navmesh = self.clone();
+        navmesh.variants.clear();
+        FlowField {
+            distances: PortalDistances::new(self, &[goal]),
+            goal,
+            version: self.version(),
+            navmesh,
+        }
+    }
+
+    /// Compute a [`FlowField`] toward `goal`.
+    ///
+    /// Inputs are transformed using the [`NavMesh::transform`]. See [`NavMesh::flow_field`].
+    pub fn transformed_flow_field(&self, goal: Vec3) -> FlowField {
+        self.flow_field(self.transform.transform_point(goal).xy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 10x10 square, with a wall from the bottom edge up to `y = 8` in the middle.
+    fn walled() -> NavMesh {
+        NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![vec![
+                Vec2::new(4.5, 0.0),
+                Vec2::new(5.5, 0.0),
+                Vec2::new(5.5, 8.0),
+                Vec2::new(4.5, 8.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn flow_goes_around_a_wall() {
+        let navmesh = walled();
+        let goal = Vec2::new(8.0, 1.0);
+        let field = navmesh.flow_field(goal);
+
+        // Straight to the goal would hit the wall, the flow goes up first
+        let mut point = Vec2::new(2.0, 1.0);
+        assert!(field.direction_at(point).unwrap().y > 0.5);
+        let mut highest = point.y;
+        for _ in 0..400 {
+            let Some(direction) = field.direction_at(point) else {
+                break;
+            };
+            point += *direction * 0.1;
+            assert!(navmesh.is_in_mesh(point), "{point}");
+            highest = highest.max(point.y);
+        }
+        assert!(point.distance(goal) < 0.2, "{point}");
+        assert!(highest > 8.0);
+    }
+
+    #[test]
+    fn distances_go_around_a_wall() {
+        let navmesh = walled();
+        let field = navmesh.distance_field(&[Vec2::new(8.0, 1.0)]);
+
+        // At least up and around the wall, and back down
+        let distance = field.distance_at(Vec2::new(2.0, 1.0)).unwrap();
+        assert!(distance > 14.0, "{distance}");
+        assert!(field.distance_at(Vec2::new(5.0, 4.0)).is_none());
+        assert!(field.ascending_direction_at(Vec2::new(8.0, 1.0)).is_some());
+    }
+}
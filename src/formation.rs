@@ -0,0 +1,196 @@
+//! Paths for groups of agents moving side by side, keeping their formation where there is enough space.
+
+use bevy::prelude::*;
+use polyanya::Path;
+
+use crate::NavMesh;
+
+/// A group of agents moving side by side, like an RTS squad. See [`NavMesh::formation_path`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Formation {
+    /// Lateral offset of each slot from the center of the formation, positive to the left of the direction of
+    /// movement.
+    pub slots: Vec<f32>,
+    /// Radius of the agents in the formation.
+    pub agent_radius: f32,
+}
+
+impl Formation {
+    /// A formation of `count` agents in a line, `spacing` apart, centered on the path.
+    pub fn line(count: usize, spacing: f32, agent_radius: f32) -> Self {
+        let center = (count.max(1) - 1) as f32 * spacing / 2.0;
+        Self {
+            slots: (0..count)
+                .map(|slot| slot as f32 * spacing - center)
+                .collect(),
+            agent_radius,
+        }
+    }
+
+    /// Clearance needed on each side of the center of the formation.
+    pub fn half_width(&self) -> f32 {
+        self.slots
+            .iter()
+            .map(|offset| offset.abs())
+            .fold(0.0, f32::max)
+            + self.agent_radius
+    }
+}
+
+/// A path for a [`Formation`], with the waypoints of each slot.
+#[derive(Debug)]
+pub struct FormationPath {
+    /// Path of the center of the formation.
+    pub path: Path,
+    /// Waypoints of each slot, in the order of [`Formation::slots`]. They start with the starting position of the slot,
+    /// then follow the waypoints of the center path.
+    pub slots: Vec<Vec<Vec2>>,
+    /// `true` if the formation couldn't find a path wide enough, and follows the path of a single agent.
+    pub narrowed: bool,
+}
+
+impl FormationPath {
+    /// Check if the slots are lined up on the center path at `waypoint`, because there isn't enough space to keep the
+    /// formation. `waypoint` is an index in the waypoints of the slots.
+    pub fn is_queued(&self, waypoint: usize) -> bool {
+        let mut slots = self.slots.iter().filter_map(|slot| slot.get(waypoint));
+        let Some(first) = slots.next() else {
+            return false;
+        };
+        self.slots.len() > 1 && slots.all(|point| point == first)
+    }
+}
+
+impl NavMesh {
+    /// Get a path for a group of agents in a [`Formation`].
+    ///
+    /// The path uses the navmesh built for the [width](Formation::half_width) of the formation if there is one, see
+    /// [`NavMesh::for_agent_radius`], so that the group moves through wide corridors. Otherwise, or if the group can't
+    /// reach `to` through a wide enough path, the path of a single agent is used. At each waypoint, slots where an
+    /// agent of the [formation radius](Formation::agent_radius) isn't [navigable](NavMesh::is_navigable) are moved on
+    /// the center path, so that the group queues up through narrow passages. This check is the same whichever navmesh
+    /// the path comes from.
+    pub fn formation_path(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        formation: &Formation,
+    ) -> Option<FormationPath> {
+        let navmesh = self
+            .for_agent_radius(formation.agent_radius)
+            .unwrap_or(self);
+        let wide = self
+            .for_agent_radius(formation.half_width())
+            .and_then(|wide| wide.path(from, to));
+        let narrowed = wide.is_none();
+        let path = wide.or_else(|| navmesh.path(from, to))?;
+
+        let mut waypoints = Vec::with_capacity(path.path.len() + 1);
+        waypoints.push(from);
+        waypoints.extend(path.path.iter().copied());
+
+        let mut slots = vec![Vec::with_capacity(waypoints.len()); formation.slots.len()];
+        for (index, waypoint) in waypoints.iter().enumerate() {
+            let incoming = index
+                .checked_sub(1)
+                .map(|previous| (*waypoint - waypoints[previous]).normalize_or_zero())
+                .unwrap_or_default();
+            let outgoing = waypoints
+                .get(index + 1)
+                .map(|next| (*next - *waypoint).normalize_or_zero())
+                .unwrap_or_default();
+            // On a U-turn the incoming and outgoing directions cancel out, keep the incoming one
+            let direction =
+                (incoming + outgoing)
+                    .try_normalize()
+                    .unwrap_or(if incoming == Vec2::ZERO {
+                        outgoing
+                    } else {
+                        incoming
+                    });
+            let normal = direction.perp();
+            for (offset, slot) in formation.slots.iter().zip(slots.iter_mut()) {
+                let point = *waypoint + normal * *offset;
+                let fits = self.is_navigable(point, formation.agent_radius)
+                    && slot
+                        .last()
+                        .map(|previous| navmesh.is_reachable_in_straight_line(*previous, point))
+                        .unwrap_or(true);
+                slot.push(if fits { point } else { *waypoint });
+            }
+        }
+
+        Some(FormationPath {
+            path,
+            slots,
+            narrowed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A corridor from (0, 0) to (20, 2), opening on a room from (0, 2) to (20, 10).
+    fn navmesh() -> NavMesh {
+        NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(20.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![vec![
+                Vec2::new(2.0, 2.0),
+                Vec2::new(20.0, 2.0),
+                Vec2::new(20.0, 3.0),
+                Vec2::new(2.0, 3.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn line_is_centered() {
+        let formation = Formation::line(3, 1.0, 0.25);
+        assert_eq!(formation.slots, vec![-1.0, 0.0, 1.0]);
+        assert_eq!(formation.half_width(), 1.25);
+    }
+
+    #[test]
+    fn slots_keep_formation_in_open_space() {
+        let formation = Formation::line(3, 1.0, 0.25);
+        let path = navmesh()
+            .formation_path(Vec2::new(4.0, 6.0), Vec2::new(16.0, 6.0), &formation)
+            .unwrap();
+
+        assert!(!path.is_queued(1));
+        let last = path.slots.iter().map(|slot| *slot.last().unwrap());
+        for (point, offset) in last.zip(&formation.slots) {
+            assert!((point - Vec2::new(16.0, 6.0 + offset)).length() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn slots_queue_in_narrow_corridor() {
+        let formation = Formation::line(3, 1.5, 0.25);
+        let path = navmesh()
+            .formation_path(Vec2::new(1.0, 6.0), Vec2::new(18.0, 1.0), &formation)
+            .unwrap();
+
+        assert!(path.narrowed);
+        assert!(path.is_queued(path.slots[0].len() - 1));
+    }
+
+    #[test]
+    fn u_turn_keeps_slots_apart() {
+        let formation = Formation::line(2, 1.0, 0.1);
+        let path = navmesh()
+            .formation_path(Vec2::new(18.0, 5.0), Vec2::new(18.0, 1.0), &formation)
+            .unwrap();
+
+        for waypoint in 1..path.slots[0].len() - 1 {
+            assert!(!path.is_queued(waypoint), "slots collapsed at {}", waypoint);
+        }
+    }
+}
@@ -0,0 +1,488 @@
+#[cfg(feature = "3d")]
+use bevy::math::Vec3;
+use bevy::{
+    math::{IVec2, Vec2},
+    utils::HashMap,
+};
+use polyanya::Trimesh;
+
+/// Signed area of a polygon, positive if counter clockwise.
+pub(crate) fn signed_area(polygon: &[Vec2]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Centroid of a polygon, or the average of its points if it's degenerate.
+pub(crate) fn centroid(polygon: &[Vec2]) -> Vec2 {
+    let area = signed_area(polygon);
+    if area.abs() > f32::EPSILON {
+        polygon
+            .iter()
+            .zip(polygon.iter().cycle().skip(1))
+            .map(|(a, b)| (*a + *b) * a.perp_dot(*b))
+            .sum::<Vec2>()
+            / (6.0 * area)
+    } else {
+        polygon.iter().sum::<Vec2>() / polygon.len().max(1) as f32
+    }
+}
+
+/// Returns the polygon in counter clockwise order.
+pub(crate) fn counter_clockwise(mut polygon: Vec<Vec2>) -> Vec<Vec2> {
+    if signed_area(&polygon) < 0.0 {
+        polygon.reverse();
+    }
+    polygon
+}
+
+/// Clip a convex polygon by a convex counter clockwise `clip` polygon.
+pub(crate) fn clip_convex(subject: &[Vec2], clip: &[Vec2]) -> Vec<Vec2> {
+    let mut output = subject.to_vec();
+    for (clip_start, clip_end) in clip.iter().zip(clip.iter().cycle().skip(1)) {
+        if output.is_empty() {
+            break;
+        }
+        output = clip_half_plane(&output, *clip_start, *clip_end);
+    }
+    output
+}
+
+/// Clip a convex polygon to the half plane on the left of the line from `start` to `end`.
+fn clip_half_plane(subject: &[Vec2], start: Vec2, end: Vec2) -> Vec<Vec2> {
+    let edge = end - start;
+    let inside = |p: Vec2| edge.perp_dot(p - start) >= 0.0;
+    let mut output = Vec::with_capacity(subject.len() + 1);
+    for (current, next) in subject.iter().zip(subject.iter().cycle().skip(1)) {
+        match (inside(*current), inside(*next)) {
+            (true, true) => output.push(*next),
+            (true, false) => output.push(line_intersection(*current, *next, start, edge)),
+            (false, true) => {
+                output.push(line_intersection(*current, *next, start, edge));
+                output.push(*next);
+            }
+            (false, false) => (),
+        }
+    }
+    output
+}
+
+/// Remove a convex counter clockwise `hole` from a convex polygon, as a list of convex polygons.
+///
+/// The part of `subject` outside of each edge of the hole is split off in turn, so the pieces don't overlap. Pieces
+/// with no area are dropped.
+pub(crate) fn subtract_convex(subject: &[Vec2], hole: &[Vec2]) -> Vec<Vec<Vec2>> {
+    let mut pieces = vec![];
+    let mut remaining = subject.to_vec();
+    for (start, end) in hole.iter().zip(hole.iter().cycle().skip(1)) {
+        if remaining.is_empty() {
+            break;
+        }
+        let outside = clip_half_plane(&remaining, *end, *start);
+        if signed_area(&outside) > f32::EPSILON {
+            pieces.push(outside);
+        }
+        remaining = clip_half_plane(&remaining, *start, *end);
+    }
+    pieces
+}
+
+/// Check if a counter clockwise polygon with at least 3 points is convex.
+pub(crate) fn is_convex(polygon: &[Vec2]) -> bool {
+    polygon.len() >= 3
+        && signed_area(polygon) > f32::EPSILON
+        && (0..polygon.len()).all(|i| {
+            let previous = polygon[(i + polygon.len() - 1) % polygon.len()];
+            let next = polygon[(i + 1) % polygon.len()];
+            (polygon[i] - previous).perp_dot(next - polygon[i]) >= -f32::EPSILON
+        })
+}
+
+/// Insert in the edges of `polygon` the `points` that are on them, closer than `tolerance`, to remove T-junctions
+/// with the neighbouring polygons.
+pub(crate) fn split_edges(polygon: &[Vec2], points: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    let mut output = Vec::with_capacity(polygon.len());
+    for (start, end) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+        output.push(*start);
+        let mut on_edge = points
+            .iter()
+            .filter(|point| {
+                point.distance(*start) > tolerance
+                    && point.distance(*end) > tolerance
+                    && closest_point_on_segment(**point, *start, *end).distance(**point)
+                        <= tolerance
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        on_edge.sort_by(|a, b| {
+            a.distance_squared(*start)
+                .total_cmp(&b.distance_squared(*start))
+        });
+        on_edge.dedup();
+        output.extend(on_edge);
+    }
+    output
+}
+
+/// Intersection of segment `a`-`b` with the line going through `origin` along `direction`.
+///
+/// The segment end points are ordered so that the same segment always gives the exact same result.
+fn line_intersection(a: Vec2, b: Vec2, origin: Vec2, direction: Vec2) -> Vec2 {
+    let (a, b) = if (a.x, a.y) < (b.x, b.y) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let segment = b - a;
+    let denominator = direction.perp_dot(segment);
+    if denominator.abs() < f32::EPSILON {
+        return a;
+    }
+    let t = direction.perp_dot(origin - a) / denominator;
+    a + segment * t.clamp(0.0, 1.0)
+}
+
+/// Build a Polyanya mesh from a list of convex polygons. Vertices with the exact same coordinates are shared.
+pub(crate) fn mesh_from_convex_polygons(
+    polygons: impl IntoIterator<Item = Vec<Vec2>>,
+) -> Option<polyanya::Mesh> {
+    let mut vertices = vec![];
+    let mut indices: HashMap<(u32, u32), usize> = HashMap::default();
+    let mut triangles = vec![];
+    for polygon in polygons {
+        let mut polygon = counter_clockwise(polygon);
+        // Clipping can repeat points, which would give degenerate triangles
+        polygon.dedup();
+        if polygon.len() > 1 && polygon.first() == polygon.last() {
+            polygon.pop();
+        }
+        if polygon.len() < 3 || signed_area(&polygon) < f32::EPSILON {
+            continue;
+        }
+        let polygon_indices = polygon
+            .iter()
+            .map(|vertex| {
+                *indices
+                    .entry((vertex.x.to_bits(), vertex.y.to_bits()))
+                    .or_insert_with(|| {
+                        vertices.push(*vertex);
+                        vertices.len() - 1
+                    })
+            })
+            .collect::<Vec<_>>();
+        for i in 2..polygon_indices.len() {
+            triangles.push([
+                polygon_indices[0],
+                polygon_indices[i - 1],
+                polygon_indices[i],
+            ]);
+        }
+    }
+    if triangles.is_empty() {
+        return None;
+    }
+    Trimesh {
+        vertices,
+        triangles,
+    }
+    .try_into()
+    .ok()
+}
+
+/// Snap points to previously seen points closer than `tolerance`.
+pub(crate) struct Welder {
+    tolerance: f32,
+    cells: HashMap<IVec2, Vec<Vec2>>,
+}
+
+impl Welder {
+    pub(crate) fn new(tolerance: f32) -> Self {
+        Self {
+            tolerance: tolerance.max(f32::EPSILON),
+            cells: HashMap::default(),
+        }
+    }
+
+    fn cell(&self, point: Vec2) -> IVec2 {
+        (point / self.tolerance).floor().as_ivec2()
+    }
+
+    /// Find a point previously welded closer than `tolerance`.
+    pub(crate) fn find(&self, point: Vec2) -> Option<Vec2> {
+        let cell = self.cell(point);
+        (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |y| cell + IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .find(|known| known.distance(point) <= self.tolerance)
+            .copied()
+    }
+
+    /// Weld a point, returning the point it was snapped to.
+    pub(crate) fn weld(&mut self, point: Vec2) -> Vec2 {
+        if let Some(known) = self.find(point) {
+            return known;
+        }
+        let cell = self.cell(point);
+        self.cells.entry(cell).or_default().push(point);
+        point
+    }
+}
+
+/// Convex hull of a set of points, in counter clockwise order.
+pub(crate) fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<Vec2> = Vec::with_capacity(points.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Box<dyn Iterator<Item = &Vec2>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for point in ordered {
+            while hull.len() >= start + 2 {
+                let a = hull[hull.len() - 2];
+                let b = hull[hull.len() - 1];
+                if (b - a).perp_dot(*point - a) > 0.0 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Closest point to `point` on the segment from `a` to `b`.
+pub(crate) fn closest_point_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let segment = b - a;
+    let length_squared = segment.length_squared();
+    if length_squared < f32::EPSILON {
+        return a;
+    }
+    a + segment * ((point - a).dot(segment) / length_squared).clamp(0.0, 1.0)
+}
+
+/// Check if `point` is inside `polygon`, in any orientation.
+pub(crate) fn contains_point(polygon: &[Vec2], point: Vec2) -> bool {
+    contains_point_in(polygon.iter().copied(), point)
+}
+
+/// Same as [`contains_point`], for a polygon given as an iterator over its points.
+pub(crate) fn contains_point_in(polygon: impl Iterator<Item = Vec2> + Clone, point: Vec2) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon.clone().zip(polygon.cycle().skip(1)) {
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Clip a polygon to the slab between the heights `min` and `max` on the `z` axis.
+///
+/// When `min` and `max` are equal, the result is the intersection of the polygon with the plane at this height.
+#[cfg(feature = "3d")]
+pub(crate) fn clip_to_slab(polygon: &[Vec3], min: f32, max: f32) -> Vec<Vec3> {
+    let clip = |points: Vec<Vec3>, height: f32, keep_above: bool| {
+        let inside = |point: &Vec3| (point.z >= height) == keep_above || point.z == height;
+        let mut clipped = Vec::with_capacity(points.len() + 1);
+        for (i, current) in points.iter().enumerate() {
+            let next = &points[(i + 1) % points.len()];
+            if inside(current) {
+                clipped.push(*current);
+            }
+            if inside(current) != inside(next) {
+                let mut crossing = current.lerp(*next, (height - current.z) / (next.z - current.z));
+                crossing.z = height;
+                clipped.push(crossing);
+            }
+        }
+        clipped
+    };
+    clip(clip(polygon.to_vec(), min, true), max, false)
+}
+
+/// Snap a point to a grid of size `grid`.
+pub(crate) fn quantize(point: Vec2, grid: f32) -> Vec2 {
+    (point / grid).round() * grid
+}
+
+/// Snap the points of a polygon to a grid of size `grid`, keeping its orientation.
+///
+/// Returns `None` if the polygon collapses to less than a triangle or to no area, like an obstacle smaller than the
+/// grid.
+pub(crate) fn quantize_polygon(polygon: &[Vec2], grid: f32) -> Option<Vec<Vec2>> {
+    let mut snapped = polygon
+        .iter()
+        .map(|point| quantize(*point, grid))
+        .collect::<Vec<_>>();
+    snapped.dedup();
+    if snapped.len() > 1 && snapped.first() == snapped.last() {
+        snapped.pop();
+    }
+    let area = signed_area(&snapped);
+    if snapped.len() < 3 || area == 0.0 {
+        return None;
+    }
+    if area.signum() != signed_area(polygon).signum() {
+        snapped.reverse();
+    }
+    Some(snapped)
+}
+
+/// Snap the vertices of a mesh to a grid of size `grid`.
+///
+/// Vertices of polygons that would be flipped or collapsed by the snapping keep their position, so that the mesh stays
+/// valid.
+pub(crate) fn quantize_mesh(mesh: &mut polyanya::Mesh, grid: f32) {
+    let original = mesh
+        .vertices
+        .iter()
+        .map(|vertex| vertex.coords)
+        .collect::<Vec<_>>();
+    let mut snapped = original
+        .iter()
+        .map(|point| quantize(*point, grid))
+        .collect::<Vec<_>>();
+    let area = |points: &[Vec2], polygon: &polyanya::Polygon| {
+        signed_area(
+            &polygon
+                .vertices
+                .iter()
+                .map(|index| points[*index as usize])
+                .collect::<Vec<_>>(),
+        )
+    };
+    // Restoring a vertex can only change the polygons around it, repeat until none is broken. This ends at the latest
+    // when all vertices are restored.
+    loop {
+        let mut restored = false;
+        for polygon in &mesh.polygons {
+            let before = area(&original, polygon);
+            let after = area(&snapped, polygon);
+            if after == 0.0 || after.signum() != before.signum() {
+                for index in &polygon.vertices {
+                    let index = *index as usize;
+                    restored |= snapped[index] != original[index];
+                    snapped[index] = original[index];
+                }
+            }
+        }
+        if !restored {
+            break;
+        }
+    }
+    for (vertex, point) in mesh.vertices.iter_mut().zip(snapped) {
+        vertex.coords = point;
+    }
+}
+
+/// Simplify a closed polygon, removing points closer than `tolerance` to the simplified edges.
+pub(crate) fn simplify_polygon(polygon: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    if polygon.len() < 4 || tolerance <= 0.0 {
+        return polygon.to_vec();
+    }
+    // Split the polygon at the point furthest from the first one
+    let furthest = (1..polygon.len())
+        .max_by(|a, b| {
+            polygon[*a]
+                .distance_squared(polygon[0])
+                .total_cmp(&polygon[*b].distance_squared(polygon[0]))
+        })
+        .unwrap();
+    let mut closed = polygon.to_vec();
+    closed.push(polygon[0]);
+    let mut simplified = simplify_polyline(&closed[..=furthest], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_polyline(&closed[furthest..], tolerance));
+    simplified.pop();
+    simplified
+}
+
+/// Simplify a polyline with the Douglas-Peucker algorithm, keeping its first and last points.
+fn simplify_polyline(polyline: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    let (first, last) = (polyline[0], polyline[polyline.len() - 1]);
+    let furthest = (1..polyline.len() - 1)
+        .map(|index| {
+            let point = polyline[index];
+            (
+                index,
+                point.distance(closest_point_on_segment(point, first, last)),
+            )
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match furthest {
+        Some((index, distance)) if distance > tolerance => {
+            let mut simplified = simplify_polyline(&polyline[..=index], tolerance);
+            simplified.pop();
+            simplified.extend(simplify_polyline(&polyline[index..], tolerance));
+            simplified
+        }
+        _ => vec![first, last],
+    }
+}
+
+/// Rectangles covering the full cells of a grid, in cells, as `(first column, first row, columns, rows)`.
+///
+/// Rectangles are grown greedily: from the first full cell not yet covered, as far as possible along the row, then
+/// along the columns while the whole run is full.
+#[cfg(any(feature = "tilemap", feature = "ldtk", feature = "tiled"))]
+pub(crate) fn grid_rectangles(
+    columns: usize,
+    rows: usize,
+    is_full: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize, usize, usize)> {
+    let mut full = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| is_full(column, row))
+        .collect::<Vec<_>>();
+    let mut rectangles = vec![];
+    for y in 0..rows {
+        for x in 0..columns {
+            if !full[y * columns + x] {
+                continue;
+            }
+            let mut end_x = x + 1;
+            while end_x < columns && full[y * columns + end_x] {
+                end_x += 1;
+            }
+            let mut end_y = y + 1;
+            while end_y < rows && (x..end_x).all(|x| full[end_y * columns + x]) {
+                end_y += 1;
+            }
+            for row in y..end_y {
+                full[row * columns + x..row * columns + end_x].fill(false);
+            }
+            rectangles.push((x, y, end_x - x, end_y - y));
+        }
+    }
+    rectangles
+}
+
+#[cfg(all(test, any(feature = "tilemap", feature = "ldtk", feature = "tiled")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_cells_are_merged_in_rectangles() {
+        // ##.
+        // ##.
+        // ###
+        let cells = [[1, 1, 0], [1, 1, 0], [1, 1, 1]];
+        let rectangles = grid_rectangles(3, 3, |column, row| cells[row][column] != 0);
+        assert_eq!(rectangles, vec![(0, 0, 2, 3), (2, 2, 1, 1)]);
+    }
+}
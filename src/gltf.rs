@@ -0,0 +1,169 @@
+//! Create navmeshes and obstacles from the nodes of glTF scenes, tagged with custom properties.
+//!
+//! Custom properties are saved in the `extras` of glTF nodes, for example from the "Custom Properties" panel of
+//! Blender when exporting with "Include > Custom Properties". With the [`GltfNavMeshPlugin`], when a scene is spawned:
+//! - the meshes of nodes with `"navmesh": true` are converted to a [`NavMesh`] asset, added as a [`GltfNavMesh`] on
+//!   the mesh entity
+//! - the mesh entities of nodes with `"obstacle": true` get the [`GltfObstacle`] marker. Add a
+//!   [`NavmeshUpdaterPlugin<Aabb, GltfObstacle>`](crate::prelude::NavmeshUpdaterPlugin) to use them as obstacles of
+//!   the navmeshes built by the updater
+
+use bevy::{gltf::GltfExtras, prelude::*, transform::TransformSystem};
+use serde_json::Value;
+
+use crate::NavMesh;
+
+/// Navmesh created from the mesh of a glTF node tagged with `"navmesh": true`.
+///
+/// The [transform](NavMesh::transform) of the navmesh is set from the [`GlobalTransform`] of the mesh when the scene
+/// was spawned, so that [`NavMesh::transformed_path`] works with world coordinates.
+#[derive(Component, Debug, Clone)]
+pub struct GltfNavMesh(pub Handle<NavMesh>);
+
+/// Marker for the mesh entities of glTF nodes tagged with `"obstacle": true`.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct GltfObstacle;
+
+/// Plugin scanning spawned glTF scenes for nodes tagged as navmeshes or obstacles. See the [module](self)
+/// documentation.
+#[derive(Debug, Clone, Copy)]
+pub struct GltfNavMeshPlugin {
+    /// Hide the meshes used for navmeshes. Enabled by default.
+    pub hide_navmesh_nodes: bool,
+}
+
+impl Default for GltfNavMeshPlugin {
+    fn default() -> Self {
+        Self {
+            hide_navmesh_nodes: true,
+        }
+    }
+}
+
+/// Settings of the [`GltfNavMeshPlugin`].
+#[derive(Resource, Debug, Clone, Copy)]
+struct GltfNavMeshSettings {
+    hide_navmesh_nodes: bool,
+}
+
+impl Plugin for GltfNavMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GltfNavMeshSettings {
+            hide_navmesh_nodes: self.hide_navmesh_nodes,
+        })
+        .add_systems(
+            PostUpdate,
+            extract_from_extras.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Check if the extras of a node have `tag` set to `true`.
+fn is_tagged(extras: &GltfExtras, tag: &str) -> bool {
+    serde_json::from_str::<Value>(&extras.value)
+        .ok()
+        .and_then(|value| value.get(tag).and_then(Value::as_bool))
+        .unwrap_or(false)
+}
+
+fn extract_from_extras(
+    mut commands: Commands,
+    settings: Res<GltfNavMeshSettings>,
+    nodes: Query<(Entity, &GltfExtras, Option<&Children>), Added<GltfExtras>>,
+    mesh_entities: Query<(&Handle<Mesh>, &GlobalTransform)>,
+    meshes: Res<Assets<Mesh>>,
+    mut navmeshes: ResMut<Assets<NavMesh>>,
+) {
+    for (node, extras, children) in &nodes {
+        let is_navmesh = is_tagged(extras, "navmesh");
+        let is_obstacle = is_tagged(extras, "obstacle");
+        if !is_navmesh && !is_obstacle {
+            continue;
+        }
+        // Primitives of the mesh of a node are spawned as its children
+        for entity in std::iter::once(node).chain(children.into_iter().flatten().copied()) {
+            let Ok((mesh, transform)) = mesh_entities.get(entity) else {
+                continue;
+            };
+            if is_obstacle {
+                commands.entity(entity).insert(GltfObstacle);
+            }
+            if is_navmesh {
+                let Some(mesh) = meshes.get(mesh) else {
+                    warn!("mesh of glTF navmesh node {:?} is not loaded", node);
+                    continue;
+                };
+                let mut navmesh = NavMesh::from_bevy_mesh(mesh);
+                navmesh.set_transform(Transform::from_matrix(
+                    navmesh.transform().compute_matrix() * transform.compute_matrix().inverse(),
+                ));
+                let mut entity = commands.entity(entity);
+                entity.insert(GltfNavMesh(navmeshes.add(navmesh)));
+                if settings.hide_navmesh_nodes {
+                    entity.insert(Visibility::Hidden);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VleueNavigatorPlugin;
+
+    #[test]
+    fn navmesh_node_maps_world_points_on_its_mesh() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            TransformPlugin,
+            VleueNavigatorPlugin,
+            GltfNavMeshPlugin::default(),
+        ))
+        .init_asset::<Mesh>();
+        let mesh = app
+            .world_mut()
+            .resource_mut::<Assets<Mesh>>()
+            .add(Plane3d::new(Vec3::Y, Vec2::ONE));
+        let mut primitive = Entity::PLACEHOLDER;
+        app.world_mut()
+            .spawn((
+                GltfExtras {
+                    value: r#"{"navmesh": true}"#.to_string(),
+                },
+                TransformBundle::from_transform(Transform::from_xyz(10.0, 0.0, 5.0)),
+            ))
+            .with_children(|node| {
+                primitive = node
+                    .spawn((
+                        mesh,
+                        TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, 0.0)),
+                        Visibility::Visible,
+                    ))
+                    .id();
+            });
+        app.update();
+
+        let handle = &app.world().get::<GltfNavMesh>(primitive).unwrap().0;
+        let navmesh = app
+            .world()
+            .resource::<Assets<NavMesh>>()
+            .get(handle)
+            .unwrap();
+        assert!(navmesh.transformed_is_in_mesh(Vec3::new(10.5, 1.0, 5.5)));
+        assert!(navmesh.transformed_is_in_mesh(Vec3::new(9.2, 1.0, 4.2)));
+        assert!(!navmesh.transformed_is_in_mesh(Vec3::new(0.5, 1.0, 0.5)));
+        assert!(!navmesh.transformed_is_in_mesh(Vec3::new(12.0, 1.0, 5.0)));
+        // World points on the mesh are on the navmesh plane
+        let on_plane = navmesh
+            .transform()
+            .transform_point(Vec3::new(11.0, 1.0, 6.0));
+        assert!(on_plane.z.abs() < 1e-5);
+        assert_eq!(
+            app.world().get::<Visibility>(primitive),
+            Some(&Visibility::Hidden)
+        );
+    }
+}
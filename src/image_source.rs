@@ -0,0 +1,235 @@
+use bevy::{
+    log::warn,
+    math::{IVec2, Rect, Vec2},
+    render::{render_resource::TextureFormat, texture::Image},
+    utils::HashMap,
+};
+use polyanya::Triangulation;
+
+use crate::geometry::{contains_point, counter_clockwise, signed_area, simplify_polygon};
+
+/// Walkable area painted in an [`Image`]: light pixels are walkable, dark pixels are blocked.
+///
+/// The contours of the walkable area are traced with marching squares, then simplified, to build the
+/// [`fixed`](crate::prelude::NavMeshSettings::fixed) triangulation of a navmesh.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageWalkableSource {
+    /// Area covered by the image, in the navmesh local space.
+    pub rect: Rect,
+    /// Luminance from which a pixel is walkable, between 0.0 and 1.0.
+    pub threshold: f32,
+    /// Maximum distance between the traced contours and the simplified edges, in the navmesh local space.
+    pub simplification: f32,
+}
+
+impl ImageWalkableSource {
+    /// Create a new source covering `rect`, with a threshold of 0.5 and no simplification.
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            threshold: 0.5,
+            simplification: 0.0,
+        }
+    }
+
+    /// Build the triangulation of the walkable area of `image`. Its outer edges are given by
+    /// [`walkable_area`](ImageWalkableSource::walkable_area), to set as the
+    /// [`outer_edges`](crate::prelude::NavMeshSettings::outer_edges) of the navmesh.
+    pub fn triangulation(&self, image: &Image) -> Option<Triangulation> {
+        let (outer_edges, obstacles) = self.walkable_area(image)?;
+        let mut triangulation = Triangulation::from_outer_edges(&outer_edges);
+        triangulation.add_obstacles(obstacles);
+        Some(triangulation)
+    }
+
+    /// Trace the walkable area of `image`, as outer edges and obstacles.
+    ///
+    /// The largest walkable area gives the outer edges, and blocked areas inside it become obstacles. Other walkable
+    /// areas are ignored. Returns `None` if nothing is walkable, if the image format is not supported, or if the image
+    /// data is shorter than its size. Supported formats have 8 bits per channel.
+    pub fn walkable_area(&self, image: &Image) -> Option<(Vec<Vec2>, Vec<Vec<Vec2>>)> {
+        let channels = match image.texture_descriptor.format {
+            TextureFormat::R8Unorm => 1,
+            TextureFormat::Rg8Unorm => 2,
+            TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8Unorm
+            | TextureFormat::Bgra8UnormSrgb => 4,
+            format => {
+                warn!("unsupported image format for walkable areas: {:?}", format);
+                return None;
+            }
+        };
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        if image.data.len() < (width * height) as usize * channels {
+            warn!(
+                "image data of {} bytes is too short for a {}x{} image",
+                image.data.len(),
+                width,
+                height
+            );
+            return None;
+        }
+        let walkable = |x: i32, y: i32| {
+            let pixel = &image.data[(y * width + x) as usize * channels..][..channels];
+            let colors = &pixel[..channels.min(3)];
+            let luminance =
+                colors.iter().map(|c| *c as f32).sum::<f32>() / colors.len() as f32 / 255.0;
+            luminance >= self.threshold
+        };
+
+        let scale = self.rect.size() / Vec2::new(width as f32, height as f32);
+        // Contour points are on a grid twice as fine as the pixels. Image rows go down, the navmesh y axis goes up.
+        let to_navmesh = |point: IVec2| {
+            Vec2::new(
+                self.rect.min.x + point.x as f32 / 2.0 * scale.x,
+                self.rect.max.y - point.y as f32 / 2.0 * scale.y,
+            )
+        };
+
+        let mut contours = trace_contours(width, height, walkable)
+            .into_iter()
+            .map(|contour| {
+                let area = signed_area(&contour.iter().map(|p| p.as_vec2()).collect::<Vec<_>>());
+                let points = contour.into_iter().map(to_navmesh).collect::<Vec<_>>();
+                (area, simplify_polygon(&points, self.simplification))
+            })
+            .filter(|(_, points)| points.len() > 2)
+            .collect::<Vec<_>>();
+
+        // Walkable areas are on the left of their contours, with a positive area
+        let outer = contours
+            .iter()
+            .enumerate()
+            .filter(|(_, (area, _))| *area > 0.0)
+            .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+            .map(|(index, _)| index)?;
+        let (_, outer) = contours.swap_remove(outer);
+
+        let obstacles = contours
+            .into_iter()
+            .filter(|(area, points)| *area < 0.0 && contains_point(&outer, points[0]))
+            .map(|(_, points)| points)
+            .collect();
+        Some((counter_clockwise(outer), obstacles))
+    }
+}
+
+/// Trace the closed contours around walkable pixels, with the walkable side on the left.
+///
+/// Points are on a grid twice as fine as the pixels: the center of the pixel `(x, y)` is `(2x + 1, 2y + 1)`.
+fn trace_contours(width: i32, height: i32, walkable: impl Fn(i32, i32) -> bool) -> Vec<Vec<IVec2>> {
+    let walkable = |x: i32, y: i32| x >= 0 && y >= 0 && x < width && y < height && walkable(x, y);
+    let mut next: HashMap<IVec2, IVec2> = HashMap::default();
+    // Cells are between the centers of four pixels, including outside the image which is never walkable
+    for y in -1..height {
+        for x in -1..width {
+            // Corners and edge midpoints, counter clockwise
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+            let values = corners.map(|(x, y)| walkable(x, y));
+            let midpoints = [
+                IVec2::new(2 * x + 2, 2 * y + 1),
+                IVec2::new(2 * x + 3, 2 * y + 2),
+                IVec2::new(2 * x + 2, 2 * y + 3),
+                IVec2::new(2 * x + 1, 2 * y + 2),
+            ];
+            for exit in 0..4 {
+                // Going from a walkable to a blocked corner, the contour exits the walkable area
+                if !values[exit] || values[(exit + 1) % 4] {
+                    continue;
+                }
+                // Connect to the previous edge entering the walkable area
+                if let Some(enter) = (1..4)
+                    .map(|offset| (exit + 4 - offset) % 4)
+                    .find(|edge| !values[*edge] && values[(*edge + 1) % 4])
+                {
+                    next.insert(midpoints[exit], midpoints[enter]);
+                }
+            }
+        }
+    }
+
+    let mut contours = vec![];
+    while let Some(&start) = next.keys().next() {
+        let mut contour = vec![start];
+        let mut current = next.remove(&start).unwrap();
+        while current != start {
+            contour.push(current);
+            let Some(following) = next.remove(&current) else {
+                break;
+            };
+            current = following;
+        }
+        contours.push(contour);
+    }
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension},
+    };
+
+    use super::*;
+    use crate::NavMesh;
+
+    /// A 5x5 image, walkable except for its center pixel.
+    fn image_with_hole() -> Image {
+        let mut data = vec![255; 25];
+        data[12] = 0;
+        Image::new(
+            Extent3d {
+                width: 5,
+                height: 5,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::R8Unorm,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    #[test]
+    fn blocked_pixel_is_a_hole_in_the_walkable_area() {
+        let contours = trace_contours(3, 3, |x, y| (x, y) != (1, 1));
+        let mut areas = contours
+            .iter()
+            .map(|contour| signed_area(&contour.iter().map(|p| p.as_vec2()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        areas.sort_by(f32::total_cmp);
+
+        assert_eq!(areas.len(), 2);
+        assert!(areas[0] < 0.0);
+        assert!(areas[1] > 0.0);
+        assert!(areas[1] > -areas[0]);
+    }
+
+    #[test]
+    fn triangulation_has_a_hole_for_blocked_pixels() {
+        let source = ImageWalkableSource::new(Rect::new(0.0, 0.0, 5.0, 5.0));
+        let mut mesh = source
+            .triangulation(&image_with_hole())
+            .unwrap()
+            .as_navmesh();
+        mesh.bake();
+        let navmesh = NavMesh::from_polyanya_mesh(mesh);
+
+        assert!(navmesh.is_in_mesh(Vec2::new(1.0, 1.0)));
+        assert!(navmesh.is_in_mesh(Vec2::new(4.0, 2.5)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(2.5, 2.5)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(6.0, 2.5)));
+    }
+
+    #[test]
+    fn short_image_data_is_rejected() {
+        let mut image = image_with_hole();
+        image.data.truncate(20);
+
+        assert!(ImageWalkableSource::new(Rect::new(0.0, 0.0, 5.0, 5.0))
+            .triangulation(&image)
+            .is_none());
+    }
+}
@@ -11,7 +11,7 @@
     missing_docs
 )]
 
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use bevy::math::Vec3Swizzles;
 use bevy::reflect::TypePath;
@@ -23,16 +23,51 @@ use bevy::{
 };
 use itertools::Itertools;
 
+mod analysis;
 pub mod asset_loaders;
-mod obstacles;
+mod baker;
+#[cfg(feature = "behaviors")]
+pub mod behaviors;
+mod builder;
+pub mod click_to_move;
+pub mod crowd;
+#[cfg(feature = "debug-with-gizmos")]
+mod debug;
+mod editing;
+mod fields;
+mod formation;
+mod geometry;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+mod image_source;
+mod links;
+mod lookup;
+#[cfg(any(feature = "ldtk", feature = "tiled"))]
+pub mod map_loaders;
+pub mod obstacles;
+mod path;
+#[cfg(any(feature = "svg", feature = "geojson"))]
+pub mod polygon_loaders;
+mod query;
+mod spatial;
+#[cfg(feature = "tilemap")]
+pub mod tilemap;
 mod updater;
 
 /// Prelude for imports
 pub mod prelude {
-    pub use crate::obstacles::{primitive::PrimitiveObstacle, ObstacleSource};
+    pub use crate::obstacles::{
+        polygon::PolygonObstacle, primitive::PrimitiveObstacle, CachableObstacle, ObstacleLayers,
+        ObstacleRadiusOverride, ObstacleSource, TransformProvider,
+    };
     pub use crate::updater::{
-        NavMeshBundle, NavMeshSettings, NavMeshStatus, NavMeshUpdateMode,
-        NavMeshUpdateModeBlocking, NavmeshUpdaterPlugin,
+        CachableObstaclePromotion, NavMeshBuildError, NavMeshBuildReport, NavMeshBundle,
+        NavMeshCommandsExt, NavMeshData, NavMeshPriority, NavMeshPriorityAnchor,
+        NavMeshPriorityFromAnchors, NavMeshPublishLimit, NavMeshSettings, NavMeshStaticTransform,
+        NavMeshStatus, NavMeshUpdateBudget, NavMeshUpdateMode, NavMeshUpdateModeBlocking,
+        NavMeshUpdateModeTimeSliced, NavMeshUpdateTaskPool, NavmeshUpdateTask,
+        NavmeshUpdaterPlugin, ObstacleAffectedNavMesh, ObstacleChange, StoreNavMeshData,
+        NAVMESH_BUILD_SKIPPED,
     };
     pub use crate::{NavMesh, VleueNavigatorPlugin};
 }
@@ -44,19 +79,97 @@ pub struct VleueNavigatorPlugin;
 impl Plugin for VleueNavigatorPlugin {
     fn build(&self, app: &mut App) {
         app.register_asset_loader(asset_loaders::NavMeshPolyanyaLoader)
-            .init_asset::<NavMesh>();
+            .register_asset_loader(asset_loaders::NavMeshLoader::default())
+            .init_asset::<NavMesh>()
+            .observe(updater::reserve_navmesh_handle);
+        #[cfg(any(feature = "svg", feature = "geojson"))]
+        app.init_asset::<polygon_loaders::NavMeshPolygons>();
+        #[cfg(feature = "svg")]
+        app.register_asset_loader(polygon_loaders::SvgPolygonsLoader);
+        #[cfg(feature = "geojson")]
+        app.register_asset_loader(polygon_loaders::GeoJsonPolygonsLoader);
+        #[cfg(any(feature = "ldtk", feature = "tiled"))]
+        app.init_asset::<map_loaders::NavMeshLevels>();
+        #[cfg(feature = "ldtk")]
+        app.register_asset_loader(map_loaders::LdtkNavMeshLoader);
+        #[cfg(feature = "tiled")]
+        app.register_asset_loader(map_loaders::TiledNavMeshLoader);
+        // Apps without gizmos, like servers or tests, can use the feature without drawing anything
+        #[cfg(feature = "debug-with-gizmos")]
+        app.add_systems(
+            Update,
+            debug::display_navmesh.run_if(resource_exists::<GizmoConfigStore>),
+        );
     }
 }
 
 /// A path between two points, in 3 dimensions using [`NavMesh::transform`].
 #[derive(Debug, PartialEq)]
 pub struct TransformedPath {
-    /// Length of the path.
+    /// Length of the path, in world space.
     pub length: f32,
+    /// Start of the path.
+    pub start: Vec3,
     /// Coordinates for each step of the path. The destination is the last step.
     pub path: Vec<Vec3>,
+    /// Length of each segment of the path in world space, ending at the step with the same index. The first
+    /// segment starts from the start of the path.
+    pub segment_lengths: Vec<f32>,
+    /// [Version](NavMesh::version) of the navmesh this path was computed on.
+    pub version: u32,
 }
 
+impl TransformedPath {
+    /// Check if this path was computed on an older version of `navmesh`, and should be computed again.
+    pub fn is_stale(&self, navmesh: &NavMesh) -> bool {
+        self.version != navmesh.version()
+    }
+
+    /// Iterate over the segments of the path, from the start to the destination.
+    pub fn segments(&self) -> impl Iterator<Item = PathSegment> + '_ {
+        let mut remaining = self.length;
+        std::iter::once(self.start)
+            .chain(self.path.iter().copied())
+            .zip(self.path.iter().zip(&self.segment_lengths))
+            .map(move |(start, (end, length))| {
+                remaining = (remaining - length).max(0.0);
+                PathSegment {
+                    start,
+                    end: *end,
+                    length: *length,
+                    remaining,
+                }
+            })
+    }
+}
+
+/// A segment of a [`TransformedPath`], in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathSegment {
+    /// Start of the segment.
+    pub start: Vec3,
+    /// End of the segment, which is a step of the path.
+    pub end: Vec3,
+    /// Length of the segment.
+    pub length: f32,
+    /// Length of the path left after this segment.
+    pub remaining: f32,
+}
+
+pub use analysis::{
+    AdjacencyNode, NavMeshAdjacency, NavMeshDiff, NavMeshStats, Portal, RegionId, RegionInfo,
+};
+pub use baker::NavMeshBaker;
+pub use builder::{NavMeshSettingsBuilder, NavMeshSettingsError};
+#[cfg(feature = "debug-with-gizmos")]
+pub use debug::{DebugObstaclePolygons, NavMeshDebug};
+pub use editing::{EditError, MergeError};
+pub use fields::{DistanceField, FlowField};
+pub use formation::{Formation, FormationPath};
+pub use image_source::ImageWalkableSource;
+pub use links::{LinkedPath, LinkedStep, OffMeshLink, TransformedLinkedPath};
+pub use lookup::{NavMeshConnection, NavMeshLookup, WorldPathfinder};
+pub use path::{PathOptions, TurningPath, TurningPathPiece};
 use polyanya::Trimesh;
 pub use polyanya::{Path, Triangulation};
 
@@ -65,6 +178,17 @@ pub use polyanya::{Path, Triangulation};
 pub struct NavMesh {
     mesh: Arc<polyanya::Mesh>,
     transform: Transform,
+    agent_radius: f32,
+    variants: Vec<NavMesh>,
+    version: u32,
+    islands: Arc<OnceLock<Vec<usize>>>,
+    boundary: Arc<OnceLock<Vec<[Vec2; 2]>>>,
+    spatial_index: Arc<OnceLock<spatial::SpatialIndex>>,
+    polygon_index: Arc<OnceLock<spatial::GridIndex>>,
+    /// Hash and bounds of each obstacle polygon used in the build, to find the area changed by the next build.
+    obstacle_footprints: Arc<Vec<(u64, Rect)>>,
+    changed_area: Option<Rect>,
+    links: Vec<OffMeshLink>,
 }
 
 impl NavMesh {
@@ -73,6 +197,16 @@ impl NavMesh {
         NavMesh {
             mesh: Arc::new(mesh),
             transform: Transform::IDENTITY,
+            agent_radius: 0.0,
+            variants: vec![],
+            version: 0,
+            islands: Default::default(),
+            boundary: Default::default(),
+            spatial_index: Default::default(),
+            polygon_index: Default::default(),
+            obstacle_footprints: Default::default(),
+            changed_area: None,
+            links: vec![],
         }
     }
 
@@ -119,6 +253,55 @@ impl NavMesh {
         Self::from_bevy_mesh_and_then(mesh, |_| {})
     }
 
+    /// Creates a [`NavMesh`] from the walkable parts of a non planar Bevy [`Mesh`], like a terrain.
+    ///
+    /// Triangles facing `up` with a slope of at most `max_slope` radians are kept, and projected on the plane
+    /// perpendicular to `up`. Triangles facing down or too steep are removed. Returns `None` if no triangle is walkable.
+    ///
+    /// The navmesh has a single layer: walkable surfaces above each other, like the floors of a building, must be split
+    /// in several meshes. Only supports meshes with the [`PrimitiveTopology::TriangleList`].
+    pub fn from_walkable_geometry(mesh: &Mesh, up: Dir3, max_slope: f32) -> Option<NavMesh> {
+        let rotation = Quat::from_rotation_arc(*up, Vec3::Z);
+        let positions = get_vectors(mesh, Mesh::ATTRIBUTE_POSITION)
+            .map(|vertex| rotation.mul_vec3(vertex))
+            .collect::<Vec<_>>();
+
+        let mut remap = vec![None; positions.len()];
+        let mut vertices = vec![];
+        let triangles = mesh
+            .indices()?
+            .iter()
+            .tuples::<(_, _, _)>()
+            .filter(|(a, b, c)| {
+                let normal = (positions[*b] - positions[*a])
+                    .cross(positions[*c] - positions[*a])
+                    .normalize_or_zero();
+                normal.z > 0.0 && normal.angle_between(Vec3::Z) <= max_slope
+            })
+            .map(|(a, b, c)| {
+                [a, b, c].map(|index| {
+                    *remap[index].get_or_insert_with(|| {
+                        vertices.push(positions[index].xy());
+                        vertices.len() - 1
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        if triangles.is_empty() {
+            return None;
+        }
+
+        let polyanya_mesh = Trimesh {
+            vertices,
+            triangles,
+        }
+        .try_into()
+        .ok()?;
+        let mut navmesh = Self::from_polyanya_mesh(polyanya_mesh);
+        navmesh.transform = Transform::from_rotation(rotation);
+        Some(navmesh)
+    }
+
     /// Build a navmesh from its edges and obstacles.
     ///
     /// Obstacles will be merged in case some are overlapping, and mesh will be simplified to reduce the number of polygons.
@@ -151,10 +334,14 @@ impl NavMesh {
 
     /// Set the [`delta`](polyanya::Mesh::delta) value of the navmesh.
     pub fn set_delta(&mut self, delta: f32) -> bool {
+        let mut updated = true;
+        for variant in &mut self.variants {
+            updated &= variant.set_delta(delta);
+        }
         if let Some(mesh) = Arc::get_mut(&mut self.mesh) {
             debug!("setting mesh delta to {}", delta);
             mesh.set_delta(delta);
-            true
+            updated
         } else {
             warn!("failed setting mesh delta to {}", delta);
             false
@@ -179,7 +366,7 @@ impl NavMesh {
         let inner_from = self.transform.transform_point(from).xy();
         let inner_to = self.transform.transform_point(to).xy();
         let path = self.mesh.get_path(inner_from, inner_to).await;
-        path.map(|path| self.transform_path(path, from, to))
+        path.map(|path| self.transform_path(path, from))
     }
 
     /// Get a path between two points
@@ -195,30 +382,78 @@ impl NavMesh {
         let inner_from = self.transform.transform_point(from).xy();
         let inner_to = self.transform.transform_point(to).xy();
         let path = self.mesh.path(inner_from, inner_to);
-        path.map(|path| self.transform_path(path, from, to))
+        path.map(|path| self.transform_path(path, from))
     }
 
-    fn transform_path(&self, path: Path, from: Vec3, to: Vec3) -> TransformedPath {
+    fn transform_path(&self, path: Path, from: Vec3) -> TransformedPath {
         let inverse_transform = self.inverse_transform();
+        let path = path
+            .path
+            .into_iter()
+            .map(|coords| inverse_transform.transform_point((coords, 0.).into()))
+            .collect::<Vec<_>>();
+        let segment_lengths = std::iter::once(from)
+            .chain(path.iter().copied())
+            .zip(path.iter())
+            .map(|(start, end)| start.distance(*end))
+            .collect::<Vec<_>>();
         TransformedPath {
-            length: from.distance(to),
-            path: path
-                .path
-                .into_iter()
-                .map(|coords| inverse_transform.transform_point((coords, 0.).into()))
-                .collect(),
+            length: segment_lengths.iter().sum(),
+            start: from,
+            segment_lengths,
+            path,
+            version: self.version,
+        }
+    }
+
+    /// Version of the navmesh, increased every time it's rebuilt by the [`NavmeshUpdaterPlugin`](crate::prelude::NavmeshUpdaterPlugin).
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub(crate) fn set_version(&mut self, version: u32) {
+        self.version = version;
+        for variant in &mut self.variants {
+            variant.set_version(version);
+        }
+    }
+
+    /// Area of the mesh that changed since the previous [version](NavMesh::version), in the mesh local space.
+    ///
+    /// This is the union of the footprints of the obstacles added, removed or moved by the
+    /// [`NavmeshUpdaterPlugin`](crate::prelude::NavmeshUpdaterPlugin), grown by the agent radius. `None` when it's not
+    /// known, for the first version, after a change of settings, or for navmeshes not built by the updater: the whole
+    /// mesh should then be considered changed.
+    pub fn changed_area(&self) -> Option<Rect> {
+        self.changed_area
+    }
+
+    /// Check if the navmesh may have changed in `area`, in the mesh local space, since `version`.
+    ///
+    /// Use this to only invalidate caches like paths when the change overlaps them. This is only precise when the
+    /// navmesh is one version ahead, as only the [last change](NavMesh::changed_area) is kept.
+    pub fn changed_since(&self, version: u32, area: Rect) -> bool {
+        if version == self.version {
+            return false;
+        }
+        match self.changed_area {
+            Some(changed) if version.wrapping_add(1) == self.version => {
+                changed.min.cmple(area.max).all() && area.min.cmple(changed.max).all()
+            }
+            _ => true,
         }
     }
 
     /// Check if a 3d point is in a navigationable part of the mesh, using the [`Mesh::transform`]
     pub fn transformed_is_in_mesh(&self, point: Vec3) -> bool {
         let point = self.transform.transform_point(point).xy();
-        self.mesh.point_in_mesh(point)
+        self.is_in_mesh(point)
     }
 
     /// Check if a point is in a navigationable part of the mesh
     pub fn is_in_mesh(&self, point: Vec2) -> bool {
-        self.mesh.point_in_mesh(point)
+        // Points not in a polygon can still be in the mesh, within its delta
+        matches!(self.indexed_polygon_at(point), Some(Some(_))) || self.mesh.point_in_mesh(point)
     }
 
     /// The transform used to convert world coordinates into mesh coordinates.
@@ -232,6 +467,57 @@ impl NavMesh {
     /// It will be used to transform a 3d point to a 2d point where the `z` axis can be ignored
     pub fn set_transform(&mut self, transform: Transform) {
         self.transform = transform;
+        for variant in &mut self.variants {
+            variant.set_transform(transform);
+        }
+    }
+
+    /// Shift the navmesh by `offset` in world space, without rebuilding it, for floating origin worlds.
+    ///
+    /// When the world is rebased so that a position `p` becomes `p + offset`, this keeps the navmesh aligned with it.
+    /// Only the [`NavMesh::transform`] of the navmesh and its variants is changed, paths already computed in world space
+    /// should be shifted by the same offset.
+    pub fn rebase(&mut self, offset: Vec3) {
+        let mut transform = self.transform;
+        transform.translation -= transform.rotation * (transform.scale * offset);
+        self.set_transform(transform);
+    }
+
+    /// Radius of the agents this navmesh was built for. Obstacles were inflated by this radius.
+    pub fn agent_radius(&self) -> f32 {
+        self.agent_radius
+    }
+
+    /// Get the navmesh built for agents of the given `radius`.
+    ///
+    /// This is the navmesh with the smallest [`NavMesh::agent_radius`] that is at least `radius`, among this navmesh
+    /// and the variants built from [`NavMeshSettings::agent_radius_variants`](crate::prelude::NavMeshSettings::agent_radius_variants).
+    /// Returns `None` if no navmesh was built for a large enough radius.
+    pub fn for_agent_radius(&self, radius: f32) -> Option<&NavMesh> {
+        std::iter::once(self)
+            .chain(self.variants.iter())
+            .filter(|navmesh| navmesh.agent_radius >= radius)
+            .min_by(|a, b| a.agent_radius.total_cmp(&b.agent_radius))
+    }
+
+    /// Get a path between two points for an agent of the given `radius`.
+    ///
+    /// See [`NavMesh::for_agent_radius`] for how the navmesh is selected.
+    pub fn path_for_agent_radius(&self, radius: f32, from: Vec2, to: Vec2) -> Option<Path> {
+        self.for_agent_radius(radius)?.path(from, to)
+    }
+
+    /// Get a path between two points for an agent of the given `radius`.
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`].
+    /// See [`NavMesh::for_agent_radius`] for how the navmesh is selected.
+    pub fn transformed_path_for_agent_radius(
+        &self,
+        radius: f32,
+        from: Vec3,
+        to: Vec3,
+    ) -> Option<TransformedPath> {
+        self.for_agent_radius(radius)?.transformed_path(from, to)
     }
 
     /// Creates a [`Mesh`] from this [`NavMesh`], suitable for debugging the surface.
@@ -291,11 +577,7 @@ impl NavMesh {
 
     #[inline]
     fn inverse_transform(&self) -> Transform {
-        Transform {
-            translation: -self.transform.translation,
-            rotation: self.transform.rotation.inverse(),
-            scale: 1.0 / self.transform.scale,
-        }
+        Transform::from_matrix(self.transform.compute_matrix().inverse())
     }
 }
 
@@ -345,6 +627,51 @@ mod tests {
         assert_same_navmesh(expected_navmesh, actual_navmesh);
     }
 
+    #[test]
+    fn rebase_keeps_navmesh_aligned() {
+        let mut navmesh = NavMesh::from_polyanya_mesh(
+            Trimesh {
+                vertices: vec![
+                    Vec2::new(0., 0.),
+                    Vec2::new(10., 0.),
+                    Vec2::new(10., 10.),
+                    Vec2::new(0., 10.),
+                ],
+                triangles: vec![[0, 1, 2], [0, 2, 3]],
+            }
+            .try_into()
+            .unwrap(),
+        );
+        let transform = Transform::from_xyz(3.0, -2.0, 1.0)
+            .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2))
+            .with_scale(Vec3::splat(2.0));
+        navmesh.set_transform(transform);
+        let from = navmesh
+            .inverse_transform()
+            .transform_point(Vec3::new(1.0, 1.0, 0.0));
+        let to = navmesh
+            .inverse_transform()
+            .transform_point(Vec3::new(9.0, 8.0, 0.0));
+        let before = navmesh.transformed_path(from, to).unwrap();
+
+        let offset = Vec3::new(-1000.0, 20.0, 500.0);
+        navmesh.rebase(offset);
+
+        let point = Vec3::new(4.0, 0.0, -7.0);
+        assert!(navmesh
+            .transform()
+            .transform_point(point + offset)
+            .abs_diff_eq(transform.transform_point(point), 1e-3));
+
+        let after = navmesh
+            .transformed_path(from + offset, to + offset)
+            .unwrap();
+        assert_eq!(before.path.len(), after.path.len());
+        for (before, after) in before.path.iter().zip(&after.path) {
+            assert!((*before + offset).abs_diff_eq(*after, 1e-3));
+        }
+    }
+
     #[test]
     fn rotated_mesh_generates_expected_navmesh() {
         let expected_navmesh = NavMesh::from_polyanya_mesh(
@@ -386,6 +713,78 @@ mod tests {
         assert_same_navmesh(expected_navmesh, actual_navmesh);
     }
 
+    #[test]
+    fn closest_point_snaps_to_mesh_boundary() {
+        let navmesh = NavMesh::from_polyanya_mesh(
+            Trimesh {
+                vertices: vec![
+                    Vec2::new(0., 0.),
+                    Vec2::new(4., 0.),
+                    Vec2::new(4., 4.),
+                    Vec2::new(0., 4.),
+                ],
+                triangles: vec![[0, 1, 2], [0, 2, 3]],
+            }
+            .try_into()
+            .unwrap(),
+        );
+
+        assert_eq!(
+            navmesh.closest_point(Vec2::new(2., 2.)),
+            Some(Vec2::new(2., 2.))
+        );
+        assert_eq!(
+            navmesh.closest_point(Vec2::new(6., 1.)),
+            Some(Vec2::new(4., 1.))
+        );
+        assert_eq!(
+            navmesh.closest_point(Vec2::new(-1., -1.)),
+            Some(Vec2::new(0., 0.))
+        );
+    }
+
+    #[test]
+    fn raycast_stops_at_mesh_boundary() {
+        let navmesh = NavMesh::from_polyanya_mesh(
+            Trimesh {
+                vertices: vec![
+                    Vec2::new(0., 0.),
+                    Vec2::new(4., 0.),
+                    Vec2::new(4., 4.),
+                    Vec2::new(0., 4.),
+                ],
+                triangles: vec![[0, 1, 2], [0, 2, 3]],
+            }
+            .try_into()
+            .unwrap(),
+        );
+
+        assert_eq!(navmesh.raycast(Vec2::new(1., 1.), Vec2::new(3., 3.)), None);
+        assert_eq!(
+            navmesh.raycast(Vec2::new(2., 2.), Vec2::new(6., 2.)),
+            Some(Vec2::new(4., 2.))
+        );
+        assert!(!navmesh.is_reachable_in_straight_line(Vec2::new(2., 2.), Vec2::new(2., -1.)));
+    }
+
+    #[test]
+    fn path_segments_track_remaining_length() {
+        let path = TransformedPath {
+            length: 7.,
+            start: Vec3::ZERO,
+            path: vec![Vec3::new(3., 0., 0.), Vec3::new(3., 4., 0.)],
+            segment_lengths: vec![3., 4.],
+            version: 0,
+        };
+        let segments = path.segments().collect::<Vec<_>>();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].start, Vec3::ZERO);
+        assert_eq!(segments[0].remaining, 4.);
+        assert_eq!(segments[1].start, Vec3::new(3., 0., 0.));
+        assert_eq!(segments[1].end, Vec3::new(3., 4., 0.));
+        assert_eq!(segments[1].remaining, 0.);
+    }
+
     fn assert_same_navmesh(expected: NavMesh, actual: NavMesh) {
         let expected_mesh = expected.mesh;
         let actual_mesh = actual.mesh;
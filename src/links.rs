@@ -0,0 +1,368 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use polyanya::Path;
+
+use crate::{NavMesh, TransformedPath};
+
+/// A connection between two points of a navmesh that can't be walked, like a jump, a ladder or a teleporter.
+///
+/// Links are only used by [`NavMesh::path_with_links`], which searches paths between the ends of all the links of the
+/// navmesh: its cost grows with the square of the number of links, keep them few on each navmesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OffMeshLink {
+    /// Start of the link, in the mesh local space.
+    pub start: Vec2,
+    /// End of the link, in the mesh local space.
+    pub end: Vec2,
+    /// Cost of taking the link, compared to the length of walked paths.
+    pub cost: f32,
+    /// If the link can also be taken from its end to its start.
+    pub bidirectional: bool,
+}
+
+impl OffMeshLink {
+    /// Create a link that can only be taken from `start` to `end`, with a cost of the distance between them.
+    pub fn new(start: Vec2, end: Vec2) -> Self {
+        Self {
+            start,
+            end,
+            cost: start.distance(end),
+            bidirectional: false,
+        }
+    }
+
+    /// Set the cost of the link.
+    pub fn with_cost(mut self, cost: f32) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    /// Allow taking the link in both directions.
+    pub fn bidirectional(mut self) -> Self {
+        self.bidirectional = true;
+        self
+    }
+}
+
+/// A step of a [`LinkedPath`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkedStep {
+    /// Position reached by this step.
+    pub position: Vec2,
+    /// Index of the [`OffMeshLink`] taken to reach this position, or `None` if it's reached by walking.
+    pub link: Option<usize>,
+}
+
+/// A path that can go through [`OffMeshLink`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedPath {
+    /// Cost of the path: the length of the walked parts, plus the cost of the links taken.
+    pub cost: f32,
+    /// Steps of the path. The destination is the last step.
+    pub path: Vec<LinkedStep>,
+}
+
+/// A [`LinkedPath`] in 3 dimensions using [`NavMesh::transform`].
+#[derive(Debug, PartialEq)]
+pub struct TransformedLinkedPath {
+    /// Cost of the path: the length of the walked parts, plus the cost of the links taken, in the mesh local space.
+    pub cost: f32,
+    /// Steps of the path. Its length is the distance covered in world space: links count for the straight line
+    /// between their ends, not for their cost.
+    pub path: TransformedPath,
+    /// Index of the [`OffMeshLink`] taken to reach each step of the path, or `None` if it's reached by walking.
+    pub links: Vec<Option<usize>>,
+}
+
+/// Node waiting to be visited, ordered by lowest cost first.
+pub(crate) struct Candidate {
+    pub(crate) cost: f32,
+    pub(crate) node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl NavMesh {
+    /// Off-mesh links of this navmesh.
+    pub fn links(&self) -> &[OffMeshLink] {
+        &self.links
+    }
+
+    /// Add an off-mesh link to this navmesh.
+    ///
+    /// Links are not used by [`NavMesh::path`], use [`NavMesh::path_with_links`].
+    pub fn add_link(&mut self, link: OffMeshLink) {
+        self.links.push(link);
+    }
+
+    /// Get a path between two points, that can go through the [off-mesh links](NavMesh::links).
+    ///
+    /// Points are in the mesh local space. The search computes paths between the ends of the links, its cost grows with
+    /// the square of the number of links. Use [`NavMesh::transformed_path_with_links`] for points in world space.
+    pub fn path_with_links(&self, from: Vec2, to: Vec2) -> Option<LinkedPath> {
+        // Nodes are the start, the destination, then the start and end of each link
+        let positions = [from, to]
+            .into_iter()
+            .chain(self.links.iter().flat_map(|link| [link.start, link.end]))
+            .collect::<Vec<_>>();
+        let link_from = |node: usize| -> Option<(usize, usize)> {
+            if node < 2 {
+                return None;
+            }
+            let (index, is_end) = ((node - 2) / 2, (node - 2) % 2 == 1);
+            let link = &self.links[index];
+            match is_end {
+                false => Some((index, node + 1)),
+                true if link.bidirectional => Some((index, node - 1)),
+                true => None,
+            }
+        };
+
+        let mut costs = vec![f32::INFINITY; positions.len()];
+        let mut previous: Vec<Option<(usize, Vec<LinkedStep>)>> = vec![None; positions.len()];
+        let mut visited = vec![false; positions.len()];
+        let mut queue = BinaryHeap::new();
+        costs[0] = 0.0;
+        queue.push(Candidate { cost: 0.0, node: 0 });
+
+        while let Some(Candidate { cost, node }) = queue.pop() {
+            if visited[node] {
+                continue;
+            }
+            visited[node] = true;
+            if node == 1 {
+                break;
+            }
+            let mut edges = vec![];
+            if let Some((index, other)) = link_from(node) {
+                let link = &self.links[index];
+                let step = LinkedStep {
+                    position: positions[other],
+                    link: Some(index),
+                };
+                edges.push((other, link.cost, vec![step]));
+            }
+            for other in 1..positions.len() {
+                if other == node || visited[other] {
+                    continue;
+                }
+                if let Some(path) = self.mesh.path(positions[node], positions[other]) {
+                    let steps = path
+                        .path
+                        .into_iter()
+                        .map(|position| LinkedStep {
+                            position,
+                            link: None,
+                        })
+                        .collect();
+                    edges.push((other, path.length, steps));
+                }
+            }
+            for (other, edge_cost, steps) in edges {
+                if cost + edge_cost < costs[other] {
+                    costs[other] = cost + edge_cost;
+                    previous[other] = Some((node, steps));
+                    queue.push(Candidate {
+                        cost: costs[other],
+                        node: other,
+                    });
+                }
+            }
+        }
+
+        if !visited[1] {
+            return None;
+        }
+        let mut path = vec![];
+        let mut node = 1;
+        while let Some((from, steps)) = previous[node].take() {
+            path.splice(0..0, steps);
+            node = from;
+        }
+        Some(LinkedPath {
+            cost: costs[1],
+            path,
+        })
+    }
+
+    /// Get a path between two points, that can go through the [off-mesh links](NavMesh::links).
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`]. See [`NavMesh::path_with_links`].
+    pub fn transformed_path_with_links(
+        &self,
+        from: Vec3,
+        to: Vec3,
+    ) -> Option<TransformedLinkedPath> {
+        let inner_from = self.transform.transform_point(from).xy();
+        let inner_to = self.transform.transform_point(to).xy();
+        let linked = self.path_with_links(inner_from, inner_to)?;
+        let path = Path {
+            length: linked.cost,
+            path: linked.path.iter().map(|step| step.position).collect(),
+        };
+        Some(TransformedLinkedPath {
+            cost: linked.cost,
+            path: self.transform_path(path, from),
+            links: linked.path.iter().map(|step| step.link).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two squares from (0, 0) to (4, 4) and from (6, 0) to (10, 4), that are not connected.
+    fn islands() -> NavMesh {
+        NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 4.0),
+                Vec2::new(0.0, 4.0),
+            ],
+            vec![vec![
+                Vec2::new(4.0, -1.0),
+                Vec2::new(6.0, -1.0),
+                Vec2::new(6.0, 5.0),
+                Vec2::new(4.0, 5.0),
+            ]],
+        )
+    }
+
+    #[test]
+    fn one_way_link() {
+        let mut navmesh = islands();
+        let (left, right) = (Vec2::new(1.0, 2.0), Vec2::new(9.0, 2.0));
+        assert!(navmesh.path_with_links(left, right).is_none());
+
+        navmesh.add_link(OffMeshLink::new(Vec2::new(3.0, 2.0), Vec2::new(7.0, 2.0)));
+        let path = navmesh.path_with_links(left, right).unwrap();
+        assert!((path.cost - 8.0).abs() < 1e-3);
+        assert_eq!(
+            path.path,
+            vec![
+                LinkedStep {
+                    position: Vec2::new(3.0, 2.0),
+                    link: None,
+                },
+                LinkedStep {
+                    position: Vec2::new(7.0, 2.0),
+                    link: Some(0),
+                },
+                LinkedStep {
+                    position: right,
+                    link: None,
+                },
+            ]
+        );
+        // The link can't be taken back, the target is unreachable
+        assert!(navmesh.path_with_links(right, left).is_none());
+    }
+
+    #[test]
+    fn bidirectional_link() {
+        let mut navmesh = islands();
+        navmesh
+            .add_link(OffMeshLink::new(Vec2::new(3.0, 2.0), Vec2::new(7.0, 2.0)).bidirectional());
+
+        let path = navmesh
+            .path_with_links(Vec2::new(9.0, 2.0), Vec2::new(1.0, 2.0))
+            .unwrap();
+        assert_eq!(path.path[1].position, Vec2::new(3.0, 2.0));
+        assert_eq!(path.path[1].link, Some(0));
+    }
+
+    #[test]
+    fn links_are_indexed_by_their_position_in_the_navmesh() {
+        let mut navmesh = islands();
+        // Only the second link can be used to go to the right
+        navmesh.add_link(OffMeshLink::new(Vec2::new(7.0, 1.0), Vec2::new(3.0, 1.0)));
+        navmesh.add_link(OffMeshLink::new(Vec2::new(3.0, 3.0), Vec2::new(7.0, 3.0)));
+
+        let path = navmesh
+            .path_with_links(Vec2::new(1.0, 2.0), Vec2::new(9.0, 2.0))
+            .unwrap();
+        assert_eq!(
+            path.path
+                .iter()
+                .filter_map(|step| step.link)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn costly_link_is_not_taken() {
+        let mut navmesh = NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 4.0),
+                Vec2::new(0.0, 4.0),
+            ],
+            vec![],
+        );
+        let (from, to) = (Vec2::new(1.0, 2.0), Vec2::new(9.0, 2.0));
+        navmesh
+            .add_link(OffMeshLink::new(Vec2::new(1.0, 1.0), Vec2::new(9.0, 1.0)).with_cost(20.0));
+
+        let path = navmesh.path_with_links(from, to).unwrap();
+        assert!((path.cost - 8.0).abs() < 1e-3);
+        assert!(path.path.iter().all(|step| step.link.is_none()));
+
+        navmesh.links[0].cost = 1.0;
+        let path = navmesh.path_with_links(from, to).unwrap();
+        assert!((path.cost - 3.0).abs() < 1e-3);
+        assert!(path.path.iter().any(|step| step.link == Some(0)));
+    }
+
+    #[test]
+    fn transformed_path_with_links() {
+        let mut navmesh = islands();
+        navmesh.add_link(OffMeshLink::new(Vec2::new(3.0, 2.0), Vec2::new(7.0, 2.0)).with_cost(1.0));
+        navmesh.set_transform(
+            Transform::from_xyz(0.0, 1.0, 0.0)
+                .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+        );
+        // In world space, the navmesh is on the plane at y = 0
+        let world = |point: Vec2| Vec3::new(point.x, 0.0, point.y - 1.0);
+
+        let path = navmesh
+            .transformed_path_with_links(world(Vec2::new(1.0, 2.0)), world(Vec2::new(9.0, 2.0)))
+            .unwrap();
+        assert!((path.cost - 5.0).abs() < 1e-3);
+        // The link counts for the distance between its ends
+        assert!((path.path.length - 8.0).abs() < 1e-3);
+        assert_eq!(path.links, vec![None, Some(0), None]);
+        for (step, expected) in path.path.path.iter().zip([
+            Vec2::new(3.0, 2.0),
+            Vec2::new(7.0, 2.0),
+            Vec2::new(9.0, 2.0),
+        ]) {
+            assert!(
+                step.abs_diff_eq(world(expected), 1e-3),
+                "{step} != {expected}"
+            );
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::BinaryHeap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+
+use crate::{links::Candidate, NavMesh, TransformedPath};
+
+/// Find which navmesh a world position is on, when a scene has several navmeshes, for example one per room.
+///
+/// Navmeshes are the ones of entities with a [`Handle<NavMesh>`], that are loaded. A position is on a navmesh when
+/// it's in a navigable part of it once projected with its [transform](NavMesh::transform). When several navmeshes
+/// overlap, like floors above each other, the closest one along their up axis is used.
+#[derive(SystemParam)]
+#[allow(missing_debug_implementations)]
+pub struct NavMeshLookup<'w, 's> {
+    navmeshes: Res<'w, Assets<NavMesh>>,
+    handles: Query<'w, 's, (Entity, &'static Handle<NavMesh>)>,
+}
+
+impl<'w, 's> NavMeshLookup<'w, 's> {
+    /// Get the navmesh at `position`, and the entity it belongs to.
+    pub fn navmesh_at(&self, position: Vec3) -> Option<(Entity, &NavMesh)> {
+        self.handles
+            .iter()
+            .filter_map(|(entity, handle)| {
+                self.navmeshes.get(handle).map(|navmesh| (entity, navmesh))
+            })
+            .filter(|(_, navmesh)| navmesh.transformed_is_in_mesh(position))
+            .min_by(|(_, a), (_, b)| {
+                let height =
+                    |navmesh: &NavMesh| navmesh.transform().transform_point(position).z.abs();
+                height(a).total_cmp(&height(b))
+            })
+    }
+
+    /// Get the entity of the navmesh at `position`.
+    pub fn entity_at(&self, position: Vec3) -> Option<Entity> {
+        self.navmesh_at(position).map(|(entity, _)| entity)
+    }
+
+    /// Get a path between `from` and `to`, on the navmesh at `from`.
+    ///
+    /// Returns `None` if there is no navmesh at `from`, or if `to` is not reachable on it.
+    pub fn transformed_path(&self, from: Vec3, to: Vec3) -> Option<TransformedPath> {
+        self.navmesh_at(from)
+            .and_then(|(_, navmesh)| navmesh.transformed_path(from, to))
+    }
+
+    /// Check if `to` can be reached from `from`, on the navmesh at `from`.
+    pub fn transformed_is_reachable(&self, from: Vec3, to: Vec3) -> bool {
+        self.navmesh_at(from)
+            .map(|(_, navmesh)| navmesh.transformed_is_reachable(from, to))
+            .unwrap_or(false)
+    }
+}
+
+/// A doorway between two navmeshes, like a door between two rooms, used by the [`WorldPathfinder`].
+///
+/// The segment is in world space, and should be in a navigable part of both navmeshes: paths cross it at its middle.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct NavMeshConnection {
+    /// Entities of the two navmeshes connected.
+    pub navmeshes: [Entity; 2],
+    /// Start of the doorway segment.
+    pub start: Vec3,
+    /// End of the doorway segment.
+    pub end: Vec3,
+}
+
+impl NavMeshConnection {
+    /// Connect navmeshes `a` and `b` through the segment from `start` to `end`.
+    pub fn new(a: Entity, b: Entity, start: Vec3, end: Vec3) -> Self {
+        Self {
+            navmeshes: [a, b],
+            start,
+            end,
+        }
+    }
+
+    /// Point where paths cross the doorway.
+    pub fn crossing(&self) -> Vec3 {
+        self.start.midpoint(self.end)
+    }
+}
+
+/// Find paths across navmeshes, through their [`NavMeshConnection`]s.
+///
+/// The search first plans which navmeshes to go through, using the straight distance between doorways, then computes
+/// the path in each navmesh on the way.
+#[derive(SystemParam)]
+#[allow(missing_debug_implementations)]
+pub struct WorldPathfinder<'w, 's> {
+    lookup: NavMeshLookup<'w, 's>,
+    connections: Query<'w, 's, &'static NavMeshConnection>,
+}
+
+impl<'w, 's> WorldPathfinder<'w, 's> {
+    /// The [`NavMeshLookup`] used to find the navmeshes at the start and destination.
+    pub fn lookup(&self) -> &NavMeshLookup<'w, 's> {
+        &self.lookup
+    }
+
+    /// Get a path between `from` and `to`, that can go through several navmeshes.
+    ///
+    /// When the path in a navmesh on the way can't be found, like when a doorway is blocked, another way is planned
+    /// without it. The [version](TransformedPath::version) of the path is the [`WorldPathfinder::version`], use
+    /// [`WorldPathfinder::is_stale`] to know when to compute it again.
+    pub fn transformed_path(&self, from: Vec3, to: Vec3) -> Option<TransformedPath> {
+        let (start_entity, start_navmesh) = self.lookup.navmesh_at(from)?;
+        let goal_entity = self.lookup.entity_at(to)?;
+        let version = self.version();
+        if start_entity == goal_entity {
+            if let Some(path) = start_navmesh.transformed_path(from, to) {
+                return Some(TransformedPath { version, ..path });
+            }
+        }
+
+        // Nodes are the start, the destination, then each connection
+        let connections = self.connections.iter().copied().collect::<Vec<_>>();
+        let positions = [from, to]
+            .into_iter()
+            .chain(connections.iter().map(NavMeshConnection::crossing))
+            .collect::<Vec<_>>();
+        let navmeshes_of = |node: usize| -> &[Entity] {
+            match node {
+                0 => std::slice::from_ref(&start_entity),
+                1 => std::slice::from_ref(&goal_entity),
+                _ => &connections[node - 2].navmeshes,
+            }
+        };
+        // Each failed leg is excluded from the next plans, so this ends once all the ways have been tried
+        let mut blocked = HashSet::new();
+        'plan: loop {
+            let legs = plan(&positions, navmeshes_of, &blocked)?;
+            let mut combined = TransformedPath {
+                length: 0.0,
+                start: from,
+                path: vec![],
+                segment_lengths: vec![],
+                version,
+            };
+            for leg @ (leg_from, leg_to, entity) in legs {
+                let Some(path) = self
+                    .lookup
+                    .handles
+                    .get(entity)
+                    .ok()
+                    .and_then(|(_, handle)| self.lookup.navmeshes.get(handle))
+                    .and_then(|navmesh| {
+                        navmesh.transformed_path(positions[leg_from], positions[leg_to])
+                    })
+                else {
+                    blocked.insert(leg);
+                    continue 'plan;
+                };
+                combined.length += path.length;
+                combined.path.extend(path.path);
+                combined.segment_lengths.extend(path.segment_lengths);
+            }
+            return Some(combined);
+        }
+    }
+
+    /// Version of the navmeshes, that changes when any of them is rebuilt.
+    ///
+    /// This is a hash of the [versions](NavMesh::version) of the navmeshes with their entities, as a path can go
+    /// through any of them.
+    pub fn version(&self) -> u32 {
+        let mut versions = self
+            .lookup
+            .handles
+            .iter()
+            .filter_map(|(entity, handle)| {
+                self.lookup
+                    .navmeshes
+                    .get(handle)
+                    .map(|navmesh| (entity, navmesh.version()))
+            })
+            .collect::<Vec<_>>();
+        // The order of the query is not guaranteed
+        versions.sort_unstable();
+        let mut hasher = DefaultHasher::new();
+        versions.hash(&mut hasher);
+        hasher.finish() as u32
+    }
+
+    /// Check if `path` was computed on older versions of the navmeshes, and should be computed again.
+    pub fn is_stale(&self, path: &TransformedPath) -> bool {
+        path.version != self.version()
+    }
+}
+
+/// Plan the navmeshes to go through, returning for each the nodes to walk between and the navmesh entity.
+///
+/// Nodes are at `positions`, and can be walked between when they share a navmesh from `navmeshes_of`, unless the leg
+/// is `blocked`.
+fn plan<'a>(
+    positions: &[Vec3],
+    navmeshes_of: impl Fn(usize) -> &'a [Entity],
+    blocked: &HashSet<(usize, usize, Entity)>,
+) -> Option<Vec<(usize, usize, Entity)>> {
+    let mut costs = vec![f32::INFINITY; positions.len()];
+    let mut previous: Vec<Option<(usize, Entity)>> = vec![None; positions.len()];
+    let mut visited = vec![false; positions.len()];
+    let mut queue = BinaryHeap::new();
+    costs[0] = 0.0;
+    queue.push(Candidate { cost: 0.0, node: 0 });
+
+    while let Some(Candidate { cost, node }) = queue.pop() {
+        if visited[node] {
+            continue;
+        }
+        visited[node] = true;
+        if node == 1 {
+            break;
+        }
+        for other in 1..positions.len() {
+            if visited[other] {
+                continue;
+            }
+            let Some(shared) = navmeshes_of(node).iter().find(|entity| {
+                navmeshes_of(other).contains(entity) && !blocked.contains(&(node, other, **entity))
+            }) else {
+                continue;
+            };
+            let other_cost = cost + positions[node].distance(positions[other]);
+            if other_cost < costs[other] {
+                costs[other] = other_cost;
+                previous[other] = Some((node, *shared));
+                queue.push(Candidate {
+                    cost: other_cost,
+                    node: other,
+                });
+            }
+        }
+    }
+
+    if !visited[1] {
+        return None;
+    }
+    let mut legs = vec![];
+    let mut node = 1;
+    while let Some((from, entity)) = previous[node] {
+        legs.push((from, node, entity));
+        node = from;
+    }
+    legs.reverse();
+    Some(legs)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn rectangle(min: Vec2, max: Vec2) -> Vec<Vec2> {
+        vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+    }
+
+    /// Two rooms side by side, the left one with an obstacle in its bottom right corner.
+    fn rooms(world: &mut World) -> (Entity, Entity) {
+        let mut navmeshes = Assets::<NavMesh>::default();
+        let left = navmeshes.add(NavMesh::from_edge_and_obstacles(
+            rectangle(Vec2::ZERO, Vec2::new(10.0, 10.0)),
+            vec![rectangle(Vec2::new(7.0, 0.5), Vec2::new(9.5, 2.5))],
+        ));
+        let right = navmeshes.add(NavMesh::from_edge_and_obstacles(
+            rectangle(Vec2::new(10.0, 0.0), Vec2::new(20.0, 10.0)),
+            vec![],
+        ));
+        world.insert_resource(navmeshes);
+        (world.spawn(left).id(), world.spawn(right).id())
+    }
+
+    fn path(world: &mut World, from: Vec3, to: Vec3) -> Option<TransformedPath> {
+        world.run_system_once(move |pathfinder: WorldPathfinder| {
+            pathfinder.transformed_path(from, to)
+        })
+    }
+
+    #[test]
+    fn path_through_a_doorway() {
+        let mut world = World::new();
+        let (left, right) = rooms(&mut world);
+        world.spawn(NavMeshConnection::new(
+            left,
+            right,
+            Vec3::new(10.0, 4.0, 0.0),
+            Vec3::new(10.0, 6.0, 0.0),
+        ));
+
+        let path = path(
+            &mut world,
+            Vec3::new(2.0, 5.0, 0.0),
+            Vec3::new(18.0, 5.0, 0.0),
+        )
+        .unwrap();
+        assert!((path.length - 16.0).abs() < 1e-3);
+        assert_eq!(path.path.last(), Some(&Vec3::new(18.0, 5.0, 0.0)));
+        assert!(path.path.contains(&Vec3::new(10.0, 5.0, 0.0)));
+    }
+
+    #[test]
+    fn blocked_doorway_is_avoided() {
+        let mut world = World::new();
+        let (left, right) = rooms(&mut world);
+        // The shortest doorway crosses the obstacle of the left room
+        world.spawn(NavMeshConnection::new(
+            left,
+            right,
+            Vec3::new(9.0, 1.0, 0.0),
+            Vec3::new(9.0, 2.0, 0.0),
+        ));
+        world.spawn(NavMeshConnection::new(
+            left,
+            right,
+            Vec3::new(10.0, 8.0, 0.0),
+            Vec3::new(10.0, 9.0, 0.0),
+        ));
+
+        let path = path(
+            &mut world,
+            Vec3::new(2.0, 1.5, 0.0),
+            Vec3::new(18.0, 1.5, 0.0),
+        )
+        .unwrap();
+        assert!(path.path.contains(&Vec3::new(10.0, 8.5, 0.0)));
+        assert_eq!(path.path.last(), Some(&Vec3::new(18.0, 1.5, 0.0)));
+    }
+
+    #[test]
+    fn path_version_changes_with_any_navmesh() {
+        let mut world = World::new();
+        let (left, right) = rooms(&mut world);
+        world.spawn(NavMeshConnection::new(
+            left,
+            right,
+            Vec3::new(10.0, 4.0, 0.0),
+            Vec3::new(10.0, 6.0, 0.0),
+        ));
+        let path = path(
+            &mut world,
+            Vec3::new(2.0, 5.0, 0.0),
+            Vec3::new(18.0, 5.0, 0.0),
+        )
+        .unwrap();
+        let version = |world: &mut World| {
+            world.run_system_once(|pathfinder: WorldPathfinder| pathfinder.version())
+        };
+        assert_eq!(path.version, version(&mut world));
+
+        // Rebuild the navmesh at the destination
+        let handle = world.get::<Handle<NavMesh>>(right).unwrap().clone();
+        world
+            .resource_mut::<Assets<NavMesh>>()
+            .get_mut(&handle)
+            .unwrap()
+            .set_version(1);
+        assert_ne!(path.version, version(&mut world));
+
+        // Versions moved between navmeshes keep the same sum
+        let rebuilt = version(&mut world);
+        let set_version = |world: &mut World, entity: Entity, value: u32| {
+            let handle = world.get::<Handle<NavMesh>>(entity).unwrap().clone();
+            world
+                .resource_mut::<Assets<NavMesh>>()
+                .get_mut(&handle)
+                .unwrap()
+                .set_version(value);
+        };
+        set_version(&mut world, left, 1);
+        set_version(&mut world, right, 0);
+        assert_ne!(rebuilt, version(&mut world));
+    }
+}
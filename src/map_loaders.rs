@@ -0,0 +1,674 @@
+//! Asset loaders reading navmesh boundaries and obstacles from 2D level editors.
+//!
+//! - With the `ldtk` feature, [`LdtkNavMeshLoader`] reads [LDtk](https://ldtk.io) projects with a `.ldtk` extension
+//! - With the `tiled` feature, [`TiledNavMeshLoader`] reads [Tiled](https://www.mapeditor.org) maps saved in the JSON
+//!   format, with a `.tmj` extension
+//!
+//! Positions are in pixels, with the `y` axis flipped so that levels are in the same orientation as in the editor.
+
+#[cfg(feature = "tiled")]
+use std::f32::consts::TAU;
+use std::{error::Error, fmt::Display};
+
+use bevy::{
+    asset::{io::Reader, Asset, AssetLoader, AsyncReadExt, LoadContext},
+    math::Vec2,
+    reflect::TypePath,
+};
+use polyanya::Triangulation;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "tiled")]
+use bevy::math::Rot2;
+
+use crate::{geometry::grid_rectangles, prelude::PolygonObstacle};
+
+/// Levels loaded from a level editor.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct NavMeshLevels {
+    /// Levels, in the order they are in the file.
+    pub levels: Vec<NavMeshLevel>,
+}
+
+impl NavMeshLevels {
+    /// Get a level by its name.
+    pub fn get(&self, name: &str) -> Option<&NavMeshLevel> {
+        self.levels.iter().find(|level| level.name == name)
+    }
+}
+
+/// Boundaries and obstacles of a level.
+#[derive(Debug, Clone, Default)]
+pub struct NavMeshLevel {
+    /// Name of the level.
+    pub name: String,
+    /// Bounds of the level.
+    pub outer_edges: Vec<Vec2>,
+    /// Obstacles from the collision layers and the obstacle entities.
+    pub obstacles: Vec<Vec<Vec2>>,
+}
+
+impl NavMeshLevel {
+    /// Create a [`Triangulation`] of the level, with its obstacles.
+    ///
+    /// It can be used as the [`fixed`](crate::prelude::NavMeshSettings::fixed) triangulation of a navmesh, with the
+    /// [`outer_edges`](NavMeshLevel::outer_edges) of the level as its
+    /// [`outer_edges`](crate::prelude::NavMeshSettings::outer_edges).
+    pub fn to_triangulation(&self) -> Triangulation {
+        let mut triangulation = Triangulation::from_outer_edges(&self.outer_edges);
+        triangulation.add_obstacles(self.obstacles.iter().cloned());
+        triangulation
+    }
+
+    /// Create a [`PolygonObstacle`] for each obstacle, to spawn them instead of adding them to the triangulation.
+    pub fn to_obstacles(&self) -> Vec<PolygonObstacle> {
+        self.obstacles
+            .iter()
+            .cloned()
+            .map(PolygonObstacle::new)
+            .collect()
+    }
+}
+
+/// Settings of the [`LdtkNavMeshLoader`] and [`TiledNavMeshLoader`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MapLoaderSettings {
+    /// Names of the layers to read obstacles from.
+    ///
+    /// When empty, all IntGrid layers of LDtk levels and all object layers of Tiled maps are used. Non-empty cells of
+    /// IntGrid and tile layers are obstacles, as are all objects of object layers.
+    pub collision_layers: Vec<String>,
+    /// Identifiers of LDtk entities, or classes of Tiled objects, that are obstacles in any layer.
+    pub obstacle_entities: Vec<String>,
+}
+
+impl MapLoaderSettings {
+    fn is_collision_layer(&self, name: &str, default: bool) -> bool {
+        if self.collision_layers.is_empty() {
+            default
+        } else {
+            self.collision_layers.iter().any(|layer| layer == name)
+        }
+    }
+
+    fn is_obstacle_entity(&self, name: &str) -> bool {
+        self.obstacle_entities.iter().any(|entity| entity == name)
+    }
+}
+
+/// Error that can happen while reading a level
+#[derive(Debug)]
+pub enum MapLoaderError {
+    /// Error when reading file
+    Io(std::io::Error),
+    /// The file is not valid, or uses a feature that is not supported
+    InvalidData(String),
+}
+
+impl Display for MapLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapLoaderError::Io(io_error) => write!(f, "IO error: {}", io_error),
+            MapLoaderError::InvalidData(reason) => write!(f, "Invalid data: {}", reason),
+        }
+    }
+}
+
+impl Error for MapLoaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MapLoaderError::Io(io_error) => Some(io_error),
+            MapLoaderError::InvalidData(_) => None,
+        }
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> MapLoaderError {
+    MapLoaderError::InvalidData(reason.into())
+}
+
+async fn read_json(reader: &mut Reader<'_>) -> Result<Value, MapLoaderError> {
+    let mut bytes = vec![];
+    reader
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(MapLoaderError::Io)?;
+    serde_json::from_slice(&bytes).map_err(|error| invalid(error.to_string()))
+}
+
+fn number(value: &Value, key: &str) -> Result<f32, MapLoaderError> {
+    value
+        .get(key)
+        .and_then(Value::as_f64)
+        .map(|number| number as f32)
+        .ok_or_else(|| invalid(format!("missing {}", key)))
+}
+
+fn string<'a>(value: &'a Value, key: &str) -> Result<&'a str, MapLoaderError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(format!("missing {}", key)))
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> Result<&'a [Value], MapLoaderError> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .ok_or_else(|| invalid(format!("missing {}", key)))
+}
+
+/// Point in a level, from editor coordinates with the `y` axis pointing down.
+fn point(x: f32, y: f32) -> Vec2 {
+    Vec2::new(x, -y)
+}
+
+/// Rectangle from its top left corner and size, in editor coordinates.
+fn rectangle(position: Vec2, size: Vec2) -> Vec<Vec2> {
+    [(0.0, 0.0), (size.x, 0.0), (size.x, size.y), (0.0, size.y)]
+        .map(|(x, y)| point(position.x + x, position.y + y))
+        .to_vec()
+}
+
+/// Asset loader for the levels of an [LDtk](https://ldtk.io) project, with a `.ldtk` extension.
+///
+/// Each level has its bounds as outer edges. Obstacles are the non-empty cells of IntGrid layers, and the entities
+/// listed in [`MapLoaderSettings::obstacle_entities`]. Levels saved in separate files are not supported.
+#[cfg(feature = "ldtk")]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct LdtkNavMeshLoader;
+
+#[cfg(feature = "ldtk")]
+impl AssetLoader for LdtkNavMeshLoader {
+    type Asset = NavMeshLevels;
+    type Settings = MapLoaderSettings;
+    type Error = MapLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let project = read_json(reader).await?;
+        let worlds = project
+            .get("worlds")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let levels = std::iter::once(&project)
+            .chain(worlds)
+            .filter_map(|world| world.get("levels").and_then(Value::as_array))
+            .flatten()
+            .map(|level| ldtk_level(level, settings))
+            .collect::<Result<_, _>>()?;
+        Ok(NavMeshLevels { levels })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
+
+#[cfg(feature = "ldtk")]
+fn ldtk_level(level: &Value, settings: &MapLoaderSettings) -> Result<NavMeshLevel, MapLoaderError> {
+    let name = string(level, "identifier")?.to_string();
+    let size = Vec2::new(number(level, "pxWid")?, number(level, "pxHei")?);
+    let layers = level
+        .get("layerInstances")
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid(format!("level {} is saved in a separate file", name)))?;
+
+    let mut obstacles = vec![];
+    for layer in layers {
+        let offset = Vec2::new(
+            number(layer, "__pxTotalOffsetX")?,
+            number(layer, "__pxTotalOffsetY")?,
+        );
+        match string(layer, "__type")? {
+            "IntGrid" if settings.is_collision_layer(string(layer, "__identifier")?, true) => {
+                let columns = number(layer, "__cWid")? as usize;
+                let rows = number(layer, "__cHei")? as usize;
+                let grid_size = number(layer, "__gridSize")?;
+                let cells = array(layer, "intGridCsv")?;
+                let is_full = |column: usize, row: usize| {
+                    cells
+                        .get(row * columns + column)
+                        .and_then(Value::as_i64)
+                        .unwrap_or(0)
+                        != 0
+                };
+                obstacles.extend(grid_rectangles(columns, rows, is_full).into_iter().map(
+                    |(column, row, width, height)| {
+                        rectangle(
+                            offset + Vec2::new(column as f32, row as f32) * grid_size,
+                            Vec2::new(width as f32, height as f32) * grid_size,
+                        )
+                    },
+                ));
+            }
+            "Entities" => {
+                for entity in array(layer, "entityInstances")? {
+                    if !settings.is_obstacle_entity(string(entity, "__identifier")?) {
+                        continue;
+                    }
+                    let position = array(entity, "px")?;
+                    let pivot = array(entity, "__pivot")?;
+                    let (Some(x), Some(y), Some(pivot_x), Some(pivot_y)) = (
+                        position.first().and_then(Value::as_f64),
+                        position.get(1).and_then(Value::as_f64),
+                        pivot.first().and_then(Value::as_f64),
+                        pivot.get(1).and_then(Value::as_f64),
+                    ) else {
+                        return Err(invalid("invalid entity position"));
+                    };
+                    let entity_size =
+                        Vec2::new(number(entity, "width")?, number(entity, "height")?);
+                    let top_left = Vec2::new(x as f32, y as f32)
+                        - Vec2::new(pivot_x as f32, pivot_y as f32) * entity_size;
+                    obstacles.push(rectangle(offset + top_left, entity_size));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(NavMeshLevel {
+        name,
+        outer_edges: rectangle(Vec2::ZERO, size),
+        obstacles,
+    })
+}
+
+/// Asset loader for a [Tiled](https://www.mapeditor.org) map saved in the JSON format, with a `.tmj` extension.
+///
+/// The map has its bounds as outer edges. Obstacles are the rectangles, ellipses and polygons of object layers, the
+/// non-empty cells of tile layers listed in [`MapLoaderSettings::collision_layers`], and the objects whose class is in
+/// [`MapLoaderSettings::obstacle_entities`]. Tile layers must be saved without compression, in CSV. The level is named
+/// after the file.
+#[cfg(feature = "tiled")]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct TiledNavMeshLoader;
+
+#[cfg(feature = "tiled")]
+impl AssetLoader for TiledNavMeshLoader {
+    type Asset = NavMeshLevels;
+    type Settings = MapLoaderSettings;
+    type Error = MapLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let map = read_json(reader).await?;
+        let name = load_context
+            .path()
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let tile_size = Vec2::new(number(&map, "tilewidth")?, number(&map, "tileheight")?);
+        let size = Vec2::new(number(&map, "width")?, number(&map, "height")?) * tile_size;
+        let mut obstacles = vec![];
+        tiled_layers(
+            array(&map, "layers")?,
+            Vec2::ZERO,
+            tile_size,
+            settings,
+            &mut obstacles,
+        )?;
+        Ok(NavMeshLevels {
+            levels: vec![NavMeshLevel {
+                name,
+                outer_edges: rectangle(Vec2::ZERO, size),
+                obstacles,
+            }],
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj"]
+    }
+}
+
+/// Number of points of obstacles from Tiled ellipses.
+#[cfg(feature = "tiled")]
+const ELLIPSE_RESOLUTION: usize = 16;
+
+#[cfg(feature = "tiled")]
+fn tiled_layers(
+    layers: &[Value],
+    parent_offset: Vec2,
+    tile_size: Vec2,
+    settings: &MapLoaderSettings,
+    obstacles: &mut Vec<Vec<Vec2>>,
+) -> Result<(), MapLoaderError> {
+    for layer in layers {
+        let name = string(layer, "name")?;
+        let offset = parent_offset
+            + Vec2::new(
+                number(layer, "offsetx").unwrap_or(0.0),
+                number(layer, "offsety").unwrap_or(0.0),
+            );
+        match string(layer, "type")? {
+            "group" => {
+                tiled_layers(
+                    array(layer, "layers")?,
+                    offset,
+                    tile_size,
+                    settings,
+                    obstacles,
+                )?;
+            }
+            "tilelayer" if settings.is_collision_layer(name, false) => {
+                if layer.get("encoding").and_then(Value::as_str) == Some("base64") {
+                    return Err(invalid(format!("layer {} is not saved in CSV", name)));
+                }
+                let columns = number(layer, "width")? as usize;
+                let rows = number(layer, "height")? as usize;
+                let cells = array(layer, "data")?;
+                let is_full = |column: usize, row: usize| {
+                    cells
+                        .get(row * columns + column)
+                        .and_then(Value::as_u64)
+                        .unwrap_or(0)
+                        != 0
+                };
+                obstacles.extend(grid_rectangles(columns, rows, is_full).into_iter().map(
+                    |(column, row, width, height)| {
+                        rectangle(
+                            offset + Vec2::new(column as f32, row as f32) * tile_size,
+                            Vec2::new(width as f32, height as f32) * tile_size,
+                        )
+                    },
+                ));
+            }
+            "objectgroup" => {
+                let is_collision_layer = settings.is_collision_layer(name, true);
+                for object in array(layer, "objects")? {
+                    let class = object
+                        .get("class")
+                        .or_else(|| object.get("type"))
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    if !is_collision_layer && !settings.is_obstacle_entity(class) {
+                        continue;
+                    }
+                    if let Some(obstacle) = tiled_object(object, offset)? {
+                        obstacles.push(obstacle);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Obstacle from a Tiled object, `None` for points, polylines and texts.
+#[cfg(feature = "tiled")]
+fn tiled_object(object: &Value, offset: Vec2) -> Result<Option<Vec<Vec2>>, MapLoaderError> {
+    let is = |key: &str| object.get(key).and_then(Value::as_bool).unwrap_or(false);
+    if is("point") || object.get("polyline").is_some() || object.get("text").is_some() {
+        return Ok(None);
+    }
+    let position = Vec2::new(number(object, "x")?, number(object, "y")?);
+    let size = Vec2::new(
+        number(object, "width").unwrap_or(0.0),
+        number(object, "height").unwrap_or(0.0),
+    );
+    let points = if let Some(polygon) = object.get("polygon").and_then(Value::as_array) {
+        polygon
+            .iter()
+            .map(|point| Ok(Vec2::new(number(point, "x")?, number(point, "y")?)))
+            .collect::<Result<Vec<_>, MapLoaderError>>()?
+    } else if is("ellipse") {
+        (0..ELLIPSE_RESOLUTION)
+            .map(|i| {
+                let angle = i as f32 / ELLIPSE_RESOLUTION as f32 * TAU;
+                size / 2.0 + Vec2::from_angle(angle) * size / 2.0
+            })
+            .collect()
+    } else {
+        vec![
+            Vec2::ZERO,
+            Vec2::new(size.x, 0.0),
+            size,
+            Vec2::new(0.0, size.y),
+        ]
+    };
+    if points.len() < 3 {
+        return Ok(None);
+    }
+    // Tiled rotates objects clockwise around their position, in degrees
+    let rotation = Rot2::degrees(number(object, "rotation").unwrap_or(0.0));
+    Ok(Some(
+        points
+            .into_iter()
+            .map(|local| {
+                let rotated = rotation * local;
+                point(
+                    offset.x + position.x + rotated.x,
+                    offset.y + position.y + rotated.y,
+                )
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_polygon_eq(polygon: &[Vec2], expected: &[Vec2]) {
+        assert_eq!(polygon.len(), expected.len());
+        for (point, expected) in polygon.iter().zip(expected) {
+            assert!(
+                point.abs_diff_eq(*expected, 1e-4),
+                "{} != {}",
+                point,
+                expected
+            );
+        }
+    }
+
+    #[cfg(feature = "ldtk")]
+    #[test]
+    fn reads_ldtk_levels() {
+        let level: Value = serde_json::from_str(
+            r#"{
+                "identifier": "Level_0",
+                "pxWid": 64,
+                "pxHei": 32,
+                "layerInstances": [
+                    {
+                        "__identifier": "Entities",
+                        "__type": "Entities",
+                        "__pxTotalOffsetX": 0,
+                        "__pxTotalOffsetY": 0,
+                        "entityInstances": [
+                            { "__identifier": "Crate", "px": [40, 8], "__pivot": [0.5, 0.5], "width": 8, "height": 8 },
+                            { "__identifier": "Player", "px": [8, 8], "__pivot": [0, 0], "width": 8, "height": 8 }
+                        ]
+                    },
+                    {
+                        "__identifier": "Collisions",
+                        "__type": "IntGrid",
+                        "__pxTotalOffsetX": 0,
+                        "__pxTotalOffsetY": 0,
+                        "__cWid": 4,
+                        "__cHei": 2,
+                        "__gridSize": 16,
+                        "intGridCsv": [1, 1, 0, 0, 0, 0, 0, 2]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let settings = MapLoaderSettings {
+            obstacle_entities: vec!["Crate".to_string()],
+            ..Default::default()
+        };
+
+        let level = ldtk_level(&level, &settings).unwrap();
+        assert_eq!(level.name, "Level_0");
+        assert_polygon_eq(
+            &level.outer_edges,
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(64.0, 0.0),
+                Vec2::new(64.0, -32.0),
+                Vec2::new(0.0, -32.0),
+            ],
+        );
+        assert_eq!(level.obstacles.len(), 3);
+        // The crate entity, centered on its position
+        assert_polygon_eq(
+            &level.obstacles[0],
+            &[
+                Vec2::new(36.0, -4.0),
+                Vec2::new(44.0, -4.0),
+                Vec2::new(44.0, -12.0),
+                Vec2::new(36.0, -12.0),
+            ],
+        );
+        // The two cells of the first row are merged
+        assert_polygon_eq(
+            &level.obstacles[1],
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(32.0, 0.0),
+                Vec2::new(32.0, -16.0),
+                Vec2::new(0.0, -16.0),
+            ],
+        );
+        assert_polygon_eq(
+            &level.obstacles[2],
+            &[
+                Vec2::new(48.0, -16.0),
+                Vec2::new(64.0, -16.0),
+                Vec2::new(64.0, -32.0),
+                Vec2::new(48.0, -32.0),
+            ],
+        );
+    }
+
+    #[cfg(feature = "ldtk")]
+    #[test]
+    fn ldtk_levels_in_separate_files_are_rejected() {
+        let level: Value =
+            serde_json::from_str(r#"{ "identifier": "Level_0", "pxWid": 64, "pxHei": 32 }"#)
+                .unwrap();
+        assert!(matches!(
+            ldtk_level(&level, &MapLoaderSettings::default()),
+            Err(MapLoaderError::InvalidData(_))
+        ));
+    }
+
+    #[cfg(feature = "tiled")]
+    #[test]
+    fn reads_tiled_layers() {
+        let layers: Value = serde_json::from_str(
+            r#"[
+                { "name": "walls", "type": "tilelayer", "width": 2, "height": 2, "data": [1, 0, 0, 0] },
+                { "name": "decor", "type": "tilelayer", "width": 2, "height": 2, "data": [1, 1, 1, 1] },
+                {
+                    "name": "objects",
+                    "type": "objectgroup",
+                    "objects": [
+                        { "x": 32, "y": 0, "width": 8, "height": 4, "rotation": 90 },
+                        { "x": 0, "y": 40, "width": 8, "height": 8, "ellipse": true },
+                        { "x": 5, "y": 5, "point": true }
+                    ]
+                },
+                {
+                    "name": "group",
+                    "type": "group",
+                    "offsetx": 100,
+                    "layers": [
+                        {
+                            "name": "markers",
+                            "type": "objectgroup",
+                            "objects": [
+                                { "class": "crate", "x": 0, "y": 0, "polygon": [{ "x": 0, "y": 0 }, { "x": 4, "y": 0 }, { "x": 0, "y": 4 }] },
+                                { "class": "spawn", "x": 0, "y": 0, "width": 4, "height": 4 }
+                            ]
+                        }
+                    ]
+                }
+            ]"#,
+        )
+        .unwrap();
+        let settings = MapLoaderSettings {
+            collision_layers: vec!["walls".to_string(), "objects".to_string()],
+            obstacle_entities: vec!["crate".to_string()],
+        };
+
+        let mut obstacles = vec![];
+        tiled_layers(
+            layers.as_array().unwrap(),
+            Vec2::ZERO,
+            Vec2::splat(16.0),
+            &settings,
+            &mut obstacles,
+        )
+        .unwrap();
+        assert_eq!(obstacles.len(), 4);
+        assert_polygon_eq(
+            &obstacles[0],
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(16.0, 0.0),
+                Vec2::new(16.0, -16.0),
+                Vec2::new(0.0, -16.0),
+            ],
+        );
+        // Rotated clockwise around its top left corner
+        assert_polygon_eq(
+            &obstacles[1],
+            &[
+                Vec2::new(32.0, 0.0),
+                Vec2::new(32.0, -8.0),
+                Vec2::new(28.0, -8.0),
+                Vec2::new(28.0, 0.0),
+            ],
+        );
+        assert_eq!(obstacles[2].len(), ELLIPSE_RESOLUTION);
+        assert_polygon_eq(&obstacles[2][..1], &[Vec2::new(8.0, -44.0)]);
+        // Offset by its group
+        assert_polygon_eq(
+            &obstacles[3],
+            &[
+                Vec2::new(100.0, 0.0),
+                Vec2::new(104.0, 0.0),
+                Vec2::new(100.0, -4.0),
+            ],
+        );
+    }
+
+    #[cfg(feature = "tiled")]
+    #[test]
+    fn compressed_tiled_layers_are_rejected() {
+        let layers: Value = serde_json::from_str(
+            r#"[{ "name": "walls", "type": "tilelayer", "width": 1, "height": 1, "encoding": "base64", "data": "AQAAAA==" }]"#,
+        )
+        .unwrap();
+        let settings = MapLoaderSettings {
+            collision_layers: vec!["walls".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            tiled_layers(
+                layers.as_array().unwrap(),
+                Vec2::ZERO,
+                Vec2::splat(16.0),
+                &settings,
+                &mut vec![]
+            ),
+            Err(MapLoaderError::InvalidData(_))
+        ));
+    }
+}
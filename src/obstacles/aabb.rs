@@ -4,7 +4,7 @@ use bevy::{
     transform::components::{GlobalTransform, Transform},
 };
 
-use super::ObstacleSource;
+use super::{sliced_hull, ObstacleSource};
 
 impl ObstacleSource for Aabb {
     fn get_polygon(
@@ -38,4 +38,65 @@ impl ObstacleSource for Aabb {
             ))),
         ]
     }
+
+    fn get_sliced_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+        min_height: f32,
+        max_height: f32,
+    ) -> Vec<Vec<Vec2>> {
+        let transform = obstacle_transform.compute_transform();
+        let corner = |i: usize| {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            let local = vec3(sign(1), sign(2), sign(4)) * Vec3::from(self.half_extents)
+                + Vec3::from(self.center);
+            navmesh_transform.transform_point(transform.transform_point(local))
+        };
+        // Two triangles for each face of the box, from the indices of its corners
+        let faces = [
+            [0, 1, 3, 2],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 3, 7, 6],
+            [0, 2, 6, 4],
+            [1, 3, 7, 5],
+        ];
+        let triangles = faces.iter().flat_map(|[a, b, c, d]| {
+            [
+                [corner(*a), corner(*b), corner(*c)],
+                [corner(*a), corner(*c), corner(*d)],
+            ]
+        });
+        let hull = sliced_hull(triangles, min_height, max_height);
+        if hull.len() < 3 {
+            return vec![];
+        }
+        vec![hull]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::signed_area;
+
+    use super::*;
+
+    #[test]
+    fn slicing_keeps_the_cross_section_at_the_cut_height() {
+        let aabb = Aabb::from_min_max(Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 4.0));
+        // Tilted, so that its full outline is larger than its section
+        let transform = GlobalTransform::from(Transform::from_rotation(
+            bevy::math::Quat::from_rotation_x(0.5),
+        ));
+
+        let section = aabb.get_sliced_polygons(&transform, &Transform::IDENTITY, 1.0, 1.0);
+        assert_eq!(section.len(), 1);
+        let outline = aabb.get_sliced_polygons(&transform, &Transform::IDENTITY, -10.0, 10.0);
+        assert!(signed_area(&section[0]) > 0.0);
+        assert!(signed_area(&section[0]) < signed_area(&outline[0]));
+
+        let above = aabb.get_sliced_polygons(&transform, &Transform::IDENTITY, 10.0, 10.0);
+        assert!(above.is_empty());
+    }
 }
@@ -1,11 +1,22 @@
+//! Obstacles that navmeshes are built around, and utilities to prepare their polygons.
+
 use bevy::{
     math::Vec2,
     prelude::Component,
     transform::components::{GlobalTransform, Transform},
 };
 
+#[cfg(feature = "3d")]
 mod aabb;
+pub mod offset;
+pub(crate) mod polygon;
 pub(crate) mod primitive;
+#[cfg(feature = "rapier2d")]
+mod rapier2d;
+#[cfg(feature = "rapier3d")]
+mod rapier3d;
+#[cfg(feature = "sprite")]
+mod sprite;
 
 /// Trait to mark a component as the source of position and shape of an obstacle.
 pub trait ObstacleSource: Component + Clone {
@@ -15,4 +26,223 @@ pub trait ObstacleSource: Component + Clone {
         obstacle_transform: &GlobalTransform,
         navmesh_transform: &Transform,
     ) -> Vec<Vec2>;
+
+    /// Get the polygons of the obstacle in the local space of the mesh.
+    ///
+    /// An obstacle can be made of several polygons, that can overlap. By default, this is the polygon from
+    /// [`ObstacleSource::get_polygon`].
+    fn get_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec<Vec2>> {
+        vec![self.get_polygon(obstacle_transform, navmesh_transform)]
+    }
+
+    /// Get the polygons of the parts of the obstacle between two heights, in the local space of the mesh.
+    ///
+    /// Heights are along the up axis of the navmesh, relative to its plane. When they are equal, this is the
+    /// cross-section of the obstacle at this height. By default, the whole obstacle is used, as with
+    /// [`ObstacleSource::get_polygons`].
+    fn get_sliced_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+        _min_height: f32,
+        _max_height: f32,
+    ) -> Vec<Vec<Vec2>> {
+        self.get_polygons(obstacle_transform, navmesh_transform)
+    }
+}
+
+/// Projection on the navmesh of the parts of convex shape between two heights, from its triangles in the navmesh
+/// space.
+#[cfg(feature = "3d")]
+pub(crate) fn sliced_hull(
+    triangles: impl IntoIterator<Item = [bevy::math::Vec3; 3]>,
+    min_height: f32,
+    max_height: f32,
+) -> Vec<Vec2> {
+    crate::geometry::convex_hull(
+        triangles
+            .into_iter()
+            .flat_map(|triangle| crate::geometry::clip_to_slab(&triangle, min_height, max_height))
+            .map(|point| point.truncate())
+            .collect(),
+    )
+}
+
+/// Projection on the navmesh of the parts of a closed triangle mesh between two heights, from its triangles in the
+/// navmesh space.
+///
+/// Unlike [`sliced_hull`], the mesh doesn't need to be convex. The parts of its surface between the heights are kept,
+/// closed by its cross-sections at both heights so that walls cut by the slab keep their footprint.
+#[cfg(feature = "rapier3d")]
+pub(crate) fn sliced_mesh(
+    triangles: &[[bevy::math::Vec3; 3]],
+    min_height: f32,
+    max_height: f32,
+) -> Vec<Vec<Vec2>> {
+    let mut polygons = cross_sections(triangles, min_height);
+    if max_height > min_height {
+        polygons.extend(cross_sections(triangles, max_height));
+        polygons.extend(
+            triangles
+                .iter()
+                .map(|triangle| sliced_hull([*triangle], min_height, max_height))
+                .filter(|polygon| {
+                    // Walls project to slivers, their footprint comes from the cross-sections
+                    let size = polygon
+                        .iter()
+                        .zip(polygon.iter().cycle().skip(1))
+                        .fold(0.0_f32, |size, (a, b)| size.max(a.distance_squared(*b)));
+                    polygon.len() >= 3
+                        && crate::geometry::signed_area(polygon) > size * SLIVER_AREA_RATIO
+                }),
+        );
+    }
+    polygons
+}
+
+/// Minimum area of a projected triangle, relative to its longest edge squared, to not be considered a sliver.
+#[cfg(feature = "rapier3d")]
+const SLIVER_AREA_RATIO: f32 = 1e-3;
+
+/// Cross-sections of a closed triangle mesh at a height, from its triangles in the navmesh space, as counter clockwise
+/// polygons.
+///
+/// The edges of the mesh crossing the height are cut, and the cuts are chained into closed outlines. Outlines that
+/// don't close, from meshes with holes, are ignored. Holes in a section are outlines too, and so are filled.
+#[cfg(feature = "rapier3d")]
+pub(crate) fn cross_sections(triangles: &[[bevy::math::Vec3; 3]], height: f32) -> Vec<Vec<Vec2>> {
+    use bevy::{math::Vec3, utils::HashMap};
+
+    let key = |point: Vec2| point.to_array().map(f32::to_bits);
+    let cut = |a: Vec3, b: Vec3| {
+        if (a.z >= height) == (b.z >= height) {
+            return None;
+        }
+        // Always cut from the same end, so that triangles sharing the edge get the same point
+        let (a, b) = if a.to_array().map(f32::to_bits) < b.to_array().map(f32::to_bits) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        Some(a.lerp(b, (height - a.z) / (b.z - a.z)).truncate())
+    };
+    let segments = triangles
+        .iter()
+        .filter_map(|[a, b, c]| {
+            let mut cuts = [(*a, *b), (*b, *c), (*c, *a)]
+                .into_iter()
+                .filter_map(|(from, to)| cut(from, to));
+            let (start, end) = (cuts.next()?, cuts.next()?);
+            (key(start) != key(end)).then_some([start, end])
+        })
+        .collect::<Vec<_>>();
+    let mut ends = HashMap::<_, Vec<usize>>::new();
+    for (index, segment) in segments.iter().enumerate() {
+        for point in segment {
+            ends.entry(key(*point)).or_default().push(index);
+        }
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut sections = vec![];
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let [first, mut current] = segments[start];
+        let mut outline = vec![first];
+        let closed = loop {
+            if key(current) == key(first) {
+                break true;
+            }
+            outline.push(current);
+            let Some(next) = ends[&key(current)]
+                .iter()
+                .copied()
+                .find(|next| !used[*next])
+            else {
+                break false;
+            };
+            used[next] = true;
+            let [a, b] = segments[next];
+            current = if key(a) == key(current) { b } else { a };
+        };
+        if closed && outline.len() >= 3 {
+            sections.push(crate::geometry::counter_clockwise(outline));
+        }
+    }
+    sections
+}
+
+/// Trait to get the transform of an obstacle, in the reference frame of the navmesh.
+///
+/// By default, the [`GlobalTransform`] of obstacles is used. Implement this trait on another component to use a
+/// different reference frame, for example the position of an entity inside a grid cell when using floating origin
+/// hierarchies. The [`Transform`] of the navmesh entity must then be expressed in the same reference frame.
+pub trait TransformProvider: Component {
+    /// Get the transform of the obstacle.
+    fn global_transform(&self) -> GlobalTransform;
+}
+
+impl TransformProvider for GlobalTransform {
+    fn global_transform(&self) -> GlobalTransform {
+        *self
+    }
+}
+
+/// Layers an obstacle belongs to, as a bitmask.
+///
+/// An obstacle is only used by navmeshes whose [`obstacle_layers`](crate::prelude::NavMeshSettings::obstacle_layers)
+/// mask shares at least one layer with it. Obstacles without this component belong to all layers.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObstacleLayers(pub u32);
+
+impl ObstacleLayers {
+    /// Belongs to all layers.
+    pub const ALL: Self = Self(u32::MAX);
+    /// Belongs to no layer, the obstacle is ignored by all navmeshes.
+    pub const NONE: Self = Self(0);
+
+    /// Belongs only to layer `layer`, between 0 and 31.
+    pub const fn layer(layer: u32) -> Self {
+        Self(1 << layer)
+    }
+
+    /// Also belong to layer `layer`, between 0 and 31.
+    pub const fn with(self, layer: u32) -> Self {
+        Self(self.0 | 1 << layer)
+    }
+
+    /// Check if these layers share at least one layer with `mask`.
+    pub const fn intersects(&self, mask: u32) -> bool {
+        self.0 & mask != 0
+    }
 }
+
+impl Default for ObstacleLayers {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Inflate an obstacle by this radius instead of the [agent radius](crate::prelude::NavMeshSettings::agent_radius) of
+/// the navmesh.
+///
+/// Use it for obstacles that need more clearance, like lava pools, or that agents can get closer to, like walls. For
+/// the [variants](crate::prelude::NavMeshSettings::agent_radius_variants), the difference between their radius and the
+/// agent radius of the navmesh is added to the override, so larger agents still keep further away.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ObstacleRadiusOverride(pub f32);
+
+/// Marks an obstacle as cached: its position is kept from the first navmesh build that used it.
+///
+/// Moving a cached obstacle doesn't trigger navmesh updates. Removing this component makes the obstacle use its
+/// current position again, and updates the navmeshes. Use it for obstacles that won't move, to avoid rebuilds when
+/// their transform is changed, for example by a physics engine settling them.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct CachableObstacle;
@@ -0,0 +1,116 @@
+//! Offset of polygons, used to inflate obstacles by the [agent radius](crate::prelude::NavMeshSettings::agent_radius).
+//!
+//! Polygons can be inflated in advance, for example to display the footprint of an obstacle as seen by agents.
+
+use std::f32::consts::TAU;
+
+use bevy::math::Vec2;
+
+use crate::geometry;
+
+/// Offset a polygon outward by `radius`.
+///
+/// Convex corners are rounded, with `quality` segments for a full circle. Concave corners are kept sharp.
+/// The polygon can be in any orientation, the result is counter clockwise.
+pub fn offset_polygon(polygon: &[Vec2], radius: f32, quality: usize) -> Vec<Vec2> {
+    if radius <= 0.0 {
+        return polygon.to_vec();
+    }
+    let mut points = polygon.to_vec();
+    points.dedup_by(|a, b| a.distance_squared(*b) < f32::EPSILON);
+    while points.len() > 1 && points[0].distance_squared(points[points.len() - 1]) < f32::EPSILON {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return points;
+    }
+    let points = geometry::counter_clockwise(points);
+    let step = TAU / quality.max(4) as f32;
+
+    let count = points.len();
+    let mut offset = Vec::with_capacity(count * 2);
+    for i in 0..count {
+        let previous = points[(i + count - 1) % count];
+        let current = points[i];
+        let next = points[(i + 1) % count];
+
+        let incoming = (current - previous).normalize_or_zero();
+        let outgoing = (next - current).normalize_or_zero();
+        // Outward normals of a counter clockwise polygon
+        let normal_in = Vec2::new(incoming.y, -incoming.x);
+        let normal_out = Vec2::new(outgoing.y, -outgoing.x);
+
+        let turn = incoming.perp_dot(outgoing);
+        if turn > f32::EPSILON {
+            // Convex corner: round it
+            let start = normal_in.to_angle();
+            let angle = normal_in.angle_between(normal_out);
+            let steps = (angle / step).ceil().max(1.0) as usize;
+            offset.extend((0..=steps).map(|s| {
+                current + Vec2::from_angle(start + angle * s as f32 / steps as f32) * radius
+            }));
+        } else if turn < -f32::EPSILON {
+            // Concave corner: intersection of both offset edges
+            let bisector = (normal_in + normal_out).normalize_or_zero();
+            let cos = bisector.dot(normal_in).max(0.1);
+            offset.push(current + bisector * radius / cos);
+        } else {
+            offset.push(current + normal_in * radius);
+        }
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convex_corners_are_rounded() {
+        let square = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+        ];
+        let offset = offset_polygon(&square, 0.5, 16);
+        // Each corner is a quarter circle of 4 segments
+        assert_eq!(offset.len(), 4 * 5);
+        let center = Vec2::splat(0.5);
+        for point in &offset {
+            let outside = (*point - center).abs() - Vec2::splat(0.5);
+            let distance = outside.max(Vec2::ZERO).length();
+            assert!(
+                (distance - 0.5).abs() < 1e-4,
+                "{point} is not at the radius"
+            );
+        }
+    }
+
+    #[test]
+    fn concave_corners_stay_sharp() {
+        // L shape, with a concave corner at (1, 1)
+        let shape = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+        let offset = offset_polygon(&shape, 0.25, 16);
+        assert!(offset.contains(&Vec2::new(1.25, 1.25)));
+        // Clockwise polygons give the same result
+        let mut reversed = shape;
+        reversed.reverse();
+        let reversed = offset_polygon(&reversed, 0.25, 16);
+        assert!(reversed.contains(&Vec2::new(1.25, 1.25)));
+        assert_eq!(offset.len(), reversed.len());
+    }
+
+    #[test]
+    fn no_radius_keeps_the_polygon() {
+        let triangle = [Vec2::ZERO, Vec2::X, Vec2::Y];
+        assert_eq!(offset_polygon(&triangle, 0.0, 16), triangle.to_vec());
+    }
+}
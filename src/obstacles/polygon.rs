@@ -0,0 +1,153 @@
+use bevy::{
+    math::{Vec2, Vec3Swizzles},
+    prelude::Component,
+    transform::components::{GlobalTransform, Transform},
+};
+use polyanya::Triangulation;
+
+use super::ObstacleSource;
+use crate::builder::has_area;
+
+/// An obstacle from a polygon, for example authored in a level editor.
+///
+/// Points are in the local space of the obstacle, on the same plane as the navmesh. Holes are parts of the polygon that
+/// are not an obstacle, they are navigable but can't be reached from the outside.
+#[derive(Component, Debug, Clone, Default)]
+pub struct PolygonObstacle {
+    /// Outline of the obstacle.
+    pub polygon: Vec<Vec2>,
+    /// Holes in the obstacle.
+    pub holes: Vec<Vec<Vec2>>,
+}
+
+impl PolygonObstacle {
+    /// Create an obstacle from its outline.
+    pub fn new(polygon: Vec<Vec2>) -> Self {
+        Self {
+            polygon,
+            holes: vec![],
+        }
+    }
+
+    /// Add a hole to the obstacle.
+    pub fn with_hole(mut self, hole: Vec<Vec2>) -> Self {
+        self.holes.push(hole);
+        self
+    }
+
+    /// Polygons covering the obstacle, in its local space.
+    fn local_polygons(&self) -> Vec<Vec<Vec2>> {
+        // Polyanya panics when triangulating an outline that can't be triangulated
+        if !has_area(&self.polygon) {
+            return vec![];
+        }
+        if self.holes.is_empty() {
+            return vec![self.polygon.clone()];
+        }
+        // Obstacles can't have holes, split the polygon around its holes by triangulating it
+        let mut triangulation = Triangulation::from_outer_edges(&self.polygon);
+        triangulation.add_obstacles(self.holes.iter().cloned());
+        let mesh = triangulation.as_navmesh();
+        mesh.polygons
+            .iter()
+            .map(|polygon| {
+                polygon
+                    .vertices
+                    .iter()
+                    .map(|index| mesh.vertices[*index as usize].coords)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Transform a polygon from the local space of the obstacle to the local space of the mesh.
+fn to_navmesh(
+    polygon: &[Vec2],
+    obstacle_transform: &GlobalTransform,
+    navmesh_transform: &Transform,
+) -> Vec<Vec2> {
+    let transform = obstacle_transform.compute_transform();
+    let to_navmesh = navmesh_transform.compute_affine().inverse();
+    polygon
+        .iter()
+        .map(|point| {
+            navmesh_transform
+                .transform_point(
+                    transform.transform_point(to_navmesh.transform_point3(point.extend(0.0))),
+                )
+                .xy()
+        })
+        .collect()
+}
+
+impl ObstacleSource for PolygonObstacle {
+    /// The outline of the obstacle, ignoring its holes.
+    fn get_polygon(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec2> {
+        to_navmesh(&self.polygon, obstacle_transform, navmesh_transform)
+    }
+
+    fn get_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec<Vec2>> {
+        self.local_polygons()
+            .iter()
+            .map(|polygon| to_navmesh(polygon, obstacle_transform, navmesh_transform))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::NavMeshSettings, NavMeshBaker};
+
+    fn square(min: f32, max: f32) -> Vec<Vec2> {
+        vec![
+            Vec2::new(min, min),
+            Vec2::new(max, min),
+            Vec2::new(max, max),
+            Vec2::new(min, max),
+        ]
+    }
+
+    #[test]
+    fn hole_is_navigable_inside_the_obstacle() {
+        let obstacle = PolygonObstacle::new(square(2.0, 8.0)).with_hole(square(4.0, 6.0));
+        let mut baker =
+            NavMeshBaker::new(NavMeshSettings::default()).with_outer_edges(&square(0.0, 10.0));
+        baker
+            .add_obstacle_source(&obstacle, &GlobalTransform::IDENTITY)
+            .unwrap();
+        let navmesh = baker.bake().unwrap();
+
+        assert!(navmesh.is_in_mesh(Vec2::new(5.0, 5.0)));
+        assert!(navmesh.is_in_mesh(Vec2::new(1.0, 1.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(3.0, 3.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(7.0, 5.0)));
+        assert!(navmesh
+            .path(Vec2::new(1.0, 1.0), Vec2::new(5.0, 5.0))
+            .is_none());
+    }
+
+    #[test]
+    fn degenerate_outline_has_no_polygons() {
+        let hole = square(4.0, 6.0);
+        assert!(PolygonObstacle::default()
+            .with_hole(hole.clone())
+            .local_polygons()
+            .is_empty());
+        assert!(
+            PolygonObstacle::new(vec![Vec2::ZERO, Vec2::X, Vec2::X * 2.0])
+                .with_hole(hole)
+                .local_polygons()
+                .is_empty()
+        );
+    }
+}
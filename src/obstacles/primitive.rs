@@ -1,19 +1,20 @@
-use std::f32::consts::PI;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
 
 use bevy::{
     math::{vec2, Rot2, Vec2, Vec3, Vec3Swizzles},
     prelude::{
-        Capsule2d, Circle, CircularSector, CircularSegment, Component, Ellipse, Rectangle,
-        RegularPolygon, Rhombus,
+        Annulus, BoxedPolygon, Capsule2d, Circle, CircularSector, CircularSegment, Component,
+        Ellipse, Polygon, Rectangle, RegularPolygon, Rhombus,
     },
     transform::components::{GlobalTransform, Transform},
 };
 
 use super::ObstacleSource;
+use crate::geometry::convex_hull;
 
 /// A primitive obstacle that can be used to create a [`NavMesh`].
 /// Variants are made from primitive shapes defined in Bevy
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone)]
 pub enum PrimitiveObstacle {
     /// A rectangle primitive.
     Rectangle(Rectangle),
@@ -32,6 +33,17 @@ pub enum PrimitiveObstacle {
     RegularPolygon(RegularPolygon),
     /// A rhombus primitive, also known as a diamond shape.
     Rhombus(Rhombus),
+    /// A ring, whose inside is not an obstacle. The inside is navigable, but can't be reached from the outside.
+    Annulus(Annulus),
+    /// A circular sector with its center cut out, between `inner_radius` and the radius of the sector.
+    AnnularSector {
+        /// The sector.
+        sector: CircularSector,
+        /// Radius of the part of the sector that is not an obstacle.
+        inner_radius: f32,
+    },
+    /// A polygon with any number of vertices. A [`Polygon`] can be converted into this variant.
+    Polygon(BoxedPolygon),
 }
 
 // Functions in this module are copied from Bevy
@@ -76,11 +88,25 @@ mod copypasta {
 }
 
 impl ObstacleSource for PrimitiveObstacle {
+    /// The convex hull of the polygons of the obstacle.
     fn get_polygon(
         &self,
         obstacle_transform: &GlobalTransform,
         navmesh_transform: &Transform,
     ) -> Vec<Vec2> {
+        convex_hull(
+            self.get_polygons(obstacle_transform, navmesh_transform)
+                .into_iter()
+                .flatten()
+                .collect(),
+        )
+    }
+
+    fn get_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec<Vec2>> {
         let transform = obstacle_transform.compute_transform();
         let to_vec2 = |v: Vec3| navmesh_transform.transform_point(v).xy();
         let to_navmesh = |v: Vec2| {
@@ -91,7 +117,7 @@ impl ObstacleSource for PrimitiveObstacle {
         };
 
         match self {
-            PrimitiveObstacle::Rectangle(primitive) => vec![
+            PrimitiveObstacle::Rectangle(primitive) => vec![vec![
                 to_vec2(transform.transform_point(to_navmesh(vec2(
                     -primitive.half_size.x,
                     -primitive.half_size.y,
@@ -108,16 +134,18 @@ impl ObstacleSource for PrimitiveObstacle {
                     primitive.half_size.x,
                     -primitive.half_size.y,
                 )))),
-            ],
+            ]],
             PrimitiveObstacle::Circle(primitive) => {
-                copypasta::ellipse_inner(vec2(primitive.radius, primitive.radius), 32)
-                    .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
-                    .collect()
+                vec![
+                    copypasta::ellipse_inner(vec2(primitive.radius, primitive.radius), 32)
+                        .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
+                        .collect(),
+                ]
             }
             PrimitiveObstacle::Ellipse(primitive) => {
-                copypasta::ellipse_inner(primitive.half_size, 32)
+                vec![copypasta::ellipse_inner(primitive.half_size, 32)
                     .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
-                    .collect()
+                    .collect()]
             }
             PrimitiveObstacle::CircularSector(primitive) => {
                 let mut arc = copypasta::arc_2d_inner(
@@ -129,12 +157,17 @@ impl ObstacleSource for PrimitiveObstacle {
                 .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
                 .collect::<Vec<_>>();
                 arc.push(to_vec2(transform.translation));
-                arc
+                vec![arc]
             }
             PrimitiveObstacle::CircularSegment(primitive) => {
-                copypasta::arc_2d_inner(0.0, primitive.arc.angle() as f64, primitive.arc.radius, 32)
-                    .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
-                    .collect()
+                vec![copypasta::arc_2d_inner(
+                    0.0,
+                    primitive.arc.angle() as f64,
+                    primitive.arc.radius,
+                    32,
+                )
+                .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
+                .collect()]
             }
             PrimitiveObstacle::Capsule(primitive) => {
                 let mut points =
@@ -156,9 +189,9 @@ impl ObstacleSource for PrimitiveObstacle {
                         },
                     ),
                 );
-                points
+                vec![points]
             }
-            PrimitiveObstacle::RegularPolygon(primitive) => (0..=primitive.sides)
+            PrimitiveObstacle::RegularPolygon(primitive) => vec![(0..=primitive.sides)
                 .map(|p| {
                     copypasta::single_circle_coordinate(
                         primitive.circumcircle.radius,
@@ -167,9 +200,9 @@ impl ObstacleSource for PrimitiveObstacle {
                     )
                 })
                 .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
-                .collect(),
+                .collect()],
             PrimitiveObstacle::Rhombus(primitive) => {
-                [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)]
+                vec![[(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)]
                     .map(|(sign_x, sign_y)| {
                         Vec2::new(
                             primitive.half_diagonals.x * sign_x,
@@ -177,9 +210,126 @@ impl ObstacleSource for PrimitiveObstacle {
                         )
                     })
                     .into_iter()
+                    .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
+                    .collect()]
+            }
+            PrimitiveObstacle::Annulus(primitive) => ring_pieces(
+                primitive.inner_circle.radius,
+                primitive.outer_circle.radius,
+                0.0,
+                TAU,
+            )
+            .map(|piece| {
+                piece
                     .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
                     .collect()
+            })
+            .collect(),
+            PrimitiveObstacle::AnnularSector {
+                sector,
+                inner_radius,
+            } => ring_pieces(
+                *inner_radius,
+                sector.arc.radius,
+                FRAC_PI_2 - sector.arc.half_angle,
+                sector.arc.angle(),
+            )
+            .map(|piece| {
+                piece
+                    .map(|v| to_vec2(transform.transform_point(to_navmesh(v))))
+                    .collect()
+            })
+            .collect(),
+            PrimitiveObstacle::Polygon(primitive) => vec![primitive
+                .vertices
+                .iter()
+                .map(|v| to_vec2(transform.transform_point(to_navmesh(*v))))
+                .collect()],
+        }
+    }
+}
+
+/// Number of pieces a full ring is split into. Obstacles can't have holes, a ring made of several pieces doesn't cover
+/// its inside.
+const RING_PIECES: usize = 8;
+
+/// Number of segments of the arcs of each piece of a ring.
+const RING_PIECE_RESOLUTION: usize = 4;
+
+/// Split the ring between `inner` and `outer` radius, from `start` angle and for `angle` radians, in pieces.
+fn ring_pieces(
+    inner: f32,
+    outer: f32,
+    start: f32,
+    angle: f32,
+) -> impl Iterator<Item = impl Iterator<Item = Vec2>> {
+    let pieces = ((angle / TAU * RING_PIECES as f32).ceil() as usize).max(1);
+    let step = angle / (pieces * RING_PIECE_RESOLUTION) as f32;
+    (0..pieces).map(move |piece| {
+        let first = piece * RING_PIECE_RESOLUTION;
+        let point = move |i: usize, radius: f32| Vec2::from_angle(start + step * i as f32) * radius;
+        (first..=first + RING_PIECE_RESOLUTION)
+            .map(move |i| point(i, outer))
+            .chain(
+                (first..=first + RING_PIECE_RESOLUTION)
+                    .rev()
+                    .map(move |i| point(i, inner)),
+            )
+    })
+}
+
+impl<const N: usize> From<Polygon<N>> for PrimitiveObstacle {
+    fn from(polygon: Polygon<N>) -> Self {
+        PrimitiveObstacle::Polygon(BoxedPolygon::new(polygon.vertices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::contains_point;
+
+    use super::*;
+
+    fn pieces(obstacle: PrimitiveObstacle) -> Vec<Vec<Vec2>> {
+        obstacle.get_polygons(&GlobalTransform::IDENTITY, &Transform::IDENTITY)
+    }
+
+    #[test]
+    fn annulus_pieces_cover_the_ring_but_not_its_inside() {
+        let pieces = pieces(PrimitiveObstacle::Annulus(Annulus::new(1.0, 2.0)));
+        assert_eq!(pieces.len(), RING_PIECES);
+
+        for piece in &pieces {
+            for point in piece {
+                assert!((0.999..=2.001).contains(&point.length()));
             }
         }
+        for i in 0..64 {
+            let direction = Vec2::from_angle((i as f32 + 0.5) / 64.0 * TAU);
+            assert!(pieces
+                .iter()
+                .any(|piece| contains_point(piece, direction * 1.5)));
+            assert!(!pieces
+                .iter()
+                .any(|piece| contains_point(piece, direction * 0.5)));
+        }
+    }
+
+    #[test]
+    fn annular_sector_pieces_follow_the_sector() {
+        let pieces = pieces(PrimitiveObstacle::AnnularSector {
+            sector: CircularSector::new(2.0, FRAC_PI_2),
+            inner_radius: 1.0,
+        });
+        // A half turn is half of the pieces of a full ring
+        assert_eq!(pieces.len(), RING_PIECES / 2);
+
+        let covered = |point: Vec2| pieces.iter().any(|piece| contains_point(piece, point));
+        // Sectors are centered on the `y` axis
+        assert!(covered(Vec2::new(0.0, 1.5)));
+        assert!(covered(Vec2::new(1.4, 0.1)));
+        assert!(covered(Vec2::new(-1.4, 0.1)));
+        assert!(!covered(Vec2::new(0.0, -1.5)));
+        assert!(!covered(Vec2::new(0.0, 0.5)));
     }
 }
@@ -0,0 +1,129 @@
+use bevy::{
+    log::warn,
+    math::{Vec2, Vec3Swizzles},
+    transform::components::{GlobalTransform, Transform},
+};
+use bevy_rapier2d::{
+    prelude::Collider,
+    rapier::{
+        math::{Isometry, Point, Real},
+        parry::shape::TypedShape,
+    },
+};
+
+use super::{offset::offset_polygon, ObstacleSource};
+use crate::geometry::convex_hull;
+
+/// Number of segments for a full circle when approximating round shapes.
+const RESOLUTION: usize = 32;
+
+impl ObstacleSource for Collider {
+    /// The convex hull of the polygons of the obstacle.
+    fn get_polygon(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec2> {
+        convex_hull(
+            self.get_polygons(obstacle_transform, navmesh_transform)
+                .into_iter()
+                .flatten()
+                .collect(),
+        )
+    }
+
+    fn get_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec<Vec2>> {
+        // The collider shape is already scaled by Rapier, only use the translation and rotation
+        let (_, rotation, translation) = obstacle_transform.to_scale_rotation_translation();
+        let transform = Transform::from_translation(translation).with_rotation(rotation);
+        let to_navmesh =
+            |v: Vec2| navmesh_transform.transform_point(transform.transform_point(v.extend(0.0)));
+
+        shape_polygons(self.raw.as_typed_shape(), &Isometry::identity())
+            .into_iter()
+            .map(|polygon| polygon.into_iter().map(|v| to_navmesh(v).xy()).collect())
+            .collect()
+    }
+}
+
+fn to_vec2(isometry: &Isometry<Real>, point: Point<Real>) -> Vec2 {
+    let point = isometry * point;
+    Vec2::new(point.x, point.y)
+}
+
+fn circle(isometry: &Isometry<Real>, center: Point<Real>, radius: Real) -> Vec<Vec2> {
+    let center = to_vec2(isometry, center);
+    (0..RESOLUTION)
+        .map(|i| {
+            center + Vec2::from_angle(i as f32 * std::f32::consts::TAU / RESOLUTION as f32) * radius
+        })
+        .collect()
+}
+
+/// Polygons of a shape, in the collider space.
+fn shape_polygons(shape: TypedShape, isometry: &Isometry<Real>) -> Vec<Vec<Vec2>> {
+    match shape {
+        TypedShape::Ball(ball) => vec![circle(isometry, Point::origin(), ball.radius)],
+        TypedShape::Cuboid(cuboid) => vec![cuboid
+            .to_polyline()
+            .into_iter()
+            .map(|point| to_vec2(isometry, point))
+            .collect()],
+        TypedShape::Capsule(capsule) => {
+            let mut points = circle(isometry, capsule.segment.a, capsule.radius);
+            points.extend(circle(isometry, capsule.segment.b, capsule.radius));
+            vec![convex_hull(points)]
+        }
+        TypedShape::Triangle(triangle) => vec![[triangle.a, triangle.b, triangle.c]
+            .into_iter()
+            .map(|point| to_vec2(isometry, point))
+            .collect()],
+        TypedShape::ConvexPolygon(polygon) => vec![polygon
+            .points()
+            .iter()
+            .map(|point| to_vec2(isometry, *point))
+            .collect()],
+        TypedShape::TriMesh(trimesh) => trimesh
+            .triangles()
+            .map(|triangle| {
+                [triangle.a, triangle.b, triangle.c]
+                    .into_iter()
+                    .map(|point| to_vec2(isometry, point))
+                    .collect()
+            })
+            .collect(),
+        TypedShape::Compound(compound) => compound
+            .shapes()
+            .iter()
+            .flat_map(|(shape_isometry, shape)| {
+                shape_polygons(shape.as_typed_shape(), &(isometry * shape_isometry))
+            })
+            .collect(),
+        TypedShape::RoundCuboid(round) => {
+            shape_polygons(TypedShape::Cuboid(&round.inner_shape), isometry)
+                .into_iter()
+                .map(|polygon| offset_polygon(&polygon, round.border_radius, RESOLUTION))
+                .collect()
+        }
+        TypedShape::RoundTriangle(round) => {
+            shape_polygons(TypedShape::Triangle(&round.inner_shape), isometry)
+                .into_iter()
+                .map(|polygon| offset_polygon(&polygon, round.border_radius, RESOLUTION))
+                .collect()
+        }
+        TypedShape::RoundConvexPolygon(round) => {
+            shape_polygons(TypedShape::ConvexPolygon(&round.inner_shape), isometry)
+                .into_iter()
+                .map(|polygon| offset_polygon(&polygon, round.border_radius, RESOLUTION))
+                .collect()
+        }
+        _ => {
+            warn!("unsupported collider shape for obstacle");
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    log::warn,
+    math::{Vec2, Vec3, Vec3Swizzles},
+    transform::components::{GlobalTransform, Transform},
+};
+use bevy_rapier3d::{
+    prelude::Collider,
+    rapier::{
+        math::{Isometry, Point, Real, Vector},
+        parry::shape::TypedShape,
+    },
+};
+
+use super::{offset::offset_polygon, sliced_hull, sliced_mesh, ObstacleSource};
+use crate::geometry::convex_hull;
+
+/// Number of segments for a full circle when approximating round shapes.
+const RESOLUTION: usize = 32;
+
+/// Maximum slope of the walkable parts of heightfields, in radians. Steeper parts are obstacles.
+const HEIGHTFIELD_MAX_SLOPE: f32 = std::f32::consts::FRAC_PI_4;
+
+/// Projects points from the collider space to the navmesh space.
+struct Projection<'a> {
+    transform: Transform,
+    navmesh_transform: &'a Transform,
+    /// Heights between which shapes are cut, `None` to project whole shapes.
+    slice: Option<(f32, f32)>,
+}
+
+impl<'a> Projection<'a> {
+    fn project(&self, isometry: &Isometry<Real>, point: Point<Real>) -> Vec2 {
+        self.to_navmesh(isometry, point).xy()
+    }
+
+    /// Transform a point from the collider space to the navmesh space, keeping its height.
+    fn to_navmesh(&self, isometry: &Isometry<Real>, point: Point<Real>) -> Vec3 {
+        let point = isometry * point;
+        self.navmesh_transform.transform_point(
+            self.transform
+                .transform_point(Vec3::new(point.x, point.y, point.z)),
+        )
+    }
+
+    /// Sample a circle around `center`, perpendicular to `axis`.
+    fn circle(
+        &self,
+        isometry: &Isometry<Real>,
+        center: Point<Real>,
+        axis: Vector<Real>,
+        radius: Real,
+    ) -> impl Iterator<Item = Vec2> + '_ {
+        let (x, y) = Vec3::new(axis.x, axis.y, axis.z)
+            .normalize_or_zero()
+            .any_orthonormal_pair();
+        let isometry = *isometry;
+        (0..RESOLUTION).map(move |i| {
+            let (sin, cos) = (i as f32 * TAU / RESOLUTION as f32).sin_cos();
+            let offset = (x * cos + y * sin) * radius;
+            self.project(
+                &isometry,
+                center + Vector::new(offset.x, offset.y, offset.z),
+            )
+        })
+    }
+
+    /// Sample a sphere around `center`, on the three axis planes. Its projection is a disk.
+    fn sphere(&self, isometry: &Isometry<Real>, center: Point<Real>, radius: Real) -> Vec<Vec2> {
+        [Vector::x(), Vector::y(), Vector::z()]
+            .into_iter()
+            .flat_map(|axis| self.circle(isometry, center, axis, radius))
+            .collect()
+    }
+
+    /// Projected polygons of the parts of a shape between two heights. Returns `None` for shapes that are not cut.
+    fn sliced_polygons(
+        &self,
+        shape: TypedShape,
+        isometry: &Isometry<Real>,
+        min_height: f32,
+        max_height: f32,
+    ) -> Option<Vec<Vec<Vec2>>> {
+        let resolution = RESOLUTION as u32;
+        let convex = |(vertices, indices): (Vec<Point<Real>>, Vec<[u32; 3]>)| {
+            let triangles = indices.into_iter().map(|triangle| {
+                triangle.map(|index| self.to_navmesh(isometry, vertices[index as usize]))
+            });
+            vec![sliced_hull(triangles, min_height, max_height)]
+        };
+        let polygons = match shape {
+            TypedShape::Ball(ball) => convex(ball.to_trimesh(resolution, resolution / 2)),
+            TypedShape::Cuboid(cuboid) => convex(cuboid.to_trimesh()),
+            TypedShape::Capsule(capsule) => convex(capsule.to_trimesh(resolution, resolution / 2)),
+            TypedShape::Cylinder(cylinder) => convex(cylinder.to_trimesh(resolution)),
+            TypedShape::Cone(cone) => convex(cone.to_trimesh(resolution)),
+            TypedShape::Triangle(triangle) => {
+                convex((vec![triangle.a, triangle.b, triangle.c], vec![[0, 1, 2]]))
+            }
+            TypedShape::ConvexPolyhedron(polyhedron) => convex(polyhedron.to_trimesh()),
+            TypedShape::TriMesh(trimesh) => sliced_mesh(
+                &trimesh
+                    .triangles()
+                    .map(|triangle| {
+                        [triangle.a, triangle.b, triangle.c]
+                            .map(|point| self.to_navmesh(isometry, point))
+                    })
+                    .collect::<Vec<_>>(),
+                min_height,
+                max_height,
+            ),
+            TypedShape::Compound(compound) => compound
+                .shapes()
+                .iter()
+                .flat_map(|(shape_isometry, shape)| {
+                    self.shape_polygons(shape.as_typed_shape(), &(isometry * shape_isometry))
+                })
+                .collect(),
+            _ => return None,
+        };
+        Some(
+            polygons
+                .into_iter()
+                .filter(|polygon| polygon.len() >= 3)
+                .collect(),
+        )
+    }
+
+    /// Projected polygons of a shape.
+    fn shape_polygons(&self, shape: TypedShape, isometry: &Isometry<Real>) -> Vec<Vec<Vec2>> {
+        if let Some((min_height, max_height)) = self.slice {
+            if let Some(polygons) = self.sliced_polygons(shape, isometry, min_height, max_height) {
+                return polygons;
+            }
+        }
+        let hull = |points: Vec<Vec2>| vec![convex_hull(points)];
+        match shape {
+            TypedShape::Ball(ball) => hull(self.sphere(isometry, Point::origin(), ball.radius)),
+            TypedShape::Cuboid(cuboid) => hull(
+                cuboid
+                    .to_trimesh()
+                    .0
+                    .into_iter()
+                    .map(|point| self.project(isometry, point))
+                    .collect(),
+            ),
+            TypedShape::Capsule(capsule) => {
+                let mut points = self.sphere(isometry, capsule.segment.a, capsule.radius);
+                points.extend(self.sphere(isometry, capsule.segment.b, capsule.radius));
+                hull(points)
+            }
+            TypedShape::Cylinder(cylinder) => hull(
+                [-cylinder.half_height, cylinder.half_height]
+                    .into_iter()
+                    .flat_map(|height| {
+                        self.circle(
+                            isometry,
+                            Point::new(0.0, height, 0.0),
+                            Vector::y(),
+                            cylinder.radius,
+                        )
+                    })
+                    .collect(),
+            ),
+            TypedShape::Cone(cone) => {
+                let mut points = self
+                    .circle(
+                        isometry,
+                        Point::new(0.0, -cone.half_height, 0.0),
+                        Vector::y(),
+                        cone.radius,
+                    )
+                    .collect::<Vec<_>>();
+                points.push(self.project(isometry, Point::new(0.0, cone.half_height, 0.0)));
+                hull(points)
+            }
+            TypedShape::Triangle(triangle) => hull(
+                [triangle.a, triangle.b, triangle.c]
+                    .into_iter()
+                    .map(|point| self.project(isometry, point))
+                    .collect(),
+            ),
+            TypedShape::ConvexPolyhedron(polyhedron) => hull(
+                polyhedron
+                    .points()
+                    .iter()
+                    .map(|point| self.project(isometry, *point))
+                    .collect(),
+            ),
+            TypedShape::TriMesh(trimesh) => trimesh
+                .triangles()
+                .map(|triangle| {
+                    convex_hull(
+                        [triangle.a, triangle.b, triangle.c]
+                            .into_iter()
+                            .map(|point| self.project(isometry, point))
+                            .collect(),
+                    )
+                })
+                .filter(|polygon| polygon.len() == 3)
+                .collect(),
+            // Parts of the terrain too steep to walk on are obstacles
+            TypedShape::HeightField(heightfield) => heightfield
+                .triangles()
+                .filter_map(|triangle| {
+                    let [a, b, c] = [triangle.a, triangle.b, triangle.c]
+                        .map(|point| self.to_navmesh(isometry, point));
+                    let normal = (b - a).cross(c - a).normalize_or_zero();
+                    let slope = normal.z.abs().acos();
+                    (slope > HEIGHTFIELD_MAX_SLOPE)
+                        .then(|| convex_hull(vec![a.xy(), b.xy(), c.xy()]))
+                })
+                .filter(|polygon| polygon.len() == 3)
+                .collect(),
+            TypedShape::Compound(compound) => compound
+                .shapes()
+                .iter()
+                .flat_map(|(shape_isometry, shape)| {
+                    self.shape_polygons(shape.as_typed_shape(), &(isometry * shape_isometry))
+                })
+                .collect(),
+            TypedShape::RoundCuboid(round) => self.rounded(
+                TypedShape::Cuboid(&round.inner_shape),
+                isometry,
+                round.border_radius,
+            ),
+            TypedShape::RoundTriangle(round) => self.rounded(
+                TypedShape::Triangle(&round.inner_shape),
+                isometry,
+                round.border_radius,
+            ),
+            TypedShape::RoundCylinder(round) => self.rounded(
+                TypedShape::Cylinder(&round.inner_shape),
+                isometry,
+                round.border_radius,
+            ),
+            TypedShape::RoundCone(round) => self.rounded(
+                TypedShape::Cone(&round.inner_shape),
+                isometry,
+                round.border_radius,
+            ),
+            TypedShape::RoundConvexPolyhedron(round) => self.rounded(
+                TypedShape::ConvexPolyhedron(&round.inner_shape),
+                isometry,
+                round.border_radius,
+            ),
+            _ => {
+                warn!("unsupported collider shape for obstacle");
+                vec![]
+            }
+        }
+    }
+
+    fn rounded(
+        &self,
+        inner_shape: TypedShape,
+        isometry: &Isometry<Real>,
+        border_radius: Real,
+    ) -> Vec<Vec<Vec2>> {
+        let scale = self.navmesh_transform.scale.x;
+        self.shape_polygons(inner_shape, isometry)
+            .into_iter()
+            .map(|polygon| offset_polygon(&polygon, border_radius * scale, RESOLUTION))
+            .collect()
+    }
+}
+
+fn collider_polygons(
+    collider: &Collider,
+    obstacle_transform: &GlobalTransform,
+    navmesh_transform: &Transform,
+    slice: Option<(f32, f32)>,
+) -> Vec<Vec<Vec2>> {
+    // The collider shape is already scaled by Rapier, only use the translation and rotation
+    let (_, rotation, translation) = obstacle_transform.to_scale_rotation_translation();
+    let projection = Projection {
+        transform: Transform::from_translation(translation).with_rotation(rotation),
+        navmesh_transform,
+        slice,
+    };
+    projection.shape_polygons(collider.raw.as_typed_shape(), &Isometry::identity())
+}
+
+impl ObstacleSource for Collider {
+    /// The convex hull of the polygons of the obstacle.
+    fn get_polygon(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec2> {
+        convex_hull(
+            self.get_polygons(obstacle_transform, navmesh_transform)
+                .into_iter()
+                .flatten()
+                .collect(),
+        )
+    }
+
+    fn get_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec<Vec2>> {
+        collider_polygons(self, obstacle_transform, navmesh_transform, None)
+    }
+
+    fn get_sliced_polygons(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+        min_height: f32,
+        max_height: f32,
+    ) -> Vec<Vec<Vec2>> {
+        collider_polygons(
+            self,
+            obstacle_transform,
+            navmesh_transform,
+            Some((min_height, max_height)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Quat;
+    use bevy_rapier3d::rapier::parry::shape::Cuboid;
+
+    use crate::geometry::signed_area;
+
+    use super::*;
+
+    #[test]
+    fn slicing_a_trimesh_keeps_its_cross_section() {
+        let (vertices, indices) = Cuboid::new(Vector::new(1.0, 1.0, 2.0)).to_trimesh();
+        let collider = Collider::trimesh(
+            vertices
+                .into_iter()
+                .map(|point| Vec3::new(point.x, point.y, point.z))
+                .collect(),
+            indices,
+        );
+        let transform = GlobalTransform::from_translation(Vec3::new(0.0, 0.0, 2.0));
+
+        let section = collider.get_sliced_polygons(&transform, &Transform::IDENTITY, 1.0, 1.0);
+        assert_eq!(section.len(), 1);
+        assert!((signed_area(&section[0]) - 4.0).abs() < 1e-4);
+
+        // The walls cut by the slab keep the footprint of the box
+        let slab = collider.get_sliced_polygons(&transform, &Transform::IDENTITY, 1.0, 3.0);
+        assert!(slab
+            .iter()
+            .any(|polygon| (signed_area(polygon) - 4.0).abs() < 1e-4));
+
+        // Tilted, its cross-section is stretched
+        let tilted = GlobalTransform::from(
+            Transform::from_translation(Vec3::new(0.0, 0.0, 2.0))
+                .with_rotation(Quat::from_rotation_x(0.5)),
+        );
+        let section = collider.get_sliced_polygons(&tilted, &Transform::IDENTITY, 2.0, 2.0);
+        assert_eq!(section.len(), 1);
+        assert!((signed_area(&section[0]) - 4.0 / 0.5_f32.cos()).abs() < 1e-3);
+
+        let above = collider.get_sliced_polygons(&transform, &Transform::IDENTITY, 10.0, 10.0);
+        assert!(above.is_empty());
+    }
+
+    #[test]
+    fn slicing_a_compound_slices_its_children() {
+        let collider = Collider::compound(vec![
+            (
+                Vec3::new(0.0, 0.0, 2.0),
+                Quat::IDENTITY,
+                Collider::cuboid(1.0, 1.0, 1.0),
+            ),
+            (
+                Vec3::new(4.0, 0.0, 10.0),
+                Quat::IDENTITY,
+                Collider::cuboid(1.0, 1.0, 1.0),
+            ),
+        ]);
+
+        let whole = collider.get_polygons(&GlobalTransform::IDENTITY, &Transform::IDENTITY);
+        assert_eq!(whole.len(), 2);
+
+        let sliced = collider.get_sliced_polygons(
+            &GlobalTransform::IDENTITY,
+            &Transform::IDENTITY,
+            1.5,
+            1.5,
+        );
+        assert_eq!(sliced.len(), 1);
+        assert!((signed_area(&sliced[0]) - 4.0).abs() < 1e-4);
+    }
+}
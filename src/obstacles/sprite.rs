@@ -0,0 +1,88 @@
+use bevy::{
+    math::{vec2, Vec2, Vec3Swizzles},
+    sprite::Sprite,
+    transform::components::{GlobalTransform, Transform},
+};
+
+use super::ObstacleSource;
+
+/// A sprite obstacle covers the area of the sprite, taking its anchor into account.
+///
+/// The size of the image is not known when building the navmesh, so the size of the sprite is taken from its
+/// [`custom_size`](Sprite::custom_size), or from its [`rect`](Sprite::rect) when it's a region of a larger image.
+/// Sprites with neither are ignored.
+impl ObstacleSource for Sprite {
+    fn get_polygon(
+        &self,
+        obstacle_transform: &GlobalTransform,
+        navmesh_transform: &Transform,
+    ) -> Vec<Vec2> {
+        let Some(size) = self.custom_size.or(self.rect.map(|rect| rect.size())) else {
+            return vec![];
+        };
+        let center = -self.anchor.as_vec() * size;
+        let half_size = size / 2.0;
+        let to_navmesh = |v: Vec2| {
+            navmesh_transform
+                .transform_point(obstacle_transform.transform_point((center + v).extend(0.0)))
+                .xy()
+        };
+
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .into_iter()
+            .map(|(x, y)| to_navmesh(vec2(x, y) * half_size))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::Rect, sprite::Anchor};
+
+    use super::*;
+
+    #[test]
+    fn anchor_offsets_the_polygon() {
+        let sprite = Sprite {
+            custom_size: Some(vec2(2.0, 4.0)),
+            anchor: Anchor::BottomLeft,
+            ..Default::default()
+        };
+        let polygon = sprite.get_polygon(
+            &GlobalTransform::from_xyz(10.0, 20.0, 0.0),
+            &Transform::IDENTITY,
+        );
+
+        assert_eq!(
+            polygon,
+            vec![
+                vec2(10.0, 20.0),
+                vec2(12.0, 20.0),
+                vec2(12.0, 24.0),
+                vec2(10.0, 24.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn size_falls_back_to_the_rect() {
+        let sprite = Sprite {
+            rect: Some(Rect::new(8.0, 8.0, 12.0, 10.0)),
+            ..Default::default()
+        };
+        let polygon = sprite.get_polygon(&GlobalTransform::IDENTITY, &Transform::IDENTITY);
+
+        assert_eq!(
+            polygon,
+            vec![
+                vec2(-2.0, -1.0),
+                vec2(2.0, -1.0),
+                vec2(2.0, 1.0),
+                vec2(-2.0, 1.0),
+            ]
+        );
+        assert!(Sprite::default()
+            .get_polygon(&GlobalTransform::IDENTITY, &Transform::IDENTITY)
+            .is_empty());
+    }
+}
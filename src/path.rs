@@ -0,0 +1,692 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    math::Vec3Swizzles,
+    prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+use itertools::Itertools;
+use polyanya::Path;
+
+use crate::{NavMesh, TransformedPath};
+
+/// Post-processing to apply on a path, for more natural looking movements.
+///
+/// Steps are applied in order: corner offset, smoothing, decimation, then resampling.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PathOptions {
+    /// Distance to keep from the corners the path goes around. Corners are moved away from the obstacles, as long as
+    /// they stay in the navmesh.
+    pub agent_radius: f32,
+    /// Number of smoothing iterations. Each iteration cuts every corner of the path.
+    ///
+    /// A corner is only cut if the cut stays in the navmesh, otherwise it's kept. Corners of the path are on obstacles
+    /// and can rarely be cut, use with [`PathOptions::agent_radius`] to leave some space around them.
+    pub smoothing_iterations: usize,
+    /// Waypoints closer than this distance to the previous waypoint are removed.
+    pub min_waypoint_distance: f32,
+    /// Segments longer than this distance are split in evenly spaced waypoints, keeping the corners of the path.
+    ///
+    /// Useful for steering, animations, or network interpolation, that expect regularly spaced waypoints.
+    pub resample_spacing: f32,
+}
+
+impl PathOptions {
+    pub(crate) fn apply(&self, navmesh: &NavMesh, from: Vec2, path: Path) -> Path {
+        let mut points = Vec::with_capacity(path.path.len() + 1);
+        points.push(from);
+        points.extend(path.path);
+
+        if self.agent_radius > 0.0 {
+            for i in 1..points.len().saturating_sub(1) {
+                let corner = points[i];
+                let bisector = (points[i - 1] - corner).normalize_or_zero()
+                    + (points[i + 1] - corner).normalize_or_zero();
+                if bisector.length_squared() < f32::EPSILON {
+                    continue;
+                }
+                let moved = corner - bisector.normalize() * self.agent_radius;
+                if navmesh.is_in_mesh(moved) {
+                    points[i] = moved;
+                }
+            }
+        }
+
+        for _ in 0..self.smoothing_iterations {
+            if points.len() < 3 {
+                break;
+            }
+            let mut smoothed = Vec::with_capacity(points.len() * 3);
+            smoothed.push(points[0]);
+            for (previous, corner, next) in points.iter().tuple_windows() {
+                let cut = [previous.lerp(*corner, 0.75), corner.lerp(*next, 0.25)];
+                smoothed.push(cut[0]);
+                if !navmesh.is_reachable_in_straight_line(cut[0], cut[1]) {
+                    smoothed.push(*corner);
+                }
+                smoothed.push(cut[1]);
+            }
+            smoothed.push(*points.last().unwrap());
+            points = smoothed;
+        }
+
+        if self.min_waypoint_distance > 0.0 && points.len() > 2 {
+            let last = points.pop().unwrap();
+            let mut decimated: Vec<Vec2> = Vec::with_capacity(points.len());
+            for point in points {
+                if decimated
+                    .last()
+                    .map(|previous| previous.distance(point) >= self.min_waypoint_distance)
+                    .unwrap_or(true)
+                {
+                    decimated.push(point);
+                }
+            }
+            decimated.push(last);
+            points = decimated;
+        }
+
+        if self.resample_spacing > 0.0 && points.len() > 1 {
+            let mut resampled = Vec::with_capacity(points.len());
+            resampled.push(points[0]);
+            for (a, b) in points.iter().tuple_windows() {
+                let steps = (a.distance(*b) / self.resample_spacing).ceil().max(1.0) as usize;
+                resampled.extend((1..=steps).map(|step| a.lerp(*b, step as f32 / steps as f32)));
+            }
+            points = resampled;
+        }
+
+        Path {
+            length: points
+                .iter()
+                .tuple_windows()
+                .map(|(a, b)| a.distance(*b))
+                .sum(),
+            path: points.split_off(1),
+        }
+    }
+}
+
+/// A part of a [`TurningPath`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TurningPathPiece {
+    /// A straight line.
+    Line {
+        /// Start of the line.
+        start: Vec2,
+        /// End of the line.
+        end: Vec2,
+    },
+    /// An arc of circle, turning at a corner of the path. Sharp corners are arcs with a radius of 0.
+    Arc {
+        /// Center of the circle.
+        center: Vec2,
+        /// Radius of the circle.
+        radius: f32,
+        /// Angle of the start of the arc, in radians from the `x` axis.
+        start_angle: f32,
+        /// Angle covered by the arc, in radians. Positive when turning counter clockwise.
+        sweep: f32,
+    },
+}
+
+impl TurningPathPiece {
+    /// Length of the piece.
+    pub fn length(&self) -> f32 {
+        match self {
+            TurningPathPiece::Line { start, end } => start.distance(*end),
+            TurningPathPiece::Arc { radius, sweep, .. } => radius * sweep.abs(),
+        }
+    }
+
+    /// Point at `distance` from the start of the piece.
+    pub fn point_at(&self, distance: f32) -> Vec2 {
+        let t = if self.length() > 0.0 {
+            (distance / self.length()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        match self {
+            TurningPathPiece::Line { start, end } => start.lerp(*end, t),
+            TurningPathPiece::Arc {
+                center,
+                radius,
+                start_angle,
+                sweep,
+            } => *center + Vec2::from_angle(start_angle + sweep * t) * *radius,
+        }
+    }
+}
+
+/// A path made of lines and arcs, for agents that can't turn in place like cars or boats. See
+/// [`NavMesh::path_with_turning_radius`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurningPath {
+    /// Pieces of the path, in order.
+    pub pieces: Vec<TurningPathPiece>,
+    /// Length of the path.
+    pub length: f32,
+}
+
+impl TurningPath {
+    /// Smallest radius of the arcs of the path, `f32::INFINITY` if the path is straight.
+    ///
+    /// This is smaller than the requested turning radius where there isn't enough space to turn, and 0 if the path has a
+    /// sharp corner.
+    pub fn min_turning_radius(&self) -> f32 {
+        self.pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                TurningPathPiece::Arc { radius, .. } => Some(*radius),
+                TurningPathPiece::Line { .. } => None,
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Points along the path, at most `spacing` apart, including the start and end of each piece.
+    pub fn sample(&self, spacing: f32) -> Vec<Vec2> {
+        let mut points = vec![];
+        for piece in &self.pieces {
+            if points.is_empty() {
+                points.push(piece.point_at(0.0));
+            }
+            let length = piece.length();
+            let steps = if spacing > 0.0 {
+                (length / spacing).ceil().max(1.0) as usize
+            } else {
+                1
+            };
+            points.extend(
+                (1..=steps).map(|step| piece.point_at(length * step as f32 / steps as f32)),
+            );
+        }
+        points
+    }
+}
+
+/// Number of points checked on each arc of a [`TurningPath`] to keep it in the navmesh.
+const ARC_CHECKS: usize = 8;
+
+/// Number of times the radius of a turn of a [`TurningPath`] is halved before falling back to a sharp turn.
+const MAX_HALVINGS: u32 = 16;
+
+/// A circle followed by a [`TurningPath`] at a corner, a point when its radius is 0.
+struct Turn {
+    center: Vec2,
+    radius: f32,
+    /// `1.0` when turning counter clockwise, `-1.0` when turning clockwise.
+    direction: f32,
+}
+
+impl Turn {
+    fn point(point: Vec2) -> Self {
+        Turn {
+            center: point,
+            radius: 0.0,
+            direction: 0.0,
+        }
+    }
+
+    /// Points of the line leaving this circle and joining `other`, tangent to both in their direction of travel.
+    fn tangent(&self, other: &Turn) -> Option<(Vec2, Vec2)> {
+        // The center of a circle is on the left of the direction of travel when turning counter clockwise, so the line
+        // `u` satisfies `other.center - self.center = length * u + offset * u.perp()`
+        let delta = other.center - self.center;
+        let offset = other.direction * other.radius - self.direction * self.radius;
+        let length = (delta.length_squared() - offset * offset).sqrt();
+        if length.is_nan() || length <= f32::EPSILON {
+            return None;
+        }
+        let direction = (delta * length - delta.perp() * offset) / delta.length_squared();
+        Some((
+            self.center - direction.perp() * self.direction * self.radius,
+            other.center - direction.perp() * other.direction * other.radius,
+        ))
+    }
+
+    /// Arc following this circle from `from` to `to`, in its direction of travel. A turn with a radius of 0 is a sharp
+    /// corner, with an arc of radius and sweep 0.
+    fn arc(&self, from: Vec2, to: Vec2) -> TurningPathPiece {
+        let start_angle = (from - self.center).to_angle();
+        let end_angle = (to - self.center).to_angle();
+        let sweep = if self.radius == 0.0 {
+            0.0
+        } else if self.direction > 0.0 {
+            (end_angle - start_angle).rem_euclid(TAU)
+        } else {
+            -(start_angle - end_angle).rem_euclid(TAU)
+        };
+        TurningPathPiece::Arc {
+            center: self.center,
+            radius: self.radius,
+            start_angle,
+            sweep,
+        }
+    }
+}
+
+impl NavMesh {
+    /// Get a path between two points, post-processed with the given [`PathOptions`].
+    pub fn path_with_options(&self, from: Vec2, to: Vec2, options: &PathOptions) -> Option<Path> {
+        self.mesh
+            .path(from, to)
+            .map(|path| options.apply(self, from, path))
+    }
+
+    /// Get a path between two points, post-processed with the given [`PathOptions`].
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`]
+    pub fn transformed_path_with_options(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        options: &PathOptions,
+    ) -> Option<TransformedPath> {
+        let inner_from = self.transform.transform_point(from).xy();
+        let inner_to = self.transform.transform_point(to).xy();
+        self.path_with_options(inner_from, inner_to, options)
+            .map(|path| self.transform_path(path, from))
+    }
+
+    /// Get a path between two points for agents that can't turn in place, like cars or boats.
+    ///
+    /// At each corner of the path, the agent follows an arc of `turning_radius` that goes around the corner, touching
+    /// it, and the arcs are linked by lines tangent to them. Where there isn't enough space, because corners are close
+    /// together or the arc or its lines would leave the navmesh, a smaller radius is used, down to a sharp turn with a
+    /// radius of 0, see [`TurningPath::min_turning_radius`]. `turning_radius` is capped at the length of the path.
+    pub fn path_with_turning_radius(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        turning_radius: f32,
+    ) -> Option<TurningPath> {
+        let path = self.mesh.path(from, to)?;
+        // A turn can't be wider than the whole path, this also keeps the radius finite
+        let turning_radius = if turning_radius > 0.0 {
+            turning_radius.min(path.length)
+        } else {
+            0.0
+        };
+        let mut points = Vec::with_capacity(path.path.len() + 1);
+        points.push(from);
+        points.extend(path.path);
+
+        let mut pieces = vec![];
+        // Circle currently followed, and the point where the path joined it
+        let mut current = Turn::point(from);
+        let mut cursor = from;
+        // Follow `turn` from `entry` to `exit`, then go in a straight line to `next`
+        let push = |pieces: &mut Vec<_>, turn: &Turn, entry: Vec2, exit: Vec2, next: Vec2| {
+            if turn.direction != 0.0 {
+                pieces.push(turn.arc(entry, exit));
+            }
+            if exit.distance_squared(next) > f32::EPSILON {
+                pieces.push(TurningPathPiece::Line {
+                    start: exit,
+                    end: next,
+                });
+            }
+        };
+        for i in 1..points.len().saturating_sub(1) {
+            let (previous, corner, next) = (points[i - 1], points[i], points[i + 1]);
+            let incoming = (corner - previous).normalize_or_zero();
+            let outgoing = (next - corner).normalize_or_zero();
+            let inside = (outgoing - incoming).normalize_or_zero();
+            let direction = incoming.perp_dot(outgoing).signum();
+            if inside == Vec2::ZERO
+                || incoming == Vec2::ZERO
+                || outgoing == Vec2::ZERO
+                || (incoming.perp_dot(outgoing).abs() <= f32::EPSILON
+                    && incoming.dot(outgoing) > 0.0)
+            {
+                continue;
+            }
+            let mut radius = turning_radius;
+            let mut halvings = 0;
+            let (turn, exit, entry) = loop {
+                let turn = if radius > f32::EPSILON && halvings < MAX_HALVINGS {
+                    Turn {
+                        center: corner + inside * radius,
+                        radius,
+                        direction,
+                    }
+                } else {
+                    Turn {
+                        direction,
+                        ..Turn::point(corner)
+                    }
+                };
+                if turn.radius == 0.0 {
+                    let (exit, entry) = current.tangent(&turn).unwrap_or((cursor, corner));
+                    break (turn, exit, entry);
+                }
+                // The way out of the turn depends on the next one, check it as if it was towards the next point
+                let fits = |(exit, entry): (Vec2, Vec2)| {
+                    let Some((next_exit, _)) = turn.tangent(&Turn::point(next)) else {
+                        return false;
+                    };
+                    self.arc_in_mesh(&current.arc(cursor, exit))
+                        && self.line_in_mesh(exit, entry)
+                        && self.arc_in_mesh(&turn.arc(entry, next_exit))
+                        && self.line_in_mesh(next_exit, next)
+                };
+                if let Some(tangent) = current.tangent(&turn).filter(|tangent| fits(*tangent)) {
+                    break (turn, tangent.0, tangent.1);
+                }
+                radius /= 2.0;
+                halvings += 1;
+            };
+            push(&mut pieces, &current, cursor, exit, entry);
+            current = turn;
+            cursor = entry;
+        }
+        let end = *points.last().unwrap();
+        let (exit, _) = current.tangent(&Turn::point(end)).unwrap_or((cursor, end));
+        push(&mut pieces, &current, cursor, exit, end);
+        if pieces.is_empty() {
+            pieces.push(TurningPathPiece::Line { start: from, end });
+        }
+
+        Some(TurningPath {
+            length: pieces.iter().map(TurningPathPiece::length).sum(),
+            pieces,
+        })
+    }
+
+    /// Check if a line stays in the mesh. It can end on the boundary of the mesh, at a corner of the path.
+    fn line_in_mesh(&self, from: Vec2, to: Vec2) -> bool {
+        !matches!(self.raycast(from, to), Some(hit) if hit.distance_squared(to) > 1e-6)
+    }
+
+    /// Check if the points along an arc are in the mesh.
+    fn arc_in_mesh(&self, arc: &TurningPathPiece) -> bool {
+        let length = arc.length();
+        (0..=ARC_CHECKS)
+            .all(|step| self.is_in_mesh(arc.point_at(length * step as f32 / ARC_CHECKS as f32)))
+    }
+
+    /// Get paths between many pairs of points, computed in parallel on the [`ComputeTaskPool`].
+    ///
+    /// Results are in the same order as `queries`. Queries are split in one batch per thread, to limit the overhead
+    /// per path.
+    pub fn paths(&self, queries: &[(Vec2, Vec2)]) -> Vec<Option<Path>> {
+        if queries.is_empty() {
+            return vec![];
+        }
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let batch_size = queries.len().div_ceil(pool.thread_num().max(1));
+        pool.scope(|scope| {
+            for batch in queries.chunks(batch_size) {
+                scope.spawn(async move {
+                    batch
+                        .iter()
+                        .map(|(from, to)| self.mesh.path(*from, *to))
+                        .collect::<Vec<_>>()
+                });
+            }
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An L shaped corridor, two units wide, turning left around `(8, 2)`.
+    fn corridor() -> NavMesh {
+        NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(8.0, 10.0),
+                Vec2::new(8.0, 2.0),
+                Vec2::new(0.0, 2.0),
+            ],
+            vec![],
+        )
+    }
+
+    /// Check that the path from `from` doesn't go through the wall of the [`corridor`], sampling every 0.05.
+    fn assert_path_in_corridor(from: Vec2, path: &Path) {
+        for (a, b) in std::iter::once(&from).chain(&path.path).tuple_windows() {
+            for i in 0..=20 {
+                let point = a.lerp(*b, i as f32 / 20.0);
+                assert!(point.x >= 8.0 - 1e-3 || point.y <= 2.0 + 1e-3, "{point}");
+            }
+        }
+    }
+
+    #[test]
+    fn corner_offset_keeps_away_from_corners() {
+        let navmesh = corridor();
+        let from = Vec2::new(1.0, 1.0);
+        let options = PathOptions {
+            agent_radius: 0.5,
+            ..default()
+        };
+        let path = navmesh
+            .path_with_options(from, Vec2::new(9.0, 9.0), &options)
+            .unwrap();
+
+        assert_eq!(path.path.len(), 2);
+        assert!((path.path[0].distance(Vec2::new(8.0, 2.0)) - 0.5).abs() < 1e-3);
+        assert!(navmesh.is_in_mesh(path.path[0]));
+        assert_eq!(path.path[1], Vec2::new(9.0, 9.0));
+        assert_path_in_corridor(from, &path);
+    }
+
+    #[test]
+    fn smoothing_does_not_cut_through_walls() {
+        let navmesh = corridor();
+        let (from, to) = (Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0));
+        let options = PathOptions {
+            smoothing_iterations: 3,
+            ..default()
+        };
+        let path = navmesh.path_with_options(from, to, &options).unwrap();
+        // The corner is on the wall, it can't be cut
+        assert!(path.path.contains(&Vec2::new(8.0, 2.0)));
+        assert_path_in_corridor(from, &path);
+
+        let offset = navmesh
+            .path_with_options(
+                from,
+                to,
+                &PathOptions {
+                    agent_radius: 0.5,
+                    ..default()
+                },
+            )
+            .unwrap();
+        let options = PathOptions {
+            agent_radius: 0.5,
+            smoothing_iterations: 3,
+            ..default()
+        };
+        let smoothed = navmesh.path_with_options(from, to, &options).unwrap();
+        assert!(smoothed.path.len() > offset.path.len());
+        assert!(smoothed.length < offset.length);
+        assert_eq!(smoothed.path.last(), Some(&to));
+        assert_path_in_corridor(from, &smoothed);
+    }
+
+    #[test]
+    fn decimation_removes_close_waypoints() {
+        let navmesh = corridor();
+        let from = Vec2::new(1.0, 1.0);
+        let options = PathOptions {
+            agent_radius: 0.5,
+            smoothing_iterations: 3,
+            min_waypoint_distance: 1.0,
+            ..default()
+        };
+        let path = navmesh
+            .path_with_options(from, Vec2::new(9.0, 9.0), &options)
+            .unwrap();
+
+        let mut points = std::iter::once(from)
+            .chain(path.path.iter().copied())
+            .collect::<Vec<_>>();
+        assert_eq!(points.pop(), Some(Vec2::new(9.0, 9.0)));
+        for (a, b) in points.iter().tuple_windows() {
+            assert!(a.distance(*b) >= 1.0, "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn resampling_splits_long_segments() {
+        let navmesh = corridor();
+        let options = PathOptions {
+            resample_spacing: 1.0,
+            ..default()
+        };
+        let path = navmesh
+            .path_with_options(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0), &options)
+            .unwrap();
+
+        // 7.07 units to the corner, then 7.07 units to the destination
+        assert_eq!(path.path.len(), 16);
+        assert!(path.path.contains(&Vec2::new(8.0, 2.0)));
+        assert_eq!(path.path.last(), Some(&Vec2::new(9.0, 9.0)));
+        for (a, b) in path.path.iter().tuple_windows() {
+            assert!(a.distance(*b) <= 1.0 + 1e-3);
+        }
+        assert!((path.length - 2.0 * 50.0_f32.sqrt()).abs() < 1e-3);
+    }
+
+    fn assert_continuous_and_in_mesh(navmesh: &NavMesh, path: &TurningPath) {
+        for (a, b) in path.pieces.iter().tuple_windows() {
+            let end = a.point_at(a.length());
+            assert!(end.distance(b.point_at(0.0)) < 1e-3, "{a:?} {b:?}");
+        }
+        for point in path.sample(0.1) {
+            assert!(navmesh.is_in_mesh(point), "{point}");
+        }
+    }
+
+    #[test]
+    fn lines_are_tangent_to_arcs() {
+        let navmesh = corridor();
+        let path = navmesh
+            .path_with_turning_radius(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0), 1.0)
+            .unwrap();
+
+        assert_eq!(path.pieces.len(), 3);
+        let TurningPathPiece::Arc { center, radius, .. } = path.pieces[1] else {
+            panic!("{:?}", path.pieces[1]);
+        };
+        assert_eq!(radius, 1.0);
+        for line in [path.pieces[0], path.pieces[2]] {
+            let TurningPathPiece::Line { start, end } = line else {
+                panic!("{line:?}");
+            };
+            let on_arc = if start.distance(center) < end.distance(center) {
+                start
+            } else {
+                end
+            };
+            assert!((on_arc.distance(center) - radius).abs() < 1e-3);
+            assert!((end - start).normalize().dot(on_arc - center).abs() < 1e-3);
+        }
+        // The arc goes around the corner
+        assert!(center.distance(Vec2::new(8.0, 2.0)) - radius < 1e-3);
+        assert_continuous_and_in_mesh(&navmesh, &path);
+    }
+
+    #[test]
+    fn turns_in_both_directions_are_linked() {
+        // Two walls, the path turns left around the first one then right around the second one
+        let navmesh = NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(12.0, 0.0),
+                Vec2::new(12.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![
+                vec![
+                    Vec2::new(3.0, 0.0),
+                    Vec2::new(4.0, 0.0),
+                    Vec2::new(4.0, 7.0),
+                    Vec2::new(3.0, 7.0),
+                ],
+                vec![
+                    Vec2::new(7.0, 3.0),
+                    Vec2::new(8.0, 3.0),
+                    Vec2::new(8.0, 10.0),
+                    Vec2::new(7.0, 10.0),
+                ],
+            ],
+        );
+        let path = navmesh
+            .path_with_turning_radius(Vec2::new(1.0, 1.0), Vec2::new(11.0, 9.0), 0.5)
+            .unwrap();
+
+        let directions = path
+            .pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                TurningPathPiece::Arc { sweep, .. } if *sweep != 0.0 => Some(sweep.signum()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(directions.contains(&1.0) && directions.contains(&-1.0));
+        assert!(path.min_turning_radius() > 0.0);
+        assert_continuous_and_in_mesh(&navmesh, &path);
+    }
+
+    #[test]
+    fn radius_is_reduced_at_tight_corners() {
+        let navmesh = corridor();
+        let path = navmesh
+            .path_with_turning_radius(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0), 20.0)
+            .unwrap();
+
+        let radius = path.min_turning_radius();
+        assert!(radius > 0.0 && radius < 20.0, "{radius}");
+        assert_continuous_and_in_mesh(&navmesh, &path);
+    }
+
+    #[test]
+    fn infinite_turning_radius_is_clamped() {
+        let navmesh = corridor();
+        let path = navmesh
+            .path_with_turning_radius(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0), f32::INFINITY)
+            .unwrap();
+
+        assert!(path.min_turning_radius().is_finite());
+        assert_continuous_and_in_mesh(&navmesh, &path);
+    }
+
+    #[test]
+    fn min_turning_radius_of_paths() {
+        let navmesh = corridor();
+
+        let straight = navmesh
+            .path_with_turning_radius(Vec2::new(1.0, 1.0), Vec2::new(7.0, 1.5), 1.0)
+            .unwrap();
+        assert_eq!(straight.min_turning_radius(), f32::INFINITY);
+        assert_eq!(straight.pieces.len(), 1);
+
+        let turning = navmesh
+            .path_with_turning_radius(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0), 0.5)
+            .unwrap();
+        assert_eq!(turning.min_turning_radius(), 0.5);
+
+        // Without a turning radius, the path keeps its corner
+        let sharp = navmesh
+            .path_with_turning_radius(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0), 0.0)
+            .unwrap();
+        assert_eq!(sharp.min_turning_radius(), 0.0);
+        let length = navmesh
+            .path(Vec2::new(1.0, 1.0), Vec2::new(9.0, 9.0))
+            .unwrap()
+            .length;
+        assert!((sharp.length - length).abs() < 1e-3);
+        assert_continuous_and_in_mesh(&navmesh, &sharp);
+    }
+}
@@ -0,0 +1,622 @@
+//! Asset loaders reading polygons drawn in external tools, to use as navmesh boundaries and obstacles.
+//!
+//! - With the `svg` feature, [`SvgPolygonsLoader`] reads `.svg` files
+//! - With the `geojson` feature, [`GeoJsonPolygonsLoader`] reads `.geojson` files
+
+use std::{error::Error, fmt::Display};
+
+use bevy::{
+    asset::{io::Reader, Asset, AssetLoader, AsyncReadExt, LoadContext},
+    math::Vec2,
+    reflect::TypePath,
+};
+use polyanya::Triangulation;
+
+use crate::{geometry::signed_area, prelude::PolygonObstacle};
+
+/// Polygons loaded from a file.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct NavMeshPolygons {
+    /// Polygons, in the order they were found in the file. Closing points equal to the first point are removed.
+    pub polygons: Vec<Vec<Vec2>>,
+    /// Holes of each polygon, at the same index as in [`NavMeshPolygons::polygons`]. Empty for formats without holes.
+    pub holes: Vec<Vec<Vec<Vec2>>>,
+}
+
+impl NavMeshPolygons {
+    /// Index of the polygon with the largest area, usually the boundary of the level.
+    pub fn largest(&self) -> Option<usize> {
+        self.polygons
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| signed_area(a).abs().total_cmp(&signed_area(b).abs()))
+            .map(|(index, _)| index)
+    }
+
+    /// Holes of the polygon at `index`.
+    fn holes_of(&self, index: usize) -> &[Vec<Vec2>] {
+        self.holes.get(index).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Create a [`Triangulation`] using the largest polygon as outer edges, and all others as obstacles.
+    ///
+    /// Holes of the largest polygon are obstacles too. Holes of the other polygons are ignored, as obstacles can't have
+    /// holes in a triangulation.
+    ///
+    /// It can be used as the [`fixed`](crate::prelude::NavMeshSettings::fixed) triangulation of a navmesh, with the
+    /// [`largest`](NavMeshPolygons::largest) polygon as its
+    /// [`outer_edges`](crate::prelude::NavMeshSettings::outer_edges).
+    pub fn to_triangulation(&self) -> Option<Triangulation> {
+        let outer = self.largest()?;
+        let mut triangulation = Triangulation::from_outer_edges(&self.polygons[outer]);
+        triangulation.add_obstacles(
+            self.polygons
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| *index != outer)
+                .map(|(_, polygon)| polygon.clone())
+                .chain(self.holes_of(outer).iter().cloned()),
+        );
+        Some(triangulation)
+    }
+
+    /// Create a [`PolygonObstacle`] for each polygon, with its holes.
+    pub fn to_obstacles(&self) -> Vec<PolygonObstacle> {
+        self.polygons
+            .iter()
+            .enumerate()
+            .map(|(index, polygon)| PolygonObstacle {
+                polygon: polygon.clone(),
+                holes: self.holes_of(index).to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// Error that can happen while reading polygons from a file
+#[derive(Debug)]
+pub enum NavMeshPolygonsLoaderError {
+    /// Error when reading file
+    Io(std::io::Error),
+    /// The file is not valid
+    InvalidData(String),
+}
+
+impl Display for NavMeshPolygonsLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavMeshPolygonsLoaderError::Io(io_error) => write!(f, "IO error: {}", io_error),
+            NavMeshPolygonsLoaderError::InvalidData(reason) => {
+                write!(f, "Invalid data: {}", reason)
+            }
+        }
+    }
+}
+
+impl Error for NavMeshPolygonsLoaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NavMeshPolygonsLoaderError::Io(io_error) => Some(io_error),
+            NavMeshPolygonsLoaderError::InvalidData(_) => None,
+        }
+    }
+}
+
+/// Remove the closing point of a polygon if it's the same as the first one, and drop polygons that are too small.
+fn close_polygon(mut polygon: Vec<Vec2>) -> Option<Vec<Vec2>> {
+    if polygon.len() > 1 && polygon.first() == polygon.last() {
+        polygon.pop();
+    }
+    (polygon.len() >= 3).then_some(polygon)
+}
+
+async fn read_to_string(reader: &mut Reader<'_>) -> Result<String, NavMeshPolygonsLoaderError> {
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .await
+        .map_err(NavMeshPolygonsLoaderError::Io)?;
+    Ok(text)
+}
+
+/// Asset loader for polygons in an SVG file, with a `.svg` extension.
+///
+/// Reads `<path>`, `<polygon>`, `<polyline>` and `<rect>` elements. Paths support the `M`, `L`, `H`, `V` and `Z`
+/// commands, absolute and relative. Curves are not supported, they are replaced by a straight line to their end point
+/// with a warning. Transforms are ignored. The `y` axis is flipped, so that shapes are in the same orientation as in
+/// the drawing.
+#[cfg(feature = "svg")]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct SvgPolygonsLoader;
+
+#[cfg(feature = "svg")]
+impl AssetLoader for SvgPolygonsLoader {
+    type Asset = NavMeshPolygons;
+    type Settings = ();
+    type Error = NavMeshPolygonsLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let text = read_to_string(reader).await?;
+        Ok(NavMeshPolygons {
+            polygons: svg::polygons(&text)?,
+            holes: vec![],
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg"]
+    }
+}
+
+#[cfg(feature = "svg")]
+mod svg {
+    use bevy::{log::warn, math::Vec2};
+
+    use super::{close_polygon, NavMeshPolygonsLoaderError};
+
+    /// Iterate over the elements with the given tag name, returning their attributes.
+    fn elements<'a>(text: &'a str, tag: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        text.match_indices('<')
+            .filter_map(move |(index, _)| text[index + 1..].strip_prefix(tag))
+            .filter(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>'))
+            .map(|rest| &rest[..rest.find('>').unwrap_or(rest.len())])
+    }
+
+    fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+        let mut rest = attributes;
+        while let Some(index) = rest.find(name) {
+            let before = rest[..index].chars().last();
+            let after = rest[index + name.len()..].trim_start();
+            rest = &rest[index + name.len()..];
+            if before.map(|c| !c.is_whitespace()).unwrap_or(false) {
+                continue;
+            }
+            let Some(value) = after.strip_prefix('=') else {
+                continue;
+            };
+            let value = value.trim_start();
+            let quote = value.chars().next()?;
+            let value = &value[quote.len_utf8()..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+        None
+    }
+
+    /// Split a list of numbers. They are separated by whitespace or commas, or by nothing when the next number starts
+    /// with a sign or with a second decimal point, like in `10-20` or `1.5.5`.
+    fn tokens(text: &str) -> Vec<&str> {
+        let mut tokens = vec![];
+        let mut start = None;
+        let (mut point, mut exponent) = (false, false);
+        let mut previous = ' ';
+        for (index, c) in text.char_indices() {
+            let separator = c.is_whitespace() || c == ',';
+            let starts_number = match c {
+                '+' | '-' => !matches!(previous, 'e' | 'E'),
+                '.' => point || exponent,
+                _ => false,
+            };
+            if separator || starts_number {
+                if let Some(start) = start.take() {
+                    tokens.push(&text[start..index]);
+                }
+            }
+            if !separator {
+                if start.is_none() {
+                    start = Some(index);
+                    (point, exponent) = (false, false);
+                }
+                point |= c == '.';
+                exponent |= matches!(c, 'e' | 'E');
+            }
+            previous = c;
+        }
+        tokens.extend(start.map(|start| &text[start..]));
+        tokens
+    }
+
+    fn numbers(text: &str) -> Result<Vec<f32>, NavMeshPolygonsLoaderError> {
+        tokens(text)
+            .into_iter()
+            .map(|number| {
+                number.parse::<f32>().map_err(|_| {
+                    NavMeshPolygonsLoaderError::InvalidData(format!("invalid number {}", number))
+                })
+            })
+            .collect()
+    }
+
+    fn number_attribute(attributes: &str, name: &str) -> Result<f32, NavMeshPolygonsLoaderError> {
+        attribute(attributes, name)
+            .map(numbers)
+            .transpose()?
+            .and_then(|numbers| numbers.first().copied())
+            .ok_or_else(|| NavMeshPolygonsLoaderError::InvalidData(format!("missing {}", name)))
+    }
+
+    fn flip(point: Vec2) -> Vec2 {
+        Vec2::new(point.x, -point.y)
+    }
+
+    /// Split path data in commands and their arguments.
+    fn commands(data: &str) -> Result<Vec<(char, Vec<f32>)>, NavMeshPolygonsLoaderError> {
+        let mut commands = vec![];
+        let mut rest = data.trim_start();
+        while let Some(command) = rest.chars().next() {
+            if !command.is_ascii_alphabetic() {
+                return Err(NavMeshPolygonsLoaderError::InvalidData(format!(
+                    "expected a path command, found {}",
+                    command
+                )));
+            }
+            rest = &rest[1..];
+            let end = rest
+                .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+                .unwrap_or(rest.len());
+            commands.push((command, numbers(&rest[..end])?));
+            rest = rest[end..].trim_start();
+        }
+        Ok(commands)
+    }
+
+    fn path(data: &str) -> Result<Vec<Vec<Vec2>>, NavMeshPolygonsLoaderError> {
+        let mut polygons = vec![];
+        let mut current: Vec<Vec2> = vec![];
+        let mut position = Vec2::ZERO;
+        for (command, arguments) in commands(data)? {
+            let relative = command.is_ascii_lowercase();
+            let origin = |position: Vec2| if relative { position } else { Vec2::ZERO };
+            match command.to_ascii_uppercase() {
+                'M' | 'L' => {
+                    for (i, pair) in arguments.chunks_exact(2).enumerate() {
+                        position = origin(position) + Vec2::new(pair[0], pair[1]);
+                        if command.eq_ignore_ascii_case(&'M') && i == 0 {
+                            polygons.extend(close_polygon(std::mem::take(&mut current)));
+                        }
+                        current.push(position);
+                    }
+                }
+                'H' => {
+                    for x in arguments {
+                        position.x = origin(position).x + x;
+                        current.push(position);
+                    }
+                }
+                'V' => {
+                    for y in arguments {
+                        position.y = origin(position).y + y;
+                        current.push(position);
+                    }
+                }
+                'Z' => {
+                    if let Some(first) = current.first() {
+                        position = *first;
+                    }
+                    polygons.extend(close_polygon(std::mem::take(&mut current)));
+                }
+                curve @ ('C' | 'S' | 'Q' | 'T' | 'A') => {
+                    let count = match curve {
+                        'C' => 6,
+                        'S' | 'Q' => 4,
+                        'T' => 2,
+                        _ => 7,
+                    };
+                    warn!(
+                        "unsupported path command {}, replaced by a line to its end point",
+                        command
+                    );
+                    for curve in arguments.chunks_exact(count) {
+                        position = origin(position) + Vec2::new(curve[count - 2], curve[count - 1]);
+                        current.push(position);
+                    }
+                }
+                other => {
+                    return Err(NavMeshPolygonsLoaderError::InvalidData(format!(
+                        "unsupported path command {}",
+                        other
+                    )))
+                }
+            }
+        }
+        polygons.extend(close_polygon(current));
+        Ok(polygons
+            .into_iter()
+            .map(|polygon| polygon.into_iter().map(flip).collect())
+            .collect())
+    }
+
+    pub(super) fn polygons(text: &str) -> Result<Vec<Vec<Vec2>>, NavMeshPolygonsLoaderError> {
+        let mut polygons = vec![];
+        for attributes in elements(text, "path") {
+            if let Some(data) = attribute(attributes, "d") {
+                polygons.extend(path(data)?);
+            }
+        }
+        for attributes in elements(text, "polygon").chain(elements(text, "polyline")) {
+            if let Some(points) = attribute(attributes, "points") {
+                let polygon = numbers(points)?
+                    .chunks_exact(2)
+                    .map(|pair| flip(Vec2::new(pair[0], pair[1])))
+                    .collect();
+                polygons.extend(close_polygon(polygon));
+            }
+        }
+        for attributes in elements(text, "rect") {
+            let x = number_attribute(attributes, "x").unwrap_or(0.0);
+            let y = number_attribute(attributes, "y").unwrap_or(0.0);
+            let width = number_attribute(attributes, "width")?;
+            let height = number_attribute(attributes, "height")?;
+            polygons.push(
+                [
+                    (x, y),
+                    (x + width, y),
+                    (x + width, y + height),
+                    (x, y + height),
+                ]
+                .map(|(x, y)| flip(Vec2::new(x, y)))
+                .to_vec(),
+            );
+        }
+        Ok(polygons)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn reads_paths_polygons_and_rects() {
+            let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <path id="wall" d="M 0,0 L 10,0 l 0,10 H 0 Z m 2 2 h 1 v 1 z" />
+                <polygon points="20,20 30,20 30,30" />
+                <rect x="1" y="2" width="3" height="4" />
+            </svg>"#;
+            let polygons = polygons(svg).unwrap();
+            assert_eq!(polygons.len(), 4);
+            assert_eq!(
+                polygons[0],
+                vec![
+                    Vec2::new(0.0, 0.0),
+                    Vec2::new(10.0, 0.0),
+                    Vec2::new(10.0, -10.0),
+                    Vec2::new(0.0, -10.0)
+                ]
+            );
+            assert_eq!(
+                polygons[1],
+                vec![
+                    Vec2::new(2.0, -2.0),
+                    Vec2::new(3.0, -2.0),
+                    Vec2::new(3.0, -3.0)
+                ]
+            );
+            assert_eq!(polygons[2].len(), 3);
+            assert_eq!(polygons[3][2], Vec2::new(4.0, -6.0));
+        }
+
+        #[test]
+        fn reads_compact_numbers() {
+            assert_eq!(
+                numbers("10-20 1.5.5,-3e2-.5").unwrap(),
+                vec![10.0, -20.0, 1.5, 0.5, -300.0, -0.5]
+            );
+            assert_eq!(numbers("1e-2").unwrap(), vec![0.01]);
+            assert!(numbers("1x").is_err());
+        }
+
+        #[test]
+        fn reads_compact_paths_and_single_quotes() {
+            let svg = "<svg><path d='M10-20L30-20 30.5.5z'/></svg>";
+            let polygons = polygons(svg).unwrap();
+            assert_eq!(
+                polygons,
+                vec![vec![
+                    Vec2::new(10.0, 20.0),
+                    Vec2::new(30.0, 20.0),
+                    Vec2::new(30.5, -0.5)
+                ]]
+            );
+        }
+
+        #[test]
+        fn replaces_curves_by_lines() {
+            let svg = r#"<svg><path d="M0 0 L10 0 Q 15 5 10 10 c 0 0 -5 5 -10 0 Z" /></svg>"#;
+            let polygons = polygons(svg).unwrap();
+            assert_eq!(
+                polygons,
+                vec![vec![
+                    Vec2::new(0.0, 0.0),
+                    Vec2::new(10.0, 0.0),
+                    Vec2::new(10.0, -10.0),
+                    Vec2::new(0.0, -10.0)
+                ]]
+            );
+        }
+    }
+}
+
+/// Asset loader for polygons in a GeoJSON file, with a `.geojson` extension.
+///
+/// Reads the rings of `Polygon` and `MultiPolygon` geometries, in features, feature collections and geometry
+/// collections. Interior rings are read as the [holes](NavMeshPolygons::holes) of their polygon. Coordinates are used
+/// as is, the first one as `x` and the second one as `y`.
+#[cfg(feature = "geojson")]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct GeoJsonPolygonsLoader;
+
+#[cfg(feature = "geojson")]
+impl AssetLoader for GeoJsonPolygonsLoader {
+    type Asset = NavMeshPolygons;
+    type Settings = ();
+    type Error = NavMeshPolygonsLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let text = read_to_string(reader).await?;
+        geojson::polygons(&text)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["geojson"]
+    }
+}
+
+#[cfg(feature = "geojson")]
+mod geojson {
+    use bevy::math::Vec2;
+    use serde_json::Value;
+
+    use super::{close_polygon, NavMeshPolygons, NavMeshPolygonsLoaderError};
+
+    fn invalid(reason: &str) -> NavMeshPolygonsLoaderError {
+        NavMeshPolygonsLoaderError::InvalidData(reason.to_string())
+    }
+
+    fn ring(value: &Value) -> Result<Option<Vec<Vec2>>, NavMeshPolygonsLoaderError> {
+        let points = value
+            .as_array()
+            .ok_or_else(|| invalid("a ring is not an array"))?
+            .iter()
+            .map(|position| {
+                match position
+                    .as_array()
+                    .map(|coordinates| (coordinates.first(), coordinates.get(1)))
+                {
+                    Some((Some(x), Some(y))) => Ok(Vec2::new(
+                        x.as_f64().ok_or_else(|| invalid("invalid coordinate"))? as f32,
+                        y.as_f64().ok_or_else(|| invalid("invalid coordinate"))? as f32,
+                    )),
+                    _ => Err(invalid("invalid position")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(close_polygon(points))
+    }
+
+    /// Read the rings of a polygon: the first one is its outline, the others are its holes.
+    fn rings(
+        value: &Value,
+        polygons: &mut NavMeshPolygons,
+    ) -> Result<(), NavMeshPolygonsLoaderError> {
+        let mut rings = value
+            .as_array()
+            .ok_or_else(|| invalid("polygon coordinates are not an array"))?
+            .iter();
+        let Some(exterior) = rings.next().map(ring).transpose()?.flatten() else {
+            return Ok(());
+        };
+        let holes = rings
+            .map(ring)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+        polygons.polygons.push(exterior);
+        polygons.holes.push(holes);
+        Ok(())
+    }
+
+    fn visit(
+        value: &Value,
+        polygons: &mut NavMeshPolygons,
+    ) -> Result<(), NavMeshPolygonsLoaderError> {
+        match value.get("type").and_then(Value::as_str) {
+            Some("FeatureCollection") => {
+                for feature in value
+                    .get("features")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| invalid("missing features"))?
+                {
+                    visit(feature, polygons)?;
+                }
+            }
+            Some("Feature") => {
+                if let Some(geometry) = value.get("geometry").filter(|geometry| !geometry.is_null())
+                {
+                    visit(geometry, polygons)?;
+                }
+            }
+            Some("GeometryCollection") => {
+                for geometry in value
+                    .get("geometries")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| invalid("missing geometries"))?
+                {
+                    visit(geometry, polygons)?;
+                }
+            }
+            Some("Polygon") => {
+                rings(
+                    value
+                        .get("coordinates")
+                        .ok_or_else(|| invalid("missing coordinates"))?,
+                    polygons,
+                )?;
+            }
+            Some("MultiPolygon") => {
+                for polygon in value
+                    .get("coordinates")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| invalid("missing coordinates"))?
+                {
+                    rings(polygon, polygons)?;
+                }
+            }
+            Some(_) => (),
+            None => return Err(invalid("missing type")),
+        }
+        Ok(())
+    }
+
+    pub(super) fn polygons(text: &str) -> Result<NavMeshPolygons, NavMeshPolygonsLoaderError> {
+        let value: Value = serde_json::from_str(text)
+            .map_err(|error| NavMeshPolygonsLoaderError::InvalidData(error.to_string()))?;
+        let mut polygons = NavMeshPolygons::default();
+        visit(&value, &mut polygons)?;
+        Ok(polygons)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn reads_polygons_and_holes() {
+            let geojson = r#"{
+                "type": "FeatureCollection",
+                "features": [
+                    {
+                        "type": "Feature",
+                        "properties": {},
+                        "geometry": {
+                            "type": "Polygon",
+                            "coordinates": [
+                                [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]],
+                                [[4, 4], [6, 4], [6, 6], [4, 4]]
+                            ]
+                        }
+                    },
+                    { "type": "Feature", "properties": {}, "geometry": { "type": "Point", "coordinates": [1, 1] } }
+                ]
+            }"#;
+            let polygons = polygons(geojson).unwrap();
+            assert_eq!(polygons.polygons.len(), 1);
+            assert_eq!(polygons.polygons[0].len(), 4);
+            assert_eq!(polygons.holes[0].len(), 1);
+            assert_eq!(polygons.holes[0][0][1], Vec2::new(6.0, 4.0));
+
+            let obstacles = polygons.to_obstacles();
+            assert_eq!(obstacles.len(), 1);
+            assert_eq!(obstacles[0].holes, polygons.holes[0]);
+        }
+    }
+}
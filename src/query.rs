@@ -0,0 +1,575 @@
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashSet};
+
+use crate::{
+    geometry::closest_point_on_segment, spatial::GridIndex, NavMesh, Path, TransformedPath,
+};
+
+/// Maximum number of edges a movement can slide along in [`NavMesh::constrain_movement`].
+const MAX_SLIDES: usize = 3;
+
+/// Fraction of the movement kept between an agent and the edge it slides along.
+const SLIDE_MARGIN: f32 = 0.001;
+
+impl NavMesh {
+    /// Get the closest point to `point` in a navigable part of the mesh.
+    ///
+    /// Returns `point` if it's already in the mesh, or `None` if the mesh is empty.
+    pub fn closest_point(&self, point: Vec2) -> Option<Vec2> {
+        if self.is_in_mesh(point) {
+            return Some(point);
+        }
+        if let Some(closest) = self.indexed_closest_edge_point(point) {
+            return closest;
+        }
+        // The closest point outside of the mesh is on its boundary
+        self.boundary_edges()
+            .iter()
+            .map(|[start, end]| closest_point_on_segment(point, *start, *end))
+            .min_by(|a, b| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+    }
+
+    /// Get the closest point to `point` in a navigable part of the mesh.
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`]. The result is on the plane of the mesh.
+    pub fn transformed_closest_point(&self, point: Vec3) -> Option<Vec3> {
+        let inner = self.transform.transform_point(point).xy();
+        self.closest_point(inner).map(|closest| {
+            self.inverse_transform()
+                .transform_point(closest.extend(0.0))
+        })
+    }
+
+    /// Check if an agent of the given `radius` centered on `point` fits in a navigable part of the mesh.
+    ///
+    /// Uses the navmesh built for this radius if there is one, see [`NavMesh::for_agent_radius`]. Otherwise, the
+    /// point must be far enough from the edges of the mesh for the part of the radius not already covered by the
+    /// [`NavMesh::agent_radius`].
+    pub fn is_navigable(&self, point: Vec2, radius: f32) -> bool {
+        if let Some(navmesh) = self.for_agent_radius(radius) {
+            return navmesh.is_in_mesh(point);
+        }
+        self.distance_to_boundary(point)
+            .map(|distance| distance >= radius - self.agent_radius)
+            .unwrap_or(false)
+    }
+
+    /// Check if an agent of the given `radius` centered on `point` fits in a navigable part of the mesh.
+    ///
+    /// Inputs are transformed using the [`NavMesh::transform`]. See [`NavMesh::is_navigable`].
+    pub fn transformed_is_navigable(&self, point: Vec3, radius: f32) -> bool {
+        self.is_navigable(self.transform.transform_point(point).xy(), radius)
+    }
+
+    /// Get the closest point to `point` where an agent of the given `radius` fits, see [`NavMesh::is_navigable`].
+    ///
+    /// Returns `point` if it's already navigable, or `None` if there is no place large enough for the agent. This is
+    /// slower than [`NavMesh::closest_point`] when there is no navmesh built for this radius, as candidates are
+    /// searched along all the edges of the mesh.
+    pub fn closest_navigable_point(&self, point: Vec2, radius: f32) -> Option<Vec2> {
+        if let Some(navmesh) = self.for_agent_radius(radius) {
+            return navmesh.closest_point(point);
+        }
+        let extra = radius - self.agent_radius;
+        if matches!(self.distance_to_boundary(point), Some(distance) if distance >= extra) {
+            return Some(point);
+        }
+        // Move away from each edge by the missing radius. Polygons are counter clockwise, the inside is on the left
+        let edges = self.boundary_edges();
+        let mut candidates = edges
+            .iter()
+            .map(|[start, end]| {
+                let closest = closest_point_on_segment(point, *start, *end);
+                closest + (*end - *start).perp().normalize_or_zero() * extra * 1.001
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| {
+            a.distance_squared(point)
+                .total_cmp(&b.distance_squared(point))
+        });
+        // Edges closer than `extra` to a candidate have their bounds, grown by `extra`, around it
+        let index = GridIndex::new(
+            &edges
+                .iter()
+                .map(|[start, end]| (start.min(*end) - extra, start.max(*end) + extra))
+                .collect::<Vec<_>>(),
+        );
+        candidates.into_iter().find(|candidate| {
+            index.items_at(*candidate).iter().all(|edge| {
+                let [start, end] = edges[*edge as usize];
+                closest_point_on_segment(*candidate, start, end).distance(*candidate) >= extra
+            }) && self.is_in_mesh(*candidate)
+        })
+    }
+
+    /// Get the closest point to `point` where an agent of the given `radius` fits.
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`]. See [`NavMesh::closest_navigable_point`].
+    pub fn transformed_closest_navigable_point(&self, point: Vec3, radius: f32) -> Option<Vec3> {
+        let inner = self.transform.transform_point(point).xy();
+        self.closest_navigable_point(inner, radius).map(|closest| {
+            self.inverse_transform()
+                .transform_point(closest.extend(0.0))
+        })
+    }
+
+    /// Get the distance from `point` to the closest edge of the navigable part of the mesh, like a wall or an obstacle.
+    ///
+    /// Returns `None` if `point` is not in the mesh. Useful to steer agents away from walls or to score positions.
+    pub fn distance_to_boundary(&self, point: Vec2) -> Option<f32> {
+        if !self.is_in_mesh(point) {
+            return None;
+        }
+        if let Some(closest) = self.indexed_closest_edge_point(point) {
+            return closest.map(|closest| closest.distance(point));
+        }
+        Some(
+            self.boundary_edges()
+                .iter()
+                .map(|[start, end]| closest_point_on_segment(point, *start, *end).distance(point))
+                .fold(f32::INFINITY, f32::min),
+        )
+    }
+
+    /// Get the distance from `point` to the closest edge of the navigable part of the mesh.
+    ///
+    /// Inputs are transformed using the [`NavMesh::transform`], the distance is in the mesh local space. See
+    /// [`NavMesh::distance_to_boundary`].
+    pub fn transformed_distance_to_boundary(&self, point: Vec3) -> Option<f32> {
+        self.distance_to_boundary(self.transform.transform_point(point).xy())
+    }
+
+    /// Clamp a movement step of an agent of the given `radius` at `from` so that it stays on the mesh, sliding along
+    /// its edges. Returns the movement to apply.
+    ///
+    /// This makes simple kinematic movement safe without a physics engine. The navmesh built for the radius is used if
+    /// there is one, see [`NavMesh::for_agent_radius`], otherwise edges are kept at the missing distance from the
+    /// agent. Corners are approximated. If `from` is not in the mesh, the movement is not constrained.
+    pub fn constrain_movement(&self, from: Vec2, desired_delta: Vec2, radius: f32) -> Vec2 {
+        let navmesh = self.for_agent_radius(radius).unwrap_or(self);
+        if !navmesh.is_in_mesh(from) {
+            return desired_delta;
+        }
+        let clearance = (radius - navmesh.agent_radius).max(0.0);
+        let mut position = from;
+        let mut remaining = desired_delta;
+        for _ in 0..MAX_SLIDES {
+            if remaining.length_squared() <= f32::EPSILON {
+                break;
+            }
+            let hit = navmesh
+                .boundary_edges()
+                .iter()
+                .filter_map(|&[start, end]| {
+                    // Polygons are counter clockwise, the inside is on the left of the edges
+                    let edge = end - start;
+                    let normal = edge.perp().normalize_or_zero();
+                    let start = start + normal * clearance;
+                    let denominator = remaining.perp_dot(edge);
+                    if remaining.dot(normal) >= 0.0 || denominator.abs() <= f32::EPSILON {
+                        return None;
+                    }
+                    let t = (start - position).perp_dot(edge) / denominator;
+                    let u = (start - position).perp_dot(remaining) / denominator;
+                    ((-SLIDE_MARGIN..=1.0).contains(&t) && (0.0..=1.0).contains(&u))
+                        .then_some((t, normal))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            let Some((t, normal)) = hit else {
+                position += remaining;
+                break;
+            };
+            // Stop just before the edge, then slide along it with the rest of the movement
+            let t = (t - SLIDE_MARGIN).max(0.0);
+            position += remaining * t;
+            remaining *= 1.0 - t;
+            remaining -= normal * remaining.dot(normal);
+        }
+        position - from
+    }
+
+    /// Clamp a movement step of an agent of the given `radius` at `from` so that it stays on the mesh, sliding along
+    /// its edges. Returns the movement to apply.
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`], the movement is on the plane of the mesh.
+    /// See [`NavMesh::constrain_movement`].
+    pub fn transformed_constrain_movement(
+        &self,
+        from: Vec3,
+        desired_delta: Vec3,
+        radius: f32,
+    ) -> Vec3 {
+        let inner_from = self.transform.transform_point(from).xy();
+        let inner_to = self.transform.transform_point(from + desired_delta).xy();
+        let moved = inner_from + self.constrain_movement(inner_from, inner_to - inner_from, radius);
+        let inverse = self.inverse_transform();
+        inverse.transform_point(moved.extend(0.0)) - inverse.transform_point(inner_from.extend(0.0))
+    }
+
+    /// Cast a ray from `from` to `to`, returning the first point where it leaves the navigable part of the mesh.
+    ///
+    /// Returns `None` if the whole segment is in the mesh, or `from` if it's not in the mesh.
+    pub fn raycast(&self, from: Vec2, to: Vec2) -> Option<Vec2> {
+        if !self.is_in_mesh(from) {
+            return Some(from);
+        }
+        let direction = to - from;
+        self.boundary_edges()
+            .iter()
+            .filter_map(|&[a, b]| {
+                let edge = b - a;
+                let denominator = direction.perp_dot(edge);
+                // Polygons are counter clockwise, only keep edges crossed from the inside
+                if denominator <= f32::EPSILON {
+                    return None;
+                }
+                let t = (a - from).perp_dot(edge) / denominator;
+                let u = (a - from).perp_dot(direction) / denominator;
+                ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(t)
+            })
+            .min_by(f32::total_cmp)
+            .map(|t| from + direction * t)
+    }
+
+    /// Cast a ray from `from` to `to`, returning the first point where it leaves the navigable part of the mesh.
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`]. The result is on the plane of the mesh.
+    pub fn transformed_raycast(&self, from: Vec3, to: Vec3) -> Option<Vec3> {
+        let inner_from = self.transform.transform_point(from).xy();
+        let inner_to = self.transform.transform_point(to).xy();
+        self.raycast(inner_from, inner_to)
+            .map(|hit| self.inverse_transform().transform_point(hit.extend(0.0)))
+    }
+
+    /// Check if `to` can be reached from `from` by moving in a straight line, without leaving the mesh.
+    pub fn is_reachable_in_straight_line(&self, from: Vec2, to: Vec2) -> bool {
+        self.raycast(from, to).is_none()
+    }
+
+    /// Check if `to` can be reached from `from` by moving in a straight line, without leaving the mesh.
+    ///
+    /// Inputs are transformed using the [`NavMesh::transform`].
+    pub fn transformed_is_reachable_in_straight_line(&self, from: Vec3, to: Vec3) -> bool {
+        self.transformed_raycast(from, to).is_none()
+    }
+
+    /// Island of the polygons containing each point, if both points are in a polygon.
+    fn islands_of(&self, from: Vec2, to: Vec2) -> Option<(usize, usize)> {
+        let islands = self.islands();
+        Some((
+            islands[self.polygon_at(from)?],
+            islands[self.polygon_at(to)?],
+        ))
+    }
+
+    /// Length of the shortest path between two points.
+    ///
+    /// Returns `None` without searching for a path if the points are on parts of the mesh that are not connected.
+    pub fn distance(&self, from: Vec2, to: Vec2) -> Option<f32> {
+        if matches!(self.islands_of(from, to), Some((a, b)) if a != b) {
+            return None;
+        }
+        self.mesh.path(from, to).map(|path| path.length)
+    }
+
+    /// Check if there is a path between two points.
+    ///
+    /// Points are reachable if they are on connected parts of the mesh, no path is computed.
+    pub fn is_reachable(&self, from: Vec2, to: Vec2) -> bool {
+        match self.islands_of(from, to) {
+            Some((a, b)) => a == b,
+            None => self.mesh.path(from, to).is_some(),
+        }
+    }
+
+    /// Check if there is a path between two points.
+    ///
+    /// Inputs are transformed using the [`NavMesh::transform`].
+    pub fn transformed_is_reachable(&self, from: Vec3, to: Vec3) -> bool {
+        self.is_reachable(
+            self.transform.transform_point(from).xy(),
+            self.transform.transform_point(to).xy(),
+        )
+    }
+
+    /// Get a path between two points, if it's not longer than `max_length`.
+    ///
+    /// Returns `None` without searching for a path if the points are further apart than `max_length` in a straight
+    /// line, on parts of the mesh that are not connected, or if every route between them leaves the area a path of
+    /// `max_length` can cover. Only the polygons in that area are explored to check it. Otherwise the search is not
+    /// bounded: the path is computed in full, then dropped if it's too long.
+    pub fn path_within(&self, from: Vec2, to: Vec2, max_length: f32) -> Option<Path> {
+        if from.distance(to) > max_length
+            || matches!(self.islands_of(from, to), Some((a, b)) if a != b)
+            || !self.may_reach_within(from, to, max_length)
+        {
+            return None;
+        }
+        self.mesh
+            .path(from, to)
+            .filter(|path| path.length <= max_length)
+    }
+
+    /// Check if a path from `from` to `to` may be at most `max_length` long, without searching for it.
+    ///
+    /// A path through a portal is at least as long as the distance from `from` to the portal plus the distance from
+    /// the portal to `to`. Polygons are explored through the portals where this is at most `max_length`, which are in
+    /// an ellipse around the two points, and `to` may be reached if its polygon is found. Returns `true` when a point
+    /// is not in a polygon, to leave it to the path search.
+    fn may_reach_within(&self, from: Vec2, to: Vec2, max_length: f32) -> bool {
+        let (Some(start), Some(goal)) = (self.polygon_at(from), self.polygon_at(to)) else {
+            return true;
+        };
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(polygon) = stack.pop() {
+            let vertices = &self.mesh.polygons[polygon].vertices;
+            for (a, b) in vertices.iter().zip(vertices.iter().cycle().skip(1)) {
+                let (a, b) = (
+                    &self.mesh.vertices[*a as usize],
+                    &self.mesh.vertices[*b as usize],
+                );
+                let lower_bound = from.distance(closest_point_on_segment(from, a.coords, b.coords))
+                    + to.distance(closest_point_on_segment(to, a.coords, b.coords));
+                if lower_bound > max_length {
+                    continue;
+                }
+                // The polygon on the other side of the edge is the other one that has both its vertices
+                let Some(other) = a
+                    .polygons
+                    .iter()
+                    .filter(|other| **other >= 0 && **other as usize != polygon)
+                    .find(|other| b.polygons.contains(other))
+                    .map(|other| *other as usize)
+                else {
+                    continue;
+                };
+                if other == goal {
+                    return true;
+                }
+                if visited.insert(other) {
+                    stack.push(other);
+                }
+            }
+        }
+        start == goal
+    }
+
+    /// Get a path between two points, if it's not longer than `max_length`.
+    ///
+    /// Inputs and results are transformed using the [`NavMesh::transform`]. `max_length` is in the mesh local space.
+    pub fn transformed_path_within(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        max_length: f32,
+    ) -> Option<TransformedPath> {
+        let inner_from = self.transform.transform_point(from).xy();
+        let inner_to = self.transform.transform_point(to).xy();
+        self.path_within(inner_from, inner_to, max_length)
+            .map(|path| self.transform_path(path, from))
+    }
+
+    /// Check if `to` can be reached from `from` by walking at most `max_length`.
+    pub fn is_within_walking_distance(&self, from: Vec2, to: Vec2, max_length: f32) -> bool {
+        self.path_within(from, to, max_length).is_some()
+    }
+
+    /// Get a path to the nearest of `goals`, by path length. Returns the index of the goal reached and the path.
+    ///
+    /// Goals are tried from the closest in a straight line, and the search stops as soon as the remaining goals can't
+    /// be closer than the best path found. Goals on parts of the mesh not connected to `from` are skipped.
+    pub fn path_to_nearest(&self, from: Vec2, goals: &[Vec2]) -> Option<(usize, Path)> {
+        let mut candidates = goals
+            .iter()
+            .enumerate()
+            .filter(|(_, goal)| !matches!(self.islands_of(from, **goal), Some((a, b)) if a != b))
+            .map(|(index, goal)| (index, from.distance(*goal)))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let mut best: Option<(usize, Path)> = None;
+        for (index, straight) in candidates {
+            if matches!(&best, Some((_, path)) if path.length <= straight) {
+                break;
+            }
+            if let Some(path) = self.mesh.path(from, goals[index]) {
+                if best
+                    .as_ref()
+                    .map(|(_, best)| path.length < best.length)
+                    .unwrap_or(true)
+                {
+                    best = Some((index, path));
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polyanya::Trimesh;
+
+    use super::*;
+
+    /// A 10x10 square, from the origin.
+    fn square() -> NavMesh {
+        rectangle(10.0, 10.0)
+    }
+
+    fn rectangle(width: f32, height: f32) -> NavMesh {
+        NavMesh::from_polyanya_mesh(
+            Trimesh {
+                vertices: vec![
+                    Vec2::new(0.0, 0.0),
+                    Vec2::new(width, 0.0),
+                    Vec2::new(width, height),
+                    Vec2::new(0.0, height),
+                ],
+                triangles: vec![[0, 1, 2], [0, 2, 3]],
+            }
+            .try_into()
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn movement_slides_along_a_wall() {
+        let navmesh = square();
+        let from = Vec2::new(5.0, 9.0);
+
+        let moved = navmesh.constrain_movement(from, Vec2::new(2.0, 2.0), 0.0);
+        let to = from + moved;
+        assert!(to.y <= 10.0 && to.y > 9.99, "{to}");
+        assert!((to.x - 7.0).abs() < 0.01, "{to}");
+
+        // Kept at the agent radius from the wall
+        let moved = navmesh.constrain_movement(from, Vec2::new(2.0, 2.0), 0.5);
+        let to = from + moved;
+        assert!(to.y <= 9.5 && to.y > 9.49, "{to}");
+        assert!(to.x > 6.9, "{to}");
+    }
+
+    #[test]
+    fn movement_stops_in_a_corner() {
+        let navmesh = square();
+        let from = Vec2::new(9.0, 9.0);
+
+        let to = from + navmesh.constrain_movement(from, Vec2::new(3.0, 2.0), 0.0);
+        assert!(navmesh.is_in_mesh(to), "{to}");
+        assert!(to.distance(Vec2::new(10.0, 10.0)) < 0.01, "{to}");
+    }
+
+    #[test]
+    fn movement_from_outside_the_mesh_is_not_constrained() {
+        let navmesh = square();
+
+        assert_eq!(
+            navmesh.constrain_movement(Vec2::new(-5.0, 5.0), Vec2::new(10.0, 0.0), 0.0),
+            Vec2::new(10.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn movement_inside_the_mesh_is_unchanged() {
+        let navmesh = square();
+
+        assert_eq!(
+            navmesh.constrain_movement(Vec2::new(2.0, 2.0), Vec2::new(3.0, 1.0), 0.5),
+            Vec2::new(3.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn navigable_point_is_moved_away_from_walls() {
+        let navmesh = square();
+
+        assert_eq!(
+            navmesh.closest_navigable_point(Vec2::new(5.0, 5.0), 1.0),
+            Some(Vec2::new(5.0, 5.0))
+        );
+        let closest = navmesh
+            .closest_navigable_point(Vec2::new(5.0, 9.8), 1.0)
+            .unwrap();
+        assert!(closest.distance(Vec2::new(5.0, 9.0)) < 0.01, "{closest}");
+        assert!(navmesh.distance_to_boundary(closest).unwrap() >= 1.0);
+        // From outside the mesh
+        let closest = navmesh
+            .closest_navigable_point(Vec2::new(-3.0, 5.0), 1.0)
+            .unwrap();
+        assert!(closest.distance(Vec2::new(1.0, 5.0)) < 0.01, "{closest}");
+    }
+
+    #[test]
+    fn no_navigable_point_in_a_narrow_mesh() {
+        let navmesh = rectangle(10.0, 1.0);
+
+        assert_eq!(
+            navmesh.closest_navigable_point(Vec2::new(5.0, 0.5), 1.0),
+            None
+        );
+        assert!(navmesh
+            .closest_navigable_point(Vec2::new(5.0, 0.5), 0.4)
+            .is_some());
+    }
+
+    #[test]
+    fn path_within_checks_the_walked_length() {
+        // A wall in the middle of the square, from the bottom edge
+        let navmesh = NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![vec![
+                Vec2::new(4.5, 0.0),
+                Vec2::new(5.5, 0.0),
+                Vec2::new(5.5, 8.0),
+                Vec2::new(4.5, 8.0),
+            ]],
+        );
+        let from = Vec2::new(2.0, 1.0);
+        let to = Vec2::new(8.0, 1.0);
+
+        let path = navmesh.path_within(from, to, 20.0).unwrap();
+        assert!(path.length > 14.0);
+        // Close in a straight line, but too far around the wall
+        assert!(navmesh.path_within(from, to, 10.0).is_none());
+        assert!(!navmesh.is_within_walking_distance(from, to, 10.0));
+        // Too far in a straight line
+        assert!(navmesh.path_within(from, to, 5.0).is_none());
+        assert!(navmesh.is_within_walking_distance(from, Vec2::new(2.0, 5.0), 4.0));
+    }
+
+    #[test]
+    fn walking_distance_explores_only_the_polygons_in_range() {
+        let navmesh = NavMesh::from_edge_and_obstacles(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            vec![vec![
+                Vec2::new(4.5, 0.0),
+                Vec2::new(5.5, 0.0),
+                Vec2::new(5.5, 8.0),
+                Vec2::new(4.5, 8.0),
+            ]],
+        );
+        let from = Vec2::new(2.0, 1.0);
+        let to = Vec2::new(8.0, 1.0);
+
+        // Going around the wall is 15.9 long, the polygons above it are not explored with much less
+        assert!(!navmesh.may_reach_within(from, to, 10.0));
+        assert!(!navmesh.may_reach_within(from, to, 15.0));
+        assert!(navmesh.may_reach_within(from, to, 16.0));
+        assert!(navmesh.may_reach_within(from, Vec2::new(2.0, 5.0), 4.0));
+        assert!(navmesh.path_within(from, to, 16.0).is_some());
+    }
+}
@@ -0,0 +1,344 @@
+//! Grid index of the polygons and edges of a navmesh, to speed up point queries on large meshes.
+
+use bevy::math::{UVec2, Vec2};
+
+use crate::{
+    geometry::{closest_point_on_segment, contains_point_in},
+    NavMesh,
+};
+
+/// Maximum number of cells of a grid along each axis.
+const MAX_CELLS: u32 = 1024;
+
+/// Uniform grid over the bounds of items, listing in each cell the items whose bounds overlap it.
+#[derive(Debug)]
+pub(crate) struct GridIndex {
+    min: Vec2,
+    cell_size: f32,
+    size: UVec2,
+    cells: Vec<Vec<u32>>,
+}
+
+impl GridIndex {
+    /// Create an index over items, from their bounds as `(min, max)`.
+    pub(crate) fn new(bounds: &[(Vec2, Vec2)]) -> Self {
+        let (min, max) = bounds.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), (item_min, item_max)| (min.min(*item_min), max.max(*item_max)),
+        );
+        if bounds.is_empty() {
+            return Self {
+                min: Vec2::ZERO,
+                cell_size: 1.0,
+                size: UVec2::ONE,
+                cells: vec![vec![]],
+            };
+        }
+        // About one cell per item
+        let extent = (max - min).max(Vec2::splat(f32::EPSILON));
+        let cell_size = (extent.x * extent.y / bounds.len() as f32)
+            .sqrt()
+            .max(extent.max_element() / MAX_CELLS as f32)
+            .max(f32::EPSILON);
+        let size = (extent / cell_size)
+            .ceil()
+            .as_uvec2()
+            .clamp(UVec2::ONE, UVec2::splat(MAX_CELLS));
+        let mut index = Self {
+            min,
+            cell_size,
+            size,
+            cells: vec![vec![]; (size.x * size.y) as usize],
+        };
+        for (item, (item_min, item_max)) in bounds.iter().enumerate() {
+            let from = index.cell_of(*item_min);
+            let to = index.cell_of(*item_max);
+            for y in from.y..=to.y {
+                for x in from.x..=to.x {
+                    let cell = index.cell_index(UVec2::new(x, y));
+                    index.cells[cell].push(item as u32);
+                }
+            }
+        }
+        index
+    }
+
+    /// Cell containing `point`, clamped to the grid.
+    fn cell_of(&self, point: Vec2) -> UVec2 {
+        ((point - self.min) / self.cell_size)
+            .floor()
+            .max(Vec2::ZERO)
+            .as_uvec2()
+            .min(self.size - UVec2::ONE)
+    }
+
+    fn cell_index(&self, cell: UVec2) -> usize {
+        (cell.y * self.size.x + cell.x) as usize
+    }
+
+    /// Items whose bounds may contain `point`.
+    pub(crate) fn items_at(&self, point: Vec2) -> &[u32] {
+        let cell = ((point - self.min) / self.cell_size).floor();
+        if cell.cmplt(Vec2::ZERO).any() || cell.cmpge(self.size.as_vec2()).any() {
+            return &[];
+        }
+        &self.cells[self.cell_index(cell.as_uvec2())]
+    }
+
+    /// Pairs of items whose `bounds` overlap, with the smaller item first, sorted.
+    ///
+    /// `bounds` must be the ones the index was created with.
+    pub(crate) fn overlapping_pairs(&self, bounds: &[(Vec2, Vec2)]) -> Vec<(u32, u32)> {
+        let mut pairs = vec![];
+        for (cell, items) in self.cells.iter().enumerate() {
+            for (position, a) in items.iter().enumerate() {
+                for b in &items[position + 1..] {
+                    let (a_min, a_max) = bounds[*a as usize];
+                    let (b_min, b_max) = bounds[*b as usize];
+                    // Each pair is in all the cells its overlap covers, only keep it in the first one
+                    if a_min.cmple(b_max).all()
+                        && b_min.cmple(a_max).all()
+                        && self.cell_index(self.cell_of(a_min.max(b_min))) == cell
+                    {
+                        pairs.push((*a, *b));
+                    }
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+
+    /// Item with the smallest `distance` to `point`, and that distance.
+    ///
+    /// `distance` must be the distance from `point` to the closest point of the item, that is inside its bounds. Cells
+    /// are visited in rings around `point`, until no item in the next ring can be closer than the best one found.
+    pub(crate) fn nearest(&self, point: Vec2, distance: impl Fn(u32) -> f32) -> Option<(u32, f32)> {
+        let center = self.cell_of(point).as_ivec2();
+        let mut best: Option<(u32, f32)> = None;
+        for ring in 0..self.size.max_element() as i32 {
+            if matches!(best, Some((_, best)) if best <= (ring - 1).max(0) as f32 * self.cell_size)
+            {
+                break;
+            }
+            for y in center.y - ring..=center.y + ring {
+                for x in center.x - ring..=center.x + ring {
+                    let on_ring = (y - center.y).abs() == ring || (x - center.x).abs() == ring;
+                    if !on_ring
+                        || x < 0
+                        || y < 0
+                        || x >= self.size.x as i32
+                        || y >= self.size.y as i32
+                    {
+                        continue;
+                    }
+                    for item in &self.cells[self.cell_index(UVec2::new(x as u32, y as u32))] {
+                        let item_distance = distance(*item);
+                        if best.map(|(_, best)| item_distance < best).unwrap_or(true) {
+                            best = Some((*item, item_distance));
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Grid index of the boundary edges of a navmesh, see [`NavMesh::build_spatial_index`].
+#[derive(Debug)]
+pub(crate) struct SpatialIndex {
+    pub(crate) edges: GridIndex,
+}
+
+impl NavMesh {
+    /// Build a grid index of the polygons and edges of the mesh, to speed up point queries on large meshes.
+    ///
+    /// Once built, it's used to find the polygon containing a point in [`NavMesh::is_in_mesh`] and the region
+    /// queries, and the closest edge in [`NavMesh::closest_point`] and [`NavMesh::distance_to_boundary`]. This is done
+    /// during the build when [`NavMeshSettings::spatial_index`](crate::prelude::NavMeshSettings::spatial_index) is
+    /// set. Variants have their own index.
+    pub fn build_spatial_index(&self) {
+        self.polygon_index();
+        self.spatial_index.get_or_init(|| {
+            let edges = self
+                .boundary_edges()
+                .iter()
+                .map(|[start, end]| (start.min(*end), start.max(*end)))
+                .collect::<Vec<_>>();
+            SpatialIndex {
+                edges: GridIndex::new(&edges),
+            }
+        });
+        for variant in &self.variants {
+            variant.build_spatial_index();
+        }
+    }
+
+    /// Grid index of the bounds of the polygons of the mesh, built on first use.
+    ///
+    /// Polyanya has its own lookup structures, but they are private to the mesh.
+    pub(crate) fn polygon_index(&self) -> &GridIndex {
+        self.polygon_index.get_or_init(|| {
+            let polygons = self
+                .mesh
+                .polygons
+                .iter()
+                .map(|polygon| {
+                    polygon.vertices.iter().fold(
+                        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                        |(min, max), index| {
+                            let point = self.mesh.vertices[*index as usize].coords;
+                            (min.min(point), max.max(point))
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
+            GridIndex::new(&polygons)
+        })
+    }
+
+    /// Index of the polygon containing `point`, among the candidates of the [polygon index](Self::polygon_index).
+    pub(crate) fn indexed_polygon(&self, point: Vec2) -> Option<usize> {
+        self.polygon_index()
+            .items_at(point)
+            .iter()
+            .map(|polygon| *polygon as usize)
+            .find(|polygon| {
+                contains_point_in(
+                    self.mesh.polygons[*polygon]
+                        .vertices
+                        .iter()
+                        .map(|index| self.mesh.vertices[*index as usize].coords),
+                    point,
+                )
+            })
+    }
+
+    /// Index of the polygon containing `point`, using the spatial index. `None` if there is no index.
+    pub(crate) fn indexed_polygon_at(&self, point: Vec2) -> Option<Option<usize>> {
+        self.spatial_index.get()?;
+        Some(self.indexed_polygon(point))
+    }
+
+    /// Closest point to `point` on the edges of the mesh, using the spatial index. `None` if there is no index.
+    pub(crate) fn indexed_closest_edge_point(&self, point: Vec2) -> Option<Option<Vec2>> {
+        let index = self.spatial_index.get()?;
+        let edges = self.boundary_edges();
+        let closest = |edge: u32| {
+            let [start, end] = edges[edge as usize];
+            closest_point_on_segment(point, start, end)
+        };
+        Some(
+            index
+                .edges
+                .nearest(point, |edge| closest(edge).distance(point))
+                .map(|(edge, _)| closest(edge)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_matches_linear_search() {
+        let segments = (0..200)
+            .map(|i| {
+                let start = Vec2::new((i * 37 % 101) as f32, (i * 53 % 89) as f32);
+                [
+                    start,
+                    start + Vec2::new((i % 7) as f32, (i % 5) as f32 - 2.0),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let bounds = segments
+            .iter()
+            .map(|[start, end]| (start.min(*end), start.max(*end)))
+            .collect::<Vec<_>>();
+        let index = GridIndex::new(&bounds);
+        let distance = |point: Vec2, segment: u32| {
+            let [start, end] = segments[segment as usize];
+            closest_point_on_segment(point, start, end).distance(point)
+        };
+
+        for point in [
+            Vec2::new(50.0, 40.0),
+            Vec2::new(-30.0, 10.0),
+            Vec2::new(120.0, 200.0),
+            Vec2::new(3.3, 7.7),
+        ] {
+            let expected = (0..segments.len() as u32)
+                .map(|segment| distance(point, segment))
+                .fold(f32::INFINITY, f32::min);
+            let (_, found) = index
+                .nearest(point, |segment| distance(point, segment))
+                .unwrap();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn overlapping_pairs_match_brute_force() {
+        let bounds = (0..150)
+            .map(|i| {
+                let min = Vec2::new((i * 37 % 101) as f32, (i * 53 % 89) as f32);
+                (min, min + Vec2::new((i % 9) as f32, (i % 4) as f32))
+            })
+            .collect::<Vec<_>>();
+        let mut expected = vec![];
+        for a in 0..bounds.len() {
+            for b in a + 1..bounds.len() {
+                if bounds[a].0.cmple(bounds[b].1).all() && bounds[b].0.cmple(bounds[a].1).all() {
+                    expected.push((a as u32, b as u32));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+
+        assert_eq!(GridIndex::new(&bounds).overlapping_pairs(&bounds), expected);
+    }
+
+    #[test]
+    fn polygon_at_matches_linear_search() {
+        let size = 12;
+        let vertices = (0..=size)
+            .flat_map(|y| (0..=size).map(move |x| Vec2::new(x as f32, y as f32 * 0.5)))
+            .collect();
+        let triangles = (0..size)
+            .flat_map(|y| {
+                (0..size).flat_map(move |x| {
+                    let corner = y * (size + 1) + x;
+                    [
+                        [corner, corner + 1, corner + size + 2],
+                        [corner, corner + size + 2, corner + size + 1],
+                    ]
+                })
+            })
+            .collect();
+        let navmesh = NavMesh::from_polyanya_mesh(
+            polyanya::Trimesh {
+                vertices,
+                triangles,
+            }
+            .try_into()
+            .unwrap(),
+        );
+
+        for i in 0..100 {
+            let point = Vec2::new((i * 37 % 131) as f32 / 10.0, (i * 53 % 67) as f32 / 10.0);
+            let expected = navmesh.get().polygons.iter().position(|polygon| {
+                contains_point_in(
+                    polygon
+                        .vertices
+                        .iter()
+                        .map(|index| navmesh.get().vertices[*index as usize].coords),
+                    point,
+                )
+            });
+            assert_eq!(navmesh.polygon_at(point), expected, "{point}");
+        }
+        assert_eq!(navmesh.polygon_at(Vec2::new(-1.0, 1.0)), None);
+    }
+}
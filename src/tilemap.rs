@@ -0,0 +1,166 @@
+//! Build navmeshes from [`bevy_ecs_tilemap`] layers.
+//!
+//! Points are in the local space of the tilemap: use the tilemap [`Transform`](bevy::prelude::Transform) as the
+//! transform of the navmesh.
+
+use bevy::{ecs::entity::Entity, math::Vec2};
+use bevy_ecs_tilemap::prelude::{TilePos, TileStorage, TilemapGridSize, TilemapSize};
+use polyanya::Triangulation;
+
+use crate::geometry::grid_rectangles;
+
+/// Build the fixed [`Triangulation`] of a navmesh from a tilemap layer.
+///
+/// `walkable` is called for every tile position of the layer, with the tile entity if there is one. Tiles that are not
+/// walkable become obstacles. Neighbouring blocked tiles are merged into rectangles to keep the number of obstacles
+/// low.
+///
+/// Only square tilemaps are supported.
+pub fn triangulation_from_tilemap(
+    storage: &TileStorage,
+    grid_size: &TilemapGridSize,
+    walkable: impl Fn(TilePos, Option<Entity>) -> bool,
+) -> Triangulation {
+    triangulation_from_tilemap_chunk(
+        storage,
+        grid_size,
+        TilePos::new(0, 0),
+        storage.size,
+        walkable,
+    )
+}
+
+/// Build the fixed [`Triangulation`] of a navmesh from a chunk of a tilemap layer.
+///
+/// The chunk starts at `origin` and spans `size` tiles, clamped to the size of the layer. The outer edges of the
+/// triangulation are the bounds of the chunk, given by [`tilemap_chunk_outer_edges`]. See
+/// [`triangulation_from_tilemap`].
+pub fn triangulation_from_tilemap_chunk(
+    storage: &TileStorage,
+    grid_size: &TilemapGridSize,
+    origin: TilePos,
+    size: TilemapSize,
+    walkable: impl Fn(TilePos, Option<Entity>) -> bool,
+) -> Triangulation {
+    let (width, height) = chunk_size(storage, origin, size);
+    let corner = tile_corner(grid_size, origin);
+
+    let blocked = |x: usize, y: usize| {
+        let position = TilePos::new(origin.x + x as u32, origin.y + y as u32);
+        !walkable(position, storage.get(&position))
+    };
+
+    let mut triangulation = Triangulation::from_outer_edges(&tilemap_chunk_outer_edges(
+        storage, grid_size, origin, size,
+    ));
+
+    let obstacles = grid_rectangles(width, height, blocked)
+        .into_iter()
+        .map(|(x, y, columns, rows)| {
+            vec![
+                corner(x, y),
+                corner(x + columns, y),
+                corner(x + columns, y + rows),
+                corner(x, y + rows),
+            ]
+        })
+        .collect::<Vec<_>>();
+    triangulation.add_obstacles(obstacles);
+    triangulation
+}
+
+/// Outer edges of the triangulation of a chunk of a tilemap layer, to set as the
+/// [`outer_edges`](crate::prelude::NavMeshSettings::outer_edges) of the navmesh. Use the size of the layer and an
+/// origin of `(0, 0)` for a whole layer.
+pub fn tilemap_chunk_outer_edges(
+    storage: &TileStorage,
+    grid_size: &TilemapGridSize,
+    origin: TilePos,
+    size: TilemapSize,
+) -> Vec<Vec2> {
+    let (width, height) = chunk_size(storage, origin, size);
+    let corner = tile_corner(grid_size, origin);
+    vec![
+        corner(0, 0),
+        corner(width, 0),
+        corner(width, height),
+        corner(0, height),
+    ]
+}
+
+/// Size of a chunk in tiles, clamped to the size of the layer.
+fn chunk_size(storage: &TileStorage, origin: TilePos, size: TilemapSize) -> (usize, usize) {
+    (
+        size.x.min(storage.size.x.saturating_sub(origin.x)) as usize,
+        size.y.min(storage.size.y.saturating_sub(origin.y)) as usize,
+    )
+}
+
+/// Corner of the tile at `(x, y)` from `origin`, on the side of the origin.
+fn tile_corner(grid_size: &TilemapGridSize, origin: TilePos) -> impl Fn(usize, usize) -> Vec2 {
+    let cell = Vec2::new(grid_size.x, grid_size.y);
+    // Tiles are centered on their position
+    move |x: usize, y: usize| {
+        Vec2::new(
+            (origin.x as usize + x) as f32,
+            (origin.y as usize + y) as f32,
+        ) * cell
+            - cell / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NavMesh;
+
+    fn navmesh(triangulation: Triangulation) -> NavMesh {
+        let mut mesh = triangulation.as_navmesh();
+        mesh.bake();
+        NavMesh::from_polyanya_mesh(mesh)
+    }
+
+    /// Tiles `(1, 1)` and `(2, 1)` of a 4x3 layer are blocked.
+    fn walkable(position: TilePos, _: Option<Entity>) -> bool {
+        !(position.y == 1 && (1..=2).contains(&position.x))
+    }
+
+    #[test]
+    fn blocked_tiles_are_obstacles() {
+        let storage = TileStorage::empty(TilemapSize { x: 4, y: 3 });
+        let grid_size = TilemapGridSize { x: 2.0, y: 2.0 };
+        let navmesh = navmesh(triangulation_from_tilemap(&storage, &grid_size, walkable));
+
+        assert!(navmesh.is_in_mesh(Vec2::new(0.0, 0.0)));
+        assert!(navmesh.is_in_mesh(Vec2::new(6.0, 2.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(2.0, 2.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(4.0, 2.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(8.0, 2.0)));
+    }
+
+    #[test]
+    fn chunk_is_clamped_to_the_layer() {
+        let storage = TileStorage::empty(TilemapSize { x: 4, y: 3 });
+        let grid_size = TilemapGridSize { x: 2.0, y: 2.0 };
+        let origin = TilePos::new(2, 1);
+        let size = TilemapSize { x: 5, y: 5 };
+
+        assert_eq!(
+            tilemap_chunk_outer_edges(&storage, &grid_size, origin, size),
+            vec![
+                Vec2::new(3.0, 1.0),
+                Vec2::new(7.0, 1.0),
+                Vec2::new(7.0, 5.0),
+                Vec2::new(3.0, 5.0),
+            ]
+        );
+        let navmesh = navmesh(triangulation_from_tilemap_chunk(
+            &storage, &grid_size, origin, size, walkable,
+        ));
+        assert!(navmesh.is_in_mesh(Vec2::new(6.0, 4.0)));
+        assert!(navmesh.is_in_mesh(Vec2::new(6.0, 2.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(4.0, 2.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(2.0, 2.0)));
+        assert!(!navmesh.is_in_mesh(Vec2::new(8.0, 4.0)));
+    }
+}
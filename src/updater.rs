@@ -1,15 +1,39 @@
 use std::{
+    error::Error,
+    fmt::Display,
+    hash::{DefaultHasher, Hash, Hasher},
     marker::PhantomData,
-    sync::{Arc, RwLock},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    task::{Poll, Waker},
+    time::Duration,
 };
 
 #[cfg(feature = "tracing")]
 use tracing::instrument;
 
-use bevy::{ecs::entity::EntityHashMap, prelude::*, tasks::AsyncComputeTaskPool, utils::HashMap};
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    ecs::entity::{EntityHashMap, EntityHashSet},
+    math::Vec3Swizzles,
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, ComputeTaskPool, TaskPool},
+    utils::{HashMap, HashSet, Instant},
+};
 use polyanya::Triangulation;
 
-use crate::{obstacles::ObstacleSource, NavMesh};
+use crate::{
+    builder::has_area,
+    geometry::{quantize_mesh, quantize_polygon},
+    obstacles::{
+        offset::offset_polygon, CachableObstacle, ObstacleLayers, ObstacleRadiusOverride,
+        ObstacleSource, TransformProvider,
+    },
+    NavMesh, NavMeshSettingsError, OffMeshLink,
+};
 
 /// Bundle for preparing an auto updated navmesh. To use with plugin [`NavmeshUpdaterPlugin`].
 #[derive(Bundle, Debug)]
@@ -18,7 +42,8 @@ pub struct NavMeshBundle {
     pub settings: NavMeshSettings,
     /// Status of the last navmesh update.
     pub status: NavMeshStatus,
-    /// Handle to the navmesh.
+    /// Handle to the navmesh. If left to its default value, a unique handle is reserved when the entity is spawned,
+    /// and kept across rebuilds.
     pub handle: Handle<NavMesh>,
     /// Transform of the navmesh. USed to transform point in 3d to 2d (by ignoring the `z` axis).
     pub transform: Transform,
@@ -38,6 +63,56 @@ impl Default for NavMeshBundle {
     }
 }
 
+impl NavMeshBundle {
+    /// Create a bundle whose navmesh is available under a handle known in advance, built from `id` with
+    /// [`Handle::weak_from_u128`].
+    ///
+    /// Other systems can get the navmesh with the same id without querying the navmesh entity.
+    pub fn with_id(id: u128) -> Self {
+        Self {
+            handle: Handle::weak_from_u128(id),
+            ..default()
+        }
+    }
+}
+
+/// Base of the ids allocated by [`NavMeshCommandsExt::spawn_navmesh`], to keep them apart from ids chosen manually.
+const SPAWNED_NAVMESH_ID_BASE: u128 = 0x6e61_766d_6573_6800_0000_0000_0000_0000;
+
+static NEXT_SPAWNED_NAVMESH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Extension trait to spawn navmeshes from [`Commands`].
+pub trait NavMeshCommandsExt {
+    /// Spawn an entity with all the components of a [`NavMeshBundle`], and return it with the handle its navmesh
+    /// will be available at.
+    ///
+    /// The handle is built from a unique id, so several navmeshes can be spawned without choosing ids with
+    /// [`NavMeshBundle::with_id`].
+    fn spawn_navmesh(
+        &mut self,
+        settings: NavMeshSettings,
+        update_mode: NavMeshUpdateMode,
+    ) -> (Entity, Handle<NavMesh>);
+}
+
+impl NavMeshCommandsExt for Commands<'_, '_> {
+    fn spawn_navmesh(
+        &mut self,
+        settings: NavMeshSettings,
+        update_mode: NavMeshUpdateMode,
+    ) -> (Entity, Handle<NavMesh>) {
+        let id = SPAWNED_NAVMESH_ID_BASE
+            | NEXT_SPAWNED_NAVMESH_ID.fetch_add(1, Ordering::Relaxed) as u128;
+        let bundle = NavMeshBundle {
+            settings,
+            update_mode,
+            ..NavMeshBundle::with_id(id)
+        };
+        let handle = bundle.handle.clone();
+        (self.spawn(bundle).id(), handle)
+    }
+}
+
 /// Settings for nav mesh generation.
 #[derive(Component, Clone, Debug)]
 pub struct NavMeshSettings {
@@ -49,8 +124,73 @@ pub struct NavMeshSettings {
     pub default_delta: f32,
     /// Fixed edges and obstacles of the mesh
     pub fixed: Triangulation,
+    /// Outer edges of the [`fixed`](NavMeshSettings::fixed) triangulation, set along with it.
+    ///
+    /// [`Triangulation`] doesn't expose its outer edges, they are kept here to be checked by
+    /// [`NavMeshSettings::validate`] before triangulating.
+    pub outer_edges: Vec<Vec2>,
     /// Duration in seconds after which to cancel a navmesh build
     pub build_timeout: Option<f32>,
+    /// Radius of the agents using the navmesh. Obstacles are inflated by this radius.
+    ///
+    /// The outer edges of the [`fixed`](NavMeshSettings::fixed) triangulation are not moved, keep a margin of this
+    /// radius when defining them.
+    pub agent_radius: f32,
+    /// Additional agent radius to build variants of the navmesh for, from the same obstacles.
+    ///
+    /// Use [`NavMesh::for_agent_radius`] to get the variant to use for an agent.
+    pub agent_radius_variants: Vec<f32>,
+    /// Size of the grid obstacles and mesh vertices are snapped to during the build.
+    ///
+    /// With quantization, the same obstacles build the same navmesh on every platform, as small floating point
+    /// differences are removed before triangulating and after simplifying. It should be smaller than the smallest
+    /// detail of the obstacles: obstacles that collapse on the grid are ignored, and mesh vertices that would flip
+    /// their polygons are not snapped. The [`fixed`](NavMeshSettings::fixed) triangulation is not snapped, keep its points
+    /// on the grid.
+    pub quantization: Option<f32>,
+    /// Up direction of the navmesh, in world space. Obstacles are projected along this direction.
+    ///
+    /// By default, the rotation of the navmesh [`Transform`] maps the up direction to the `z` axis. When set, this
+    /// direction takes precedence over the rotation of the [`Transform`], its translation and scale are still used.
+    #[cfg(feature = "3d")]
+    pub up_override: Option<Dir3>,
+    /// Compute the data cached for queries during the build, before publishing the navmesh. See [`NavMesh::precompute`].
+    ///
+    /// This avoids a cost spike on the first queries after a rebuild, but makes builds longer.
+    pub precompute: bool,
+    /// Off-mesh links added to the navmesh, in the mesh local space. See [`NavMesh::path_with_links`], whose cost grows
+    /// with the square of the number of links.
+    pub links: Vec<OffMeshLink>,
+    /// Mask of the [`ObstacleLayers`] used by this navmesh. Obstacles on other layers are ignored.
+    ///
+    /// Useful when a scene has several navmeshes, like one per room or per floor. Defaults to all layers.
+    pub obstacle_layers: u32,
+    /// Range of heights of the obstacles used by this navmesh, along its up axis and relative to its plane. Obstacles
+    /// whose position is outside this range are ignored.
+    ///
+    /// Useful for navmeshes on several floors, to only use the obstacles of the floor of each navmesh.
+    pub obstacle_height_range: Option<Range<f32>>,
+    /// Height above the navmesh plane at which 3D obstacles are cut, along its up axis. The cross-section of the
+    /// obstacles at this height is used instead of their whole outline, so that tall or tilted obstacles don't carve
+    /// holes larger than their footprint.
+    ///
+    /// `None` to project whole obstacles. Obstacle sources that can't be cut are always projected whole, see
+    /// [`ObstacleSource::get_sliced_polygons`].
+    pub slice_height: Option<f32>,
+    /// Height of the agents using the navmesh. Only the parts of 3D obstacles between the
+    /// [`slice_height`](NavMeshSettings::slice_height) (or the navmesh plane) and this height above it are used, so
+    /// that agents can walk under obstacles with enough clearance, like bridges or archways.
+    ///
+    /// `None` to use whole obstacles, or only their cross-section when `slice_height` is set.
+    pub agent_height: Option<f32>,
+    /// Make builds deterministic: the same obstacles and settings always give the same navmesh, whatever the order in
+    /// which obstacles are found.
+    ///
+    /// Obstacle polygons are sorted before the triangulation, and the build is not split in parallel tasks.
+    pub deterministic: bool,
+    /// Build a grid index of the navmesh, to speed up point queries on large meshes. See
+    /// [`NavMesh::build_spatial_index`].
+    pub spatial_index: bool,
 }
 
 impl Default for NavMeshSettings {
@@ -60,7 +200,74 @@ impl Default for NavMeshSettings {
             merge_steps: 2,
             default_delta: 0.01,
             fixed: Triangulation::from_outer_edges(&[]),
+            outer_edges: vec![],
             build_timeout: None,
+            agent_radius: 0.0,
+            agent_radius_variants: vec![],
+            quantization: None,
+            precompute: false,
+            #[cfg(feature = "3d")]
+            up_override: None,
+            links: vec![],
+            obstacle_layers: u32::MAX,
+            obstacle_height_range: None,
+            slice_height: None,
+            agent_height: None,
+            deterministic: false,
+            spatial_index: false,
+        }
+    }
+}
+
+impl NavMeshSettings {
+    /// Check if an obstacle is used by a navmesh with these settings, from its layers and its height above the
+    /// navmesh. `mesh_transform` is the transform used to project obstacles on the navmesh.
+    pub(crate) fn uses_obstacle(
+        &self,
+        mesh_transform: &Transform,
+        layers: Option<&ObstacleLayers>,
+        obstacle_transform: &GlobalTransform,
+    ) -> bool {
+        layers
+            .map(|l| l.intersects(self.obstacle_layers))
+            .unwrap_or(true)
+            && self
+                .obstacle_height_range
+                .as_ref()
+                .map(|range| {
+                    range.contains(
+                        &mesh_transform
+                            .transform_point(obstacle_transform.translation())
+                            .z,
+                    )
+                })
+                .unwrap_or(true)
+    }
+
+    /// Polygons of an obstacle as used by a navmesh with these settings, cut at the
+    /// [`slice_height`](NavMeshSettings::slice_height) and up to the [`agent_height`](NavMeshSettings::agent_height)
+    /// if set.
+    pub(crate) fn obstacle_polygons<T: ObstacleSource>(
+        &self,
+        obstacle: &T,
+        obstacle_transform: &GlobalTransform,
+        mesh_transform: &Transform,
+    ) -> Vec<Vec<Vec2>> {
+        if self.slice_height.is_none() && self.agent_height.is_none() {
+            return obstacle.get_polygons(obstacle_transform, mesh_transform);
+        }
+        let min_height = self.slice_height.unwrap_or(0.0);
+        let max_height = min_height + self.agent_height.unwrap_or(0.0);
+        obstacle.get_sliced_polygons(obstacle_transform, mesh_transform, min_height, max_height)
+    }
+
+    /// Create a [`NavMeshBundle`] with these settings, updated on every change, with the navmesh available under a
+    /// handle built from `id`. See [`NavMeshBundle::with_id`].
+    pub fn bundle_with_id(self, id: u128) -> NavMeshBundle {
+        NavMeshBundle {
+            settings: self,
+            update_mode: NavMeshUpdateMode::Direct,
+            ..NavMeshBundle::with_id(id)
         }
     }
 }
@@ -74,8 +281,12 @@ pub enum NavMeshStatus {
     Built,
     /// Last build command failed. The mesh may still be available from a previous build, but it will be out of date.
     ///
-    /// This can happen if the build takes longer than the `build_timeout` defined in the settings.
+    /// This can happen if the build takes longer than the `build_timeout` defined in the settings. The reason is
+    /// available in the [`NavMeshBuildError`] component.
     Failed,
+    /// Settings are invalid, or the last build gave no polygons. The reason is available in the
+    /// [`NavMeshSettingsError`] component. The navmesh is built again on the next update once the settings are valid.
+    Invalid,
 }
 
 /// Control when to update the navmesh
@@ -87,6 +298,11 @@ pub enum NavMeshUpdateMode {
     Debounced(f32),
     /// On demand, set it to `true` to trigger an update
     OnDemand(bool),
+    /// Every `u32` ticks of the [`FixedUpdate`] schedule, whether something changed or not
+    ///
+    /// Builds then happen at a fixed cadence, aligned with the simulation, which suits deterministic simulations and
+    /// lockstep multiplayer.
+    Scheduled(u32),
 }
 
 /// If this component is added to an entity with the `NavMeshBundle`, updating the navmesh will be blocking. Otherwise
@@ -94,41 +310,747 @@ pub enum NavMeshUpdateMode {
 #[derive(Component, Debug, Copy, Clone)]
 pub struct NavMeshUpdateModeBlocking;
 
+/// If this component is added to an entity with the `NavMeshBundle`, its builds are split in steps run across frames,
+/// spending at most `max_ms_per_frame` each frame.
+///
+/// This keeps the frame rate stable on platforms where the [`AsyncComputeTaskPool`] runs on the main thread, like the
+/// web. The triangulation of the navmesh and of each of its variants is one step that can't be split, so a frame can
+/// still exceed the budget on large navmeshes. It has no effect if [`NavMeshUpdateModeBlocking`] is also present.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct NavMeshUpdateModeTimeSliced {
+    /// Maximum time spent building the navmesh each frame, in milliseconds.
+    pub max_ms_per_frame: f32,
+}
+
+/// If this component is added to an entity with the `NavMeshBundle`, changes to its [`GlobalTransform`], or its
+/// [`Transform`] if it has none, don't trigger a rebuild of the navmesh.
+///
+/// By default, obstacles are projected again when the navmesh moves, like on a rotating platform or a ship deck. Use
+/// this for navmeshes that don't move, or when obstacles move along with the navmesh.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct NavMeshStaticTransform;
+
+/// If this component is added to an entity with the `NavMeshBundle`, the built mesh will also be stored on the entity
+/// in a [`NavMeshData`] component, in addition to the [`NavMesh`] asset.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct StoreNavMeshData;
+
+/// The last built mesh of an entity with the [`StoreNavMeshData`] component.
+///
+/// This can be used to find paths from systems that already query the navmesh entity, without going through the
+/// [`Assets<NavMesh>`] resource. Points must be in the mesh local space, use the entity [`Transform`] to convert them.
+#[derive(Component, Debug, Clone)]
+pub struct NavMeshData(pub Arc<polyanya::Mesh>);
+
+/// If this component is added to an entity with the `NavMeshBundle`, updating the navmesh will happen on this
+/// [`TaskPool`] instead of the [`AsyncComputeTaskPool`].
+///
+/// This can be used to dedicate threads to navmesh builds, so that they don't compete with other async work. The
+/// parallel steps of the builds also run on it, instead of the [`ComputeTaskPool`]. It has no effect if
+/// [`NavMeshUpdateModeBlocking`] is also present.
+#[derive(Component, Debug, Clone)]
+pub struct NavMeshUpdateTaskPool(pub Arc<TaskPool>);
+
+/// Reason of the last failed build of a navmesh entity, present when its status is [`NavMeshStatus::Failed`], or
+/// [`NavMeshStatus::Invalid`] for an [`EmptyMesh`](NavMeshBuildError::EmptyMesh).
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum NavMeshBuildError {
+    /// The build took longer than the [`build_timeout`](NavMeshSettings::build_timeout).
+    Timeout {
+        /// Duration after which the build was cancelled, in seconds.
+        seconds: f32,
+    },
+    /// An obstacle has points that are not finite, for example because its transform has a scale of zero.
+    NonFiniteObstacle {
+        /// Translation of the obstacle.
+        translation: Vec3,
+    },
+    /// The triangulation produced no polygons. The outer edges of the [`fixed`](NavMeshSettings::fixed)
+    /// triangulation can be empty or degenerate, or obstacles cover the whole area. The navmesh is then
+    /// [`Invalid`](NavMeshStatus::Invalid).
+    EmptyMesh {
+        /// Agent radius of the navmesh variant that is empty.
+        agent_radius: f32,
+    },
+    /// The build was cancelled before finishing, because it was superseded by a newer one.
+    Cancelled,
+}
+
+impl Display for NavMeshBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavMeshBuildError::Timeout { seconds } => {
+                write!(f, "Build timed out after {} seconds", seconds)
+            }
+            NavMeshBuildError::NonFiniteObstacle { translation } => {
+                write!(f, "Obstacle at {} has non finite points", translation)
+            }
+            NavMeshBuildError::EmptyMesh { agent_radius } => write!(
+                f,
+                "Triangulation for agent radius {} has no polygons",
+                agent_radius
+            ),
+            NavMeshBuildError::Cancelled => write!(f, "Build was cancelled"),
+        }
+    }
+}
+
+impl Error for NavMeshBuildError {}
+
+/// Limit on the time spent building navmeshes each frame.
+///
+/// Navmeshes waiting to be built are queued, the ones waiting the longest are built first. Once the budget is spent,
+/// the remaining builds wait for the next frame. At least one build happens each frame.
+///
+/// This only applies to navmeshes with [`NavMeshUpdateModeBlocking`], as other builds happen outside of the frame.
+#[derive(Resource, Debug, Copy, Clone)]
+pub struct NavMeshUpdateBudget {
+    /// Maximum time spent building navmeshes each frame, in milliseconds.
+    pub max_ms_per_frame: f32,
+}
+
+/// Priority of a navmesh entity, higher values are handled first when several navmeshes are waiting.
+///
+/// This orders navmesh builds, and their publication when limited by a [`NavMeshPublishLimit`]. Navmeshes without
+/// this component have a priority of 0.
+#[derive(Component, Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NavMeshPriority(pub i32);
+
+/// Marker for entities whose proximity makes navmeshes more important to build, usually the player or active agents.
+///
+/// See [`NavMeshPriorityFromAnchors`].
+#[derive(Component, Debug, Copy, Clone, Default)]
+pub struct NavMeshPriorityAnchor;
+
+/// If this component is added to an entity with the `NavMeshBundle`, its [`NavMeshPriority`] is updated every frame
+/// from the distance to the closest [`NavMeshPriorityAnchor`].
+///
+/// Navmeshes are ranked by that distance, the closest one gets the priority 0, the next one -1, and so on. Navmeshes
+/// that are not built yet are ranked last.
+#[derive(Component, Debug, Copy, Clone, Default)]
+pub struct NavMeshPriorityFromAnchors;
+
+type NavMeshToPrioritizeQuery<'world, 'state, 'a, 'b> = Query<
+    'world,
+    'state,
+    (Entity, &'a Handle<NavMesh>, Option<&'b NavMeshPriority>),
+    With<NavMeshPriorityFromAnchors>,
+>;
+
+fn prioritize_by_anchors(
+    mut commands: Commands,
+    anchors: Query<&GlobalTransform, With<NavMeshPriorityAnchor>>,
+    navmesh_entities: NavMeshToPrioritizeQuery,
+    navmeshes: Res<Assets<NavMesh>>,
+) {
+    let mut ranked = navmesh_entities
+        .iter()
+        .map(|(entity, handle, priority)| {
+            let distance = navmeshes
+                .get(handle)
+                .and_then(|navmesh| {
+                    anchors
+                        .iter()
+                        .filter_map(|anchor| {
+                            let inner = navmesh
+                                .transform()
+                                .transform_point(anchor.translation())
+                                .xy();
+                            navmesh
+                                .closest_point(inner)
+                                .map(|closest| closest.distance(inner))
+                        })
+                        .min_by(f32::total_cmp)
+                })
+                .unwrap_or(f32::INFINITY);
+            (entity, priority, distance)
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| a.2.total_cmp(&b.2));
+    for (rank, (entity, priority, _)) in ranked.into_iter().enumerate() {
+        let new_priority = NavMeshPriority(-(rank as i32));
+        if priority != Some(&new_priority) {
+            commands.entity(entity).insert(new_priority);
+        }
+    }
+}
+
+/// Limit on how many built navmeshes are published as assets each frame.
+///
+/// Publishing a large navmesh has a cost, limiting it can avoid a hitch when many builds finish in the same frame.
+/// Built navmeshes waiting to be published are ordered by their [`NavMeshPriority`].
+#[derive(Resource, Debug, Copy, Clone, Default)]
+pub struct NavMeshPublishLimit {
+    /// Maximum number of navmeshes published per frame. `None` for no limit.
+    pub max_per_frame: Option<usize>,
+}
+
+/// Automatically mark obstacles as [`CachableObstacle`] once they stopped moving.
+///
+/// When this resource is present, obstacles whose transform didn't change for `idle_duration` seconds are cached.
+/// Obstacles cached this way are no longer cached as soon as they move again. Obstacles cached manually are not
+/// changed.
+#[derive(Resource, Debug, Copy, Clone)]
+pub struct CachableObstaclePromotion {
+    /// Duration in seconds an obstacle must stay still to be cached.
+    pub idle_duration: f32,
+}
+
+impl Default for CachableObstaclePromotion {
+    fn default() -> Self {
+        Self { idle_duration: 2.0 }
+    }
+}
+
+fn promote_idle_obstacles<Marker: Component, Frame: TransformProvider>(
+    mut commands: Commands,
+    promotion: Option<Res<CachableObstaclePromotion>>,
+    obstacles: Query<(Entity, Ref<Frame>, Has<CachableObstacle>), With<Marker>>,
+    time: Res<Time>,
+    mut idle: Local<EntityHashMap<f32>>,
+    mut promoted: Local<EntityHashSet>,
+) {
+    let Some(promotion) = promotion else {
+        return;
+    };
+    idle.retain(|entity, _| obstacles.contains(*entity));
+    promoted.retain(|entity| obstacles.contains(*entity));
+    for (entity, frame, cached) in &obstacles {
+        if frame.is_changed() {
+            idle.insert(entity, 0.0);
+            if promoted.remove(&entity) && cached {
+                commands.entity(entity).remove::<CachableObstacle>();
+            }
+            continue;
+        }
+        if cached {
+            continue;
+        }
+        let since = idle.entry(entity).or_default();
+        *since += time.delta_seconds();
+        if *since >= promotion.idle_duration {
+            commands.entity(entity).insert(CachableObstacle);
+            promoted.insert(entity);
+        }
+    }
+}
+
+/// Number of segments for a full circle when rounding the corners of inflated obstacles.
+pub(crate) const INFLATION_QUALITY: usize = 16;
+
+/// Statistics of the last successful build of a navmesh, to help tuning its [`NavMeshSettings`].
+///
+/// Counts are for the navmesh built with [`NavMeshSettings::agent_radius`], not its variants.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct NavMeshBuildReport {
+    /// Number of obstacles used for the build.
+    pub obstacles: usize,
+    /// Number of obstacles whose transform was taken from the cache, see [`CachableObstacle`].
+    pub cached_obstacles: usize,
+    /// Number of polygons from the obstacles, before being inflated by the agent radius.
+    pub obstacle_polygons: usize,
+    /// Number of polygons of the navmesh after triangulation and [simplification](NavMeshSettings::simplify).
+    pub polygons_before_merge: usize,
+    /// Number of polygons of the navmesh after the [merge steps](NavMeshSettings::merge_steps).
+    pub polygons_after_merge: usize,
+    /// Number of vertices of the navmesh.
+    pub vertices: usize,
+    /// Duration of the build, including the variants.
+    pub duration: Duration,
+    /// Hash of the obstacle polygons. When the next build has the same settings and obstacle polygons, it's skipped.
+    /// When they match one of the last few builds, that navmesh is published again instead of being rebuilt.
+    pub obstacle_hash: u64,
+    /// `true` if the settings changed since the previous build, or if there was no previous build.
+    pub settings_changed: bool,
+}
+
 #[cfg_attr(feature = "tracing", instrument(skip_all))]
-fn build_navmesh<T: ObstacleSource>(
-    obstacles: Vec<(GlobalTransform, T)>,
+async fn build_navmesh<T: ObstacleSource>(
+    obstacles: Vec<(GlobalTransform, T, Option<f32>)>,
     settings: NavMeshSettings,
     mesh_transform: Transform,
-) -> NavMesh {
-    let obstacle_aabbs = obstacles
+    cached_obstacles: usize,
+    previous_hash: Option<u64>,
+    cached_hashes: Vec<u64>,
+    mut checkpoint: Checkpoint<'_>,
+) -> NavMeshBuildResult {
+    let start = Instant::now();
+    let mut obstacle_polygons = vec![];
+    let polygons = checked_map(
+        &obstacles,
+        &settings,
+        PARALLEL_CHUNK_SIZE,
+        &mut checkpoint,
+        |(transform, obstacle, _)| settings.obstacle_polygons(obstacle, transform, &mesh_transform),
+    )
+    .await?;
+    for ((transform, _, radius), polygons) in obstacles.iter().zip(polygons) {
+        if polygons.iter().flatten().any(|point| !point.is_finite()) {
+            return Err(NavMeshBuildError::NonFiniteObstacle {
+                translation: transform.translation(),
+            });
+        }
+        obstacle_polygons.extend(
+            polygons
+                .into_iter()
+                .filter(|polygon| !polygon.is_empty())
+                .map(|polygon| (polygon, *radius)),
+        );
+    }
+    if settings.deterministic {
+        sort_polygons(&mut obstacle_polygons);
+    }
+    let obstacle_hash = hash_obstacles(&obstacle_polygons, &mesh_transform);
+    if previous_hash == Some(obstacle_hash) {
+        return Ok(NavMeshBuildOutcome::Unchanged);
+    }
+    if cached_hashes.contains(&obstacle_hash) {
+        return Ok(NavMeshBuildOutcome::Cached(obstacle_hash));
+    }
+
+    let mut report = NavMeshBuildReport {
+        obstacles: obstacles.len(),
+        cached_obstacles,
+        obstacle_hash,
+        settings_changed: previous_hash.is_none(),
+        ..default()
+    };
+    let mut navmesh = build_with_report(
+        &obstacle_polygons,
+        &settings,
+        mesh_transform,
+        &mut report,
+        &mut checkpoint,
+    )
+    .await?;
+    let margin = settings
+        .agent_radius_variants
+        .iter()
+        .fold(settings.agent_radius, |max, radius| max.max(*radius));
+    navmesh.obstacle_footprints = Arc::new(
+        obstacle_polygons
+            .iter()
+            .map(|(polygon, radius)| {
+                obstacle_footprint(polygon, inflation_radius(&settings, margin, *radius))
+            })
+            .collect(),
+    );
+    report.duration = start.elapsed();
+    Ok(NavMeshBuildOutcome::Built(Box::new((navmesh, report))))
+}
+
+/// Obstacle polygon projected on a navmesh, with its [`ObstacleRadiusOverride`].
+pub(crate) type ObstaclePolygon = (Vec<Vec2>, Option<f32>);
+
+/// Radius to inflate an obstacle by for a navmesh built for `agent_radius`, taking its [`ObstacleRadiusOverride`] into
+/// account.
+fn inflation_radius(
+    settings: &NavMeshSettings,
+    agent_radius: f32,
+    radius_override: Option<f32>,
+) -> f32 {
+    radius_override
+        .map(|radius| (radius + agent_radius - settings.agent_radius).max(0.0))
+        .unwrap_or(agent_radius)
+}
+
+/// Hash of the projected obstacle polygons and the transform of the navmesh, to detect builds that would give the same
+/// navmesh.
+fn hash_obstacles(obstacle_polygons: &[ObstaclePolygon], mesh_transform: &Transform) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    mesh_transform
+        .compute_matrix()
+        .to_cols_array()
+        .map(f32::to_bits)
+        .hash(&mut hasher);
+    for (polygon, radius) in obstacle_polygons {
+        polygon.len().hash(&mut hasher);
+        for point in polygon {
+            point.to_array().map(f32::to_bits).hash(&mut hasher);
+        }
+        radius.map(f32::to_bits).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Hash and bounds of an obstacle polygon, grown by `margin`.
+fn obstacle_footprint(polygon: &[Vec2], margin: f32) -> (u64, Rect) {
+    let mut hasher = DefaultHasher::new();
+    for point in polygon {
+        point.to_array().map(f32::to_bits).hash(&mut hasher);
+    }
+    let bounds = polygon.iter().fold(
+        Rect {
+            min: Vec2::splat(f32::INFINITY),
+            max: Vec2::splat(f32::NEG_INFINITY),
+        },
+        |bounds, point| bounds.union_point(*point),
+    );
+    (hasher.finish(), bounds.inflate(margin))
+}
+
+/// Union of the footprints of the obstacles in only one of `previous` and `next`. `None` if they are the same, as the
+/// navmesh then changed for another reason.
+fn changed_area(previous: &[(u64, Rect)], next: &[(u64, Rect)]) -> Option<Rect> {
+    let hashes = |footprints: &[(u64, Rect)]| {
+        footprints
+            .iter()
+            .map(|(hash, _)| *hash)
+            .collect::<HashSet<_>>()
+    };
+    let (previous_hashes, next_hashes) = (hashes(previous), hashes(next));
+    previous
+        .iter()
+        .filter(|(hash, _)| !next_hashes.contains(hash))
+        .chain(
+            next.iter()
+                .filter(|(hash, _)| !previous_hashes.contains(hash)),
+        )
+        .map(|(_, bounds)| *bounds)
+        .reduce(|a, b| a.union(b))
+}
+
+/// Transform used to project obstacles on the navmesh, taking [`NavMeshSettings::up_override`] into account.
+#[cfg_attr(not(feature = "3d"), allow(unused_variables))]
+pub(crate) fn mesh_transform(settings: &NavMeshSettings, transform: Transform) -> Transform {
+    #[cfg(feature = "3d")]
+    if let Some(up) = settings.up_override {
+        return Transform {
+            rotation: Quat::from_rotation_arc(*up, Vec3::Z),
+            ..transform
+        };
+    }
+    transform
+}
+
+/// Build a navmesh and its variants from obstacle polygons, in the mesh local space.
+pub(crate) fn build_from_polygons(
+    obstacle_polygons: &[Vec<Vec2>],
+    settings: &NavMeshSettings,
+    mesh_transform: Transform,
+) -> Result<NavMesh, NavMeshBuildError> {
+    let mut obstacle_polygons = obstacle_polygons
         .iter()
-        .map(|(transform, obstacle)| obstacle.get_polygon(transform, &mesh_transform))
-        .filter(|polygon| !polygon.is_empty());
+        .map(|polygon| (polygon.clone(), None))
+        .collect::<Vec<_>>();
+    if settings.deterministic {
+        sort_polygons(&mut obstacle_polygons);
+    }
+    block_on(build_with_report(
+        &obstacle_polygons,
+        settings,
+        mesh_transform,
+        &mut NavMeshBuildReport::default(),
+        &mut Checkpoint::new(&AtomicBool::new(false), None, Some(compute_task_pool())),
+    ))
+}
+
+async fn build_with_report(
+    obstacle_polygons: &[ObstaclePolygon],
+    settings: &NavMeshSettings,
+    mesh_transform: Transform,
+    report: &mut NavMeshBuildReport,
+    checkpoint: &mut Checkpoint<'_>,
+) -> Result<NavMesh, NavMeshBuildError> {
+    report.obstacle_polygons = obstacle_polygons.len();
+    let mut navmesh = build_variant(
+        obstacle_polygons,
+        settings,
+        settings.agent_radius,
+        report,
+        checkpoint,
+    )
+    .await?;
+    if checkpoint.slice.is_some() {
+        for radius in &settings.agent_radius_variants {
+            let variant = build_variant(
+                obstacle_polygons,
+                settings,
+                *radius,
+                &mut NavMeshBuildReport::default(),
+                checkpoint,
+            )
+            .await?;
+            navmesh.variants.push(variant);
+        }
+    } else {
+        let chunk_size = parallel_chunk_size(settings, 1);
+        let cancelled = checkpoint.cancelled;
+        // Variants are built in parallel, each of them serially to not nest task scopes
+        navmesh.variants = par_map(
+            &settings.agent_radius_variants,
+            chunk_size,
+            checkpoint.pool,
+            |radius| {
+                block_on(build_variant(
+                    obstacle_polygons,
+                    settings,
+                    *radius,
+                    &mut NavMeshBuildReport::default(),
+                    &mut Checkpoint::new(cancelled, None, None),
+                ))
+            },
+        )
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+    }
+    navmesh.set_transform(mesh_transform);
+    if settings.precompute {
+        navmesh.precompute();
+    }
+    Ok(navmesh)
+}
+
+/// Number of obstacles or polygons processed by each task when a build is split in parallel tasks.
+const PARALLEL_CHUNK_SIZE: usize = 64;
+
+/// Chunk size to use with [`par_map`], disabling parallelism for [deterministic](NavMeshSettings::deterministic)
+/// builds.
+fn parallel_chunk_size(settings: &NavMeshSettings, chunk_size: usize) -> usize {
+    if settings.deterministic {
+        usize::MAX
+    } else {
+        chunk_size
+    }
+}
+
+/// Sort obstacle polygons by their points, so that the order in which obstacles are found doesn't change the build.
+fn sort_polygons(obstacle_polygons: &mut [ObstaclePolygon]) {
+    obstacle_polygons.sort_by_cached_key(|(polygon, radius)| {
+        (
+            polygon
+                .iter()
+                .map(|point| point.to_array().map(f32::to_bits))
+                .collect::<Vec<_>>(),
+            radius.map(f32::to_bits),
+        )
+    });
+}
+
+/// Task pool of the parallel steps of builds, when no [`NavMeshUpdateTaskPool`] is set.
+fn compute_task_pool() -> &'static TaskPool {
+    ComputeTaskPool::get_or_init(TaskPool::default)
+}
+
+/// Map `items` in chunks of `chunk_size` on `pool`, keeping their order. Items are mapped serially without a pool.
+///
+/// Only the steps independent of each other are done in parallel: extracting the polygons of obstacles, inflating
+/// them, and building the variants. The triangulation itself is serial.
+fn par_map<T: Sync, U: Send + 'static>(
+    items: &[T],
+    chunk_size: usize,
+    pool: Option<&TaskPool>,
+    f: impl Fn(&T) -> U + Sync,
+) -> Vec<U> {
+    let Some(pool) = pool.filter(|_| items.len() > chunk_size) else {
+        return items.iter().map(f).collect();
+    };
+    let f = &f;
+    pool.scope(|scope| {
+        for chunk in items.chunks(chunk_size) {
+            scope.spawn(async move { chunk.iter().map(f).collect::<Vec<_>>() });
+        }
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Map `items` in chunks of `chunk_size` with [`par_map`], or serially with a checkpoint before each chunk for
+/// time-sliced builds.
+///
+/// Time-sliced builds are serial, so they are deterministic and keep their chunks even for
+/// [deterministic](NavMeshSettings::deterministic) builds.
+async fn checked_map<T: Sync, U: Send + 'static>(
+    items: &[T],
+    settings: &NavMeshSettings,
+    chunk_size: usize,
+    checkpoint: &mut Checkpoint<'_>,
+    f: impl Fn(&T) -> U + Sync,
+) -> Result<Vec<U>, NavMeshBuildError> {
+    if checkpoint.slice.is_none() {
+        return Ok(par_map(
+            items,
+            parallel_chunk_size(settings, chunk_size),
+            checkpoint.pool,
+            f,
+        ));
+    }
+    let mut mapped = Vec::with_capacity(items.len());
+    for chunk in items.chunks(chunk_size) {
+        checkpoint.check().await?;
+        mapped.extend(chunk.iter().map(&f));
+    }
+    Ok(mapped)
+}
+
+/// Counter of frames, used by time-sliced builds to know when they can resume. See [`NavMeshUpdateModeTimeSliced`].
+#[derive(Resource, Clone, Default)]
+struct FrameClock(Arc<Mutex<FrameClockState>>);
+
+#[derive(Default)]
+struct FrameClockState {
+    frame: u64,
+    /// Builds waiting for the next frame.
+    waiting: Vec<Waker>,
+}
+
+impl FrameClock {
+    /// Start a new frame, waking up the builds waiting for it.
+    fn advance(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.frame = state.frame.wrapping_add(1);
+        state.waiting.drain(..).for_each(Waker::wake);
+    }
+}
+
+fn advance_frame_clock(clock: Res<FrameClock>) {
+    clock.advance();
+}
+
+/// Time budget of a time-sliced build in the current frame.
+struct TimeSlice {
+    clock: FrameClock,
+    budget: Duration,
+    frame: Option<u64>,
+    start: Instant,
+}
+
+impl TimeSlice {
+    /// Wait for the next frame if the budget of the current frame is spent.
+    async fn wait(&mut self) {
+        std::future::poll_fn(|context| {
+            let mut state = self.clock.0.lock().unwrap();
+            if self.frame != Some(state.frame) {
+                self.frame = Some(state.frame);
+                self.start = Instant::now();
+                Poll::Ready(())
+            } else if self.start.elapsed() < self.budget {
+                Poll::Ready(())
+            } else {
+                state.waiting.push(context.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+}
+
+/// Points between the steps of a build, where it stops when cancelled, and yields to the next frame when time-sliced.
+///
+/// Also holds the task pool the parallel steps of the build run on, see [`par_map`].
+struct Checkpoint<'a> {
+    cancelled: &'a AtomicBool,
+    slice: Option<TimeSlice>,
+    pool: Option<&'a TaskPool>,
+}
+
+impl<'a> Checkpoint<'a> {
+    fn new(
+        cancelled: &'a AtomicBool,
+        slice: Option<TimeSlice>,
+        pool: Option<&'a TaskPool>,
+    ) -> Self {
+        Self {
+            cancelled,
+            slice,
+            pool,
+        }
+    }
+
+    async fn check(&mut self) -> Result<(), NavMeshBuildError> {
+        if let Some(slice) = self.slice.as_mut() {
+            slice.wait().await;
+        }
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(NavMeshBuildError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+async fn build_variant(
+    obstacle_polygons: &[ObstaclePolygon],
+    settings: &NavMeshSettings,
+    agent_radius: f32,
+    report: &mut NavMeshBuildReport,
+    checkpoint: &mut Checkpoint<'_>,
+) -> Result<NavMesh, NavMeshBuildError> {
+    checkpoint.check().await?;
+    let inflated = checked_map(
+        obstacle_polygons,
+        settings,
+        PARALLEL_CHUNK_SIZE,
+        checkpoint,
+        |(polygon, radius)| {
+            let polygon = offset_polygon(
+                polygon,
+                inflation_radius(settings, agent_radius, *radius),
+                INFLATION_QUALITY,
+            );
+            match settings.quantization {
+                Some(grid) => quantize_polygon(&polygon, grid),
+                None => Some(polygon),
+            }
+        },
+    )
+    .await?
+    .into_iter()
+    .flatten();
+    checkpoint.check().await?;
     let mut triangulation = settings.fixed.clone();
-    triangulation.add_obstacles(obstacle_aabbs);
+    triangulation.add_obstacles(inflated);
     if settings.simplify != 0.0 {
+        checkpoint.check().await?;
         triangulation.simplify(settings.simplify);
     }
+    // The triangulation is done by Polyanya in one call, it's the only step that can't be time-sliced
+    checkpoint.check().await?;
+    // Polyanya panics when triangulating without outer edges
+    if !has_area(&settings.outer_edges) {
+        return Err(NavMeshBuildError::EmptyMesh { agent_radius });
+    }
     let mut navmesh = triangulation.as_navmesh();
+    if navmesh.polygons.is_empty() {
+        return Err(NavMeshBuildError::EmptyMesh { agent_radius });
+    }
+    if let Some(grid) = settings.quantization {
+        quantize_mesh(&mut navmesh, grid);
+    }
+    report.polygons_before_merge = navmesh.polygons.len();
     for _ in 0..settings.merge_steps {
+        checkpoint.check().await?;
         if !navmesh.merge_polygons() {
             break;
         }
     }
+    report.polygons_after_merge = navmesh.polygons.len();
+    report.vertices = navmesh.vertices.len();
+    checkpoint.check().await?;
     navmesh.bake();
     navmesh.set_delta(settings.default_delta);
     let mut navmesh = NavMesh::from_polyanya_mesh(navmesh);
-    navmesh.set_transform(mesh_transform);
-    navmesh
+    navmesh.agent_radius = agent_radius;
+    navmesh.links = settings.links.clone();
+    if settings.spatial_index {
+        navmesh.build_spatial_index();
+    }
+    Ok(navmesh)
 }
 
 fn drop_dead_tasks(
     mut commands: Commands,
-    mut navmeshes: Query<(Entity, &mut NavMeshStatus, &NavMeshSettings), With<NavmeshUpdateTask>>,
+    mut navmeshes: Query<(
+        Entity,
+        &mut NavMeshStatus,
+        &NavMeshSettings,
+        &NavmeshUpdateTask,
+    )>,
     time: Res<Time>,
     mut task_ages: Local<EntityHashMap<f32>>,
 ) {
-    for (entity, mut status, settings) in &mut navmeshes {
+    for (entity, mut status, settings, task) in &mut navmeshes {
         if status.is_changed() {
             task_ages.insert(entity, time.elapsed_seconds());
         } else if let Some(age) = task_ages.get(&entity) {
@@ -136,8 +1058,12 @@ fn drop_dead_tasks(
                 continue;
             };
             if time.elapsed_seconds() - *age > timeout {
+                task.cancel();
                 *status = NavMeshStatus::Failed;
-                commands.entity(entity).remove::<NavmeshUpdateTask>();
+                commands
+                    .entity(entity)
+                    .remove::<NavmeshUpdateTask>()
+                    .insert(NavMeshBuildError::Timeout { seconds: timeout });
                 task_ages.remove(&entity);
                 warn!("NavMesh build timed out for {:?}", entity);
             }
@@ -145,32 +1071,296 @@ fn drop_dead_tasks(
     }
 }
 
+/// Outcome of a successful build.
+#[derive(Debug)]
+enum NavMeshBuildOutcome {
+    /// A new navmesh was built.
+    Built(Box<(NavMesh, NavMeshBuildReport)>),
+    /// The obstacles didn't change since the last build, the navmesh is kept.
+    Unchanged,
+    /// The obstacles are the same as for a navmesh in the [`NavMeshBuildCache`], with this hash.
+    Cached(u64),
+}
+
+type NavMeshBuildResult = Result<NavMeshBuildOutcome, NavMeshBuildError>;
+
+/// Number of built navmeshes kept for each navmesh entity, to be published again when their obstacles come back to the
+/// same state instead of being rebuilt.
+const BUILD_CACHE_SIZE: usize = 4;
+
+/// Last navmeshes built for an entity with its current settings, most recently used first, with their
+/// [`NavMeshBuildReport::obstacle_hash`]. Removed when the settings change.
+#[derive(Component, Debug, Default)]
+struct NavMeshBuildCache(Vec<(NavMesh, NavMeshBuildReport)>);
+
+impl NavMeshBuildCache {
+    fn hashes(&self) -> Vec<u64> {
+        self.0
+            .iter()
+            .map(|(_, report)| report.obstacle_hash)
+            .collect()
+    }
+
+    fn insert(&mut self, navmesh: NavMesh, report: NavMeshBuildReport) {
+        self.0.insert(0, (navmesh, report));
+        self.0.truncate(BUILD_CACHE_SIZE);
+    }
+
+    /// Take the navmesh built for `hash`, and move it to the front.
+    fn get(&mut self, hash: u64) -> Option<(NavMesh, NavMeshBuildReport)> {
+        let index = self
+            .0
+            .iter()
+            .position(|(_, report)| report.obstacle_hash == hash)?;
+        let entry = self.0.remove(index);
+        self.0.insert(0, entry.clone());
+        Some(entry)
+    }
+}
+
 /// Task holder for a navmesh update.
 #[derive(Component, Debug, Clone)]
-pub struct NavmeshUpdateTask(Arc<RwLock<Option<NavMesh>>>);
+pub struct NavmeshUpdateTask(Arc<RwLock<Option<NavMeshBuildResult>>>, Arc<AtomicBool>);
+
+impl NavmeshUpdateTask {
+    /// Cancel the build. It stops at the next step, and its result is never published.
+    ///
+    /// This is done automatically when the [`NavMeshSettings`] change during a build, or when the build times out.
+    pub fn cancel(&self) {
+        self.1.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the build was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.1.load(Ordering::Relaxed)
+    }
+}
 
-type NavMeshToUpdateQuery<'world, 'state, 'a, 'b, 'c, 'd, 'e, 'f> = Query<
+type NavMeshToUpdateQuery<'world, 'state, 'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h, 'i, 'j, 'k> = Query<
     'world,
     'state,
     (
         Entity,
         Ref<'a, NavMeshSettings>,
-        Ref<'b, Transform>,
+        (Ref<'b, Transform>, Option<Ref<'k, GlobalTransform>>),
         &'c NavMeshUpdateMode,
         &'d mut NavMeshStatus,
         Option<&'e NavMeshUpdateModeBlocking>,
         Option<&'f NavmeshUpdateTask>,
+        Option<&'g NavMeshUpdateTaskPool>,
+        Option<&'h NavMeshBuildReport>,
+        Option<&'i NavMeshPriority>,
+        Has<NavMeshStaticTransform>,
+        Option<&'j NavMeshUpdateModeTimeSliced>,
+    ),
+>;
+
+type ObstacleQuery<'world, 'state, 'a, 'b, 'c, 'd, Marker, Obstacle, Frame> = Query<
+    'world,
+    'state,
+    (
+        Entity,
+        Ref<'a, Frame>,
+        &'b Obstacle,
+        Option<Ref<'c, ObstacleLayers>>,
+        Has<CachableObstacle>,
+        Option<Ref<'d, ObstacleRadiusOverride>>,
     ),
+    With<Marker>,
 >;
 
-fn trigger_navmesh_build<Marker: Component, Obstacle: ObstacleSource>(
+/// How an obstacle changed, see [`ObstacleAffectedNavMesh`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObstacleChange {
+    /// The obstacle was added.
+    Added,
+    /// The obstacle was removed, or despawned.
+    Removed,
+    /// The obstacle moved.
+    Moved,
+    /// The [`ObstacleLayers`] of the obstacle changed.
+    LayersChanged,
+    /// The [`ObstacleRadiusOverride`] of the obstacle was added or changed.
+    RadiusChanged,
+}
+
+/// Event sent when the [`NavmeshUpdaterPlugin`] detects a change of an obstacle that affects a navmesh, before the
+/// navmesh is rebuilt.
+///
+/// Useful to invalidate cached AI decisions, or to give feedback when a path gets blocked. Added and moved obstacles
+/// are only reported to the navmeshes using them, removed obstacles to all navmeshes.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObstacleAffectedNavMesh {
+    /// Entity of the navmesh.
+    pub navmesh: Entity,
+    /// Entity of the obstacle.
+    pub obstacle: Entity,
+    /// How the obstacle changed.
+    pub kind: ObstacleChange,
+}
+
+/// Navmeshes to update after obstacles were added or removed, filled by observers.
+#[derive(Resource)]
+struct DirtyNavMeshes<Marker: Component, Obstacle: ObstacleSource> {
+    entities: EntityHashSet,
+    /// Obstacles added since the last update, to send [`ObstacleAffectedNavMesh`] events.
+    added: EntityHashSet,
+    /// Obstacles removed since the last update, to send [`ObstacleAffectedNavMesh`] events.
+    removed: EntityHashSet,
+    /// Navmeshes with [`NavMeshUpdateMode::Scheduled`] whose build is due.
+    scheduled: EntityHashSet,
+    marker: PhantomData<(Marker, Obstacle)>,
+}
+
+impl<Marker: Component, Obstacle: ObstacleSource> Default for DirtyNavMeshes<Marker, Obstacle> {
+    fn default() -> Self {
+        Self {
+            entities: Default::default(),
+            added: Default::default(),
+            removed: Default::default(),
+            scheduled: Default::default(),
+            marker: Default::default(),
+        }
+    }
+}
+
+fn mark_navmeshes_dirty<Marker: Component, Obstacle: ObstacleSource, E: Event, C: Component>(
+    _trigger: Trigger<E, C>,
+    navmeshes: Query<Entity, With<NavMeshSettings>>,
+    mut dirty: ResMut<DirtyNavMeshes<Marker, Obstacle>>,
+) {
+    dirty.entities.extend(&navmeshes);
+}
+
+fn obstacle_added<Marker: Component, Obstacle: ObstacleSource, C: Component>(
+    trigger: Trigger<OnAdd, C>,
+    navmeshes: Query<Entity, With<NavMeshSettings>>,
+    mut dirty: ResMut<DirtyNavMeshes<Marker, Obstacle>>,
+) {
+    dirty.entities.extend(&navmeshes);
+    dirty.added.insert(trigger.entity());
+}
+
+fn obstacle_removed<Marker: Component, Obstacle: ObstacleSource, C: Component>(
+    trigger: Trigger<OnRemove, C>,
+    navmeshes: Query<Entity, With<NavMeshSettings>>,
+    mut dirty: ResMut<DirtyNavMeshes<Marker, Obstacle>>,
+) {
+    dirty.entities.extend(&navmeshes);
+    dirty.removed.insert(trigger.entity());
+}
+
+/// Give each navmesh a handle of its own, so that navmeshes spawned with the default handle don't overwrite each other.
+///
+/// Rebuilds are then inserted in place under this handle.
+pub(crate) fn reserve_navmesh_handle(
+    trigger: Trigger<OnAdd, NavMeshSettings>,
+    mut commands: Commands,
+    handles: Query<&Handle<NavMesh>>,
+    navmeshes: Res<Assets<NavMesh>>,
+) {
+    let entity = trigger.entity();
+    if handles
+        .get(entity)
+        .map(|handle| handle.id() == AssetId::default())
+        .unwrap_or(true)
+    {
+        commands.entity(entity).insert(navmeshes.reserve_handle());
+    }
+}
+
+fn schedule_navmesh_builds<Marker: Component, Obstacle: ObstacleSource>(
+    navmeshes: Query<(Entity, &NavMeshUpdateMode)>,
+    mut dirty: ResMut<DirtyNavMeshes<Marker, Obstacle>>,
+    mut ticks: Local<EntityHashMap<u32>>,
+) {
+    ticks.retain(|entity, _| {
+        matches!(
+            navmeshes.get(*entity),
+            Ok((_, NavMeshUpdateMode::Scheduled(_)))
+        )
+    });
+    for (entity, mode) in &navmeshes {
+        let NavMeshUpdateMode::Scheduled(every) = *mode else {
+            continue;
+        };
+        let tick = ticks.entry(entity).or_default();
+        *tick += 1;
+        if *tick >= every.max(1) {
+            *tick = 0;
+            dirty.scheduled.insert(entity);
+        }
+    }
+}
+
+/// Transform of a navmesh entity, and if it changed since it was added.
+///
+/// The [`GlobalTransform`] is used when present, so that navmeshes moved by their parent are also projected again.
+pub(crate) fn navmesh_transform(
+    (transform, global): &(Ref<Transform>, Option<Ref<GlobalTransform>>),
+) -> (Transform, bool) {
+    match global {
+        Some(global) => (
+            global.compute_transform(),
+            global.is_changed() && !global.is_added(),
+        ),
+        None => (**transform, transform.is_changed() && !transform.is_added()),
+    }
+}
+
+/// Check the settings of navmeshes when they are spawned or changed, so that invalid ones are reported before their
+/// first build is triggered.
+fn validate_navmesh_settings(
+    mut commands: Commands,
+    mut navmeshes: Query<(Entity, &NavMeshSettings, &mut NavMeshStatus), Changed<NavMeshSettings>>,
+) {
+    for (entity, settings, mut status) in &mut navmeshes {
+        match settings.validate() {
+            Err(error) => {
+                warn!("NavMesh settings of {:?} are invalid: {}", entity, error);
+                *status = NavMeshStatus::Invalid;
+                commands.entity(entity).insert(error);
+            }
+            Ok(()) => {
+                if matches!(*status, NavMeshStatus::Invalid) {
+                    *status = NavMeshStatus::Building;
+                    commands.entity(entity).remove::<NavMeshSettingsError>();
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn trigger_navmesh_build<Marker: Component, Obstacle: ObstacleSource, Frame: TransformProvider>(
     mut commands: Commands,
-    obstacles: Query<(Ref<GlobalTransform>, &Obstacle), With<Marker>>,
-    removed_obstacles: RemovedComponents<Marker>,
+    obstacles: ObstacleQuery<Marker, Obstacle, Frame>,
+    mut dirty: ResMut<DirtyNavMeshes<Marker, Obstacle>>,
     mut navmeshes: NavMeshToUpdateQuery,
     time: Res<Time>,
+    budget: Option<Res<NavMeshUpdateBudget>>,
     mut ready_to_update: Local<HashMap<Entity, (f32, bool)>>,
+    mut pending: Local<EntityHashMap<f32>>,
+    mut cached: Local<EntityHashMap<GlobalTransform>>,
+    mut settings_changed: Local<EntityHashSet>,
+    mut used_obstacles: Local<EntityHashMap<EntityHashSet>>,
+    mut changes: EventWriter<ObstacleAffectedNavMesh>,
+    clock: Res<FrameClock>,
+    caches: Query<&NavMeshBuildCache>,
 ) {
+    used_obstacles.retain(|entity, _| navmeshes.contains(*entity));
+    cached.retain(|entity, _| {
+        obstacles
+            .get(*entity)
+            .map(|(.., cachable, _)| cachable)
+            .unwrap_or(false)
+    });
+    settings_changed.extend(
+        navmeshes
+            .iter()
+            .filter(|(_, settings, ..)| settings.is_changed())
+            .map(|(entity, ..)| entity),
+    );
     let keys = ready_to_update.keys().cloned().collect::<Vec<_>>();
     let mut retrigger = vec![];
     for key in keys {
@@ -183,15 +1373,57 @@ fn trigger_navmesh_build<Marker: Component, Obstacle: ObstacleSource>(
             ready_to_update.remove(&key);
         }
     }
-    let has_removed_obstacles = !removed_obstacles.is_empty();
+    let added = std::mem::take(&mut dirty.added);
+    let removed = std::mem::take(&mut dirty.removed);
     let mut to_check = navmeshes
         .iter()
-        .filter_map(|(entity, settings, _, mode, ..)| {
-            if obstacles
+        .filter_map(|(entity, settings, transform, mode, .., is_static, _)| {
+            let (transform, changed) = navmesh_transform(&transform);
+            let moved = !is_static && changed;
+            let transform = mesh_transform(&settings, transform);
+            let mut affected = false;
+            let used = used_obstacles.get(&entity);
+            for (obstacle, t, _, layers, cachable, radius) in &obstacles {
+                let layers_changed = layers.as_ref().map(|l| l.is_changed()).unwrap_or(false);
+                let kind = if added.contains(&obstacle) {
+                    ObstacleChange::Added
+                } else if layers_changed {
+                    ObstacleChange::LayersChanged
+                } else if radius.map(|radius| radius.is_changed()).unwrap_or(false) {
+                    ObstacleChange::RadiusChanged
+                } else if !cachable && t.is_changed() && !t.is_added() {
+                    ObstacleChange::Moved
+                } else {
+                    continue;
+                };
+                // Obstacles on other layers can still affect the navmesh when their layers changed, and obstacles used
+                // by the last build when they moved away
+                if kind == ObstacleChange::LayersChanged
+                    || used.map(|used| used.contains(&obstacle)).unwrap_or(false)
+                    || settings.uses_obstacle(&transform, layers.as_deref(), &t.global_transform())
+                {
+                    affected |= kind != ObstacleChange::Added;
+                    changes.send(ObstacleAffectedNavMesh {
+                        navmesh: entity,
+                        obstacle,
+                        kind,
+                    });
+                }
+            }
+            // Removed obstacles can't be checked anymore, they are reported to all navmeshes
+            for obstacle in removed
                 .iter()
-                .any(|(t, _)| t.is_changed() && !t.is_added())
+                .filter(|obstacle| !obstacles.contains(**obstacle))
+            {
+                changes.send(ObstacleAffectedNavMesh {
+                    navmesh: entity,
+                    obstacle: *obstacle,
+                    kind: ObstacleChange::Removed,
+                });
+            }
+            if affected
                 || settings.is_changed()
-                || has_removed_obstacles
+                || moved
                 || matches!(mode, NavMeshUpdateMode::OnDemand(true))
             {
                 Some(entity)
@@ -200,13 +1432,14 @@ fn trigger_navmesh_build<Marker: Component, Obstacle: ObstacleSource>(
             }
         })
         .chain(retrigger)
+        .chain(dirty.entities.drain())
         .collect::<Vec<_>>();
+    let scheduled = std::mem::take(&mut dirty.scheduled);
+    to_check.extend(&scheduled);
     to_check.sort_unstable();
     to_check.dedup();
     for entity in to_check.into_iter() {
-        if let Ok((entity, settings, transform, update_mode, mut status, is_blocking, updating)) =
-            navmeshes.get_mut(entity)
-        {
+        if let Ok((entity, settings, _, update_mode, _, _, updating, ..)) = navmeshes.get(entity) {
             if let Some(val) = ready_to_update.get_mut(&entity) {
                 val.1 = true;
                 continue;
@@ -218,6 +1451,9 @@ fn trigger_navmesh_build<Marker: Component, Obstacle: ObstacleSource>(
                 NavMeshUpdateMode::OnDemand(false) => {
                     continue;
                 }
+                NavMeshUpdateMode::Scheduled(_) if !scheduled.contains(&entity) => {
+                    continue;
+                }
                 NavMeshUpdateMode::OnDemand(true) => {
                     commands
                         .entity(entity)
@@ -225,85 +1461,1029 @@ fn trigger_navmesh_build<Marker: Component, Obstacle: ObstacleSource>(
                 }
                 _ => (),
             };
-            if updating.is_some() {
+            if let Some(task) = updating {
+                // With the same settings, the running build is kept and the navmesh stays pending, to be built
+                // again with the new obstacles once the running build is published
+                if settings.is_changed() {
+                    // The running build is stale, stop it and start a new one once it's removed
+                    task.cancel();
+                    commands.entity(entity).remove::<NavmeshUpdateTask>();
+                }
+            }
+            pending
+                .entry(entity)
+                .or_insert_with(|| time.elapsed_seconds());
+        }
+    }
+
+    // Build the navmeshes with the highest priority first, then the ones that have been waiting the longest
+    let mut queue = pending
+        .iter()
+        .map(|(entity, requested)| {
+            let priority = navmeshes
+                .get(*entity)
+                .ok()
+                .and_then(|(.., priority, _, _)| priority.copied())
+                .unwrap_or_default();
+            (*entity, priority, *requested)
+        })
+        .collect::<Vec<_>>();
+    queue.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.total_cmp(&b.2)));
+    let start = Instant::now();
+    for (entity, ..) in queue {
+        let Ok((
+            entity,
+            settings,
+            transform,
+            _,
+            mut status,
+            is_blocking,
+            updating,
+            task_pool,
+            report,
+            _,
+            _,
+            time_sliced,
+        )) = navmeshes.get_mut(entity)
+        else {
+            pending.remove(&entity);
+            continue;
+        };
+        if updating.is_some() {
+            continue;
+        }
+        if let Err(error) = settings.validate() {
+            if !matches!(*status, NavMeshStatus::Invalid) {
+                warn!("NavMesh settings of {:?} are invalid: {}", entity, error);
+                *status = NavMeshStatus::Invalid;
+            }
+            commands.entity(entity).insert(error);
+            pending.remove(&entity);
+            continue;
+        }
+        if let (Some(_), Some(budget)) = (is_blocking, budget.as_ref()) {
+            if start.elapsed().as_secs_f32() * 1000.0 >= budget.max_ms_per_frame {
                 continue;
             }
-            let obstacles_local = obstacles
+        }
+        pending.remove(&entity);
+
+        let transform_local = mesh_transform(&settings, navmesh_transform(&transform).0);
+        let obstacles_local = obstacles
+            .iter()
+            .map(|(obstacle, t, o, layers, cachable, radius)| {
+                let transform = if cachable {
+                    *cached
+                        .entry(obstacle)
+                        .or_insert_with(|| t.global_transform())
+                } else {
+                    t.global_transform()
+                };
+                (obstacle, transform, o, layers, cachable, radius)
+            })
+            .filter(|(_, t, _, layers, ..)| {
+                settings.uses_obstacle(&transform_local, layers.as_deref(), t)
+            })
+            .map(|(obstacle, t, o, _, cachable, radius)| {
+                (
+                    obstacle,
+                    t,
+                    o.clone(),
+                    radius.map(|radius| radius.0),
+                    cachable,
+                )
+            })
+            .collect::<Vec<_>>();
+        let cached_obstacles = obstacles_local
+            .iter()
+            .filter(|(.., cachable)| *cachable)
+            .count();
+        used_obstacles.insert(
+            entity,
+            obstacles_local
                 .iter()
-                .map(|(t, o)| (*t, o.clone()))
-                .collect::<Vec<_>>();
-            let settings_local = settings.clone();
-            let transform_local = *transform;
-
-            *status = NavMeshStatus::Building;
-            let updating = NavmeshUpdateTask(Arc::new(RwLock::new(None)));
-            let writer = updating.0.clone();
-            if is_blocking.is_some() {
-                let navmesh = build_navmesh(obstacles_local, settings_local, transform_local);
+                .map(|(obstacle, ..)| *obstacle)
+                .collect(),
+        );
+        let obstacles_local = obstacles_local
+            .into_iter()
+            .map(|(_, t, o, radius, _)| (t, o, radius))
+            .collect::<Vec<_>>();
+        let settings_local = settings.clone();
+        // The obstacles are compared to the previous builds only if they used the same settings
+        let (previous_hash, cached_hashes) = if settings_changed.remove(&entity) {
+            commands.entity(entity).remove::<NavMeshBuildCache>();
+            (None, vec![])
+        } else {
+            (
+                report.map(|report| report.obstacle_hash),
+                caches
+                    .get(entity)
+                    .map(NavMeshBuildCache::hashes)
+                    .unwrap_or_default(),
+            )
+        };
+
+        *status = NavMeshStatus::Building;
+        let updating = NavmeshUpdateTask(
+            Arc::new(RwLock::new(None)),
+            Arc::new(AtomicBool::new(false)),
+        );
+        let writer = updating.0.clone();
+        let cancelled = updating.1.clone();
+        if is_blocking.is_some() {
+            let navmesh = block_on(build_navmesh(
+                obstacles_local,
+                settings_local,
+                transform_local,
+                cached_obstacles,
+                previous_hash,
+                cached_hashes,
+                Checkpoint::new(&cancelled, None, Some(compute_task_pool())),
+            ));
+            *writer.write().unwrap() = Some(navmesh);
+        } else {
+            let slice = time_sliced.map(|time_sliced| TimeSlice {
+                clock: clock.clone(),
+                budget: Duration::from_secs_f32(time_sliced.max_ms_per_frame.max(0.0) / 1000.0),
+                frame: None,
+                start: Instant::now(),
+            });
+            let pool = task_pool.map(|task_pool| task_pool.0.clone());
+            let task = async move {
+                let pool = match &pool {
+                    Some(pool) => pool,
+                    None => compute_task_pool(),
+                };
+                let navmesh = build_navmesh(
+                    obstacles_local,
+                    settings_local,
+                    transform_local,
+                    cached_obstacles,
+                    previous_hash,
+                    cached_hashes,
+                    Checkpoint::new(&cancelled, slice, Some(pool)),
+                )
+                .await;
                 *writer.write().unwrap() = Some(navmesh);
+            };
+            if let Some(task_pool) = task_pool {
+                task_pool.0.spawn(task).detach();
             } else {
-                AsyncComputeTaskPool::get()
-                    .spawn(async move {
-                        let navmesh =
-                            build_navmesh(obstacles_local, settings_local, transform_local);
-                        *writer.write().unwrap() = Some(navmesh);
-                    })
-                    .detach();
+                AsyncComputeTaskPool::get().spawn(task).detach();
             }
-            commands.entity(entity).insert(updating);
         }
+        commands.entity(entity).insert(updating);
     }
 }
 
+type NavMeshToPublishQuery<'world, 'state, 'a, 'b, 'c, 'd, 'e> = Query<
+    'world,
+    'state,
+    (
+        Entity,
+        &'a Handle<NavMesh>,
+        &'b NavmeshUpdateTask,
+        &'c mut NavMeshStatus,
+        Option<&'d StoreNavMeshData>,
+        Option<&'e NavMeshPriority>,
+    ),
+>;
+
 fn update_navmesh_asset(
     mut commands: Commands,
-    mut live_navmeshes: Query<(
-        Entity,
-        &Handle<NavMesh>,
-        &NavmeshUpdateTask,
-        &mut NavMeshStatus,
-    )>,
+    mut live_navmeshes: NavMeshToPublishQuery,
     mut navmeshes: ResMut<Assets<NavMesh>>,
+    limit: Res<NavMeshPublishLimit>,
+    mut diagnostics: Diagnostics,
+    mut skipped: Local<u64>,
+    mut caches: Query<&mut NavMeshBuildCache>,
 ) {
-    for (entity, handle, task, mut status) in &mut live_navmeshes {
-        let mut task = task.0.write().unwrap();
-        if task.is_some() {
-            let navmesh_built = task.take().unwrap();
-            commands.entity(entity).remove::<NavmeshUpdateTask>();
+    let mut ready = live_navmeshes
+        .iter()
+        .filter(|(_, _, task, ..)| task.0.read().unwrap().is_some())
+        .map(|(entity, .., priority)| (entity, priority.map(|p| p.0).unwrap_or_default()))
+        .collect::<Vec<_>>();
+    ready.sort_by_key(|(_, priority)| std::cmp::Reverse(*priority));
+    if let Some(max_per_frame) = limit.max_per_frame {
+        ready.truncate(max_per_frame);
+    }
 
-            debug!("navmesh built");
-            navmeshes.insert(handle, navmesh_built);
-            *status = NavMeshStatus::Built;
+    for (entity, _) in ready {
+        let Ok((entity, handle, task, mut status, store_data, _)) = live_navmeshes.get_mut(entity)
+        else {
+            continue;
+        };
+        let Some(result) = task.0.write().unwrap().take() else {
+            continue;
+        };
+        commands.entity(entity).remove::<NavmeshUpdateTask>();
+        if task.is_cancelled() {
+            continue;
+        }
+        let (mut navmesh_built, report) = match result {
+            Ok(NavMeshBuildOutcome::Built(built)) => {
+                let (navmesh, report) = *built;
+                commands.entity(entity).insert(report);
+                if let Ok(mut cache) = caches.get_mut(entity) {
+                    cache.insert(navmesh.clone(), report);
+                } else {
+                    let mut cache = NavMeshBuildCache::default();
+                    cache.insert(navmesh.clone(), report);
+                    commands.entity(entity).insert(cache);
+                }
+                (navmesh, report)
+            }
+            Ok(NavMeshBuildOutcome::Cached(hash)) => {
+                // The cache is only cleared when the settings change, which cancels the running build
+                let Some((navmesh, report)) = caches
+                    .get_mut(entity)
+                    .ok()
+                    .and_then(|mut cache| cache.get(hash))
+                else {
+                    continue;
+                };
+                debug!("navmesh build skipped, reusing a previous build");
+                *skipped += 1;
+                diagnostics.add_measurement(&NAVMESH_BUILD_SKIPPED, || *skipped as f64);
+                commands.entity(entity).insert(report);
+                (navmesh, report)
+            }
+            Ok(NavMeshBuildOutcome::Unchanged) => {
+                debug!("navmesh build skipped, obstacles didn't change");
+                *skipped += 1;
+                diagnostics.add_measurement(&NAVMESH_BUILD_SKIPPED, || *skipped as f64);
+                commands
+                    .entity(entity)
+                    .remove::<(NavMeshBuildError, NavMeshSettingsError)>();
+                *status = NavMeshStatus::Built;
+                continue;
+            }
+            Err(error @ NavMeshBuildError::EmptyMesh { .. }) => {
+                warn!("NavMesh settings of {:?} are invalid: {}", entity, error);
+                commands
+                    .entity(entity)
+                    .insert((error, NavMeshSettingsError::EmptyTriangulation));
+                *status = NavMeshStatus::Invalid;
+                continue;
+            }
+            Err(error) => {
+                warn!("NavMesh build failed for {:?}: {}", entity, error);
+                commands.entity(entity).insert(error);
+                *status = NavMeshStatus::Failed;
+                continue;
+            }
+        };
+        commands
+            .entity(entity)
+            .remove::<(NavMeshBuildError, NavMeshSettingsError)>();
+        if let Some(previous) = navmeshes.get(handle) {
+            navmesh_built.set_version(previous.version().wrapping_add(1));
+            if !report.settings_changed {
+                navmesh_built.changed_area = changed_area(
+                    &previous.obstacle_footprints,
+                    &navmesh_built.obstacle_footprints,
+                );
+            }
+        } else {
+            navmesh_built.set_version(0);
         }
+        if store_data.is_some() {
+            commands
+                .entity(entity)
+                .insert(NavMeshData(navmesh_built.get()));
+        }
+
+        debug!("navmesh built");
+        navmeshes.insert(handle, navmesh_built);
+        *status = NavMeshStatus::Built;
     }
 }
 
+/// Diagnostic counting the navmesh builds skipped because their obstacles and settings didn't change since the last
+/// build, or were the same as for a navmesh still in the build cache.
+pub const NAVMESH_BUILD_SKIPPED: DiagnosticPath =
+    DiagnosticPath::const_new("navmesh_build_skipped");
+
 /// Plugin to enable automatic navmesh updates.
 /// - `Marker` is the component type that marks an entity as an obstacle.
 /// - `Obstacle` is the component type that provides the position and shape of an obstacle.
+/// - `Frame` is the component type that provides the transform of an obstacle, see [`TransformProvider`].
 #[derive(Debug)]
-pub struct NavmeshUpdaterPlugin<Obstacle: ObstacleSource, Marker: Component = Obstacle> {
+pub struct NavmeshUpdaterPlugin<
+    Obstacle: ObstacleSource,
+    Marker: Component = Obstacle,
+    Frame: TransformProvider = GlobalTransform,
+> {
     marker1: PhantomData<Marker>,
     marker2: PhantomData<Obstacle>,
+    marker3: PhantomData<Frame>,
 }
 
-impl<Marker: Component, Obstacle: ObstacleSource> Default
-    for NavmeshUpdaterPlugin<Obstacle, Marker>
+impl<Marker: Component, Obstacle: ObstacleSource, Frame: TransformProvider> Default
+    for NavmeshUpdaterPlugin<Obstacle, Marker, Frame>
 {
     fn default() -> Self {
         Self {
             marker1: Default::default(),
             marker2: Default::default(),
+            marker3: Default::default(),
         }
     }
 }
 
-impl<Obstacle: ObstacleSource, Marker: Component> Plugin
-    for NavmeshUpdaterPlugin<Obstacle, Marker>
+impl<Obstacle: ObstacleSource, Marker: Component, Frame: TransformProvider> Plugin
+    for NavmeshUpdaterPlugin<Obstacle, Marker, Frame>
 {
     fn build(&self, app: &mut App) {
-        app.add_systems(PostUpdate, trigger_navmesh_build::<Marker, Obstacle>)
-            .add_systems(PreUpdate, update_navmesh_asset)
-            .add_systems(Update, drop_dead_tasks);
+        app.register_diagnostic(Diagnostic::new(NAVMESH_BUILD_SKIPPED))
+            .init_resource::<NavMeshPublishLimit>()
+            .init_resource::<DirtyNavMeshes<Marker, Obstacle>>()
+            .add_event::<ObstacleAffectedNavMesh>()
+            .observe(obstacle_added::<Marker, Obstacle, Marker>)
+            .observe(obstacle_removed::<Marker, Obstacle, Marker>)
+            .observe(obstacle_added::<Marker, Obstacle, Obstacle>)
+            .observe(obstacle_removed::<Marker, Obstacle, Obstacle>)
+            .observe(mark_navmeshes_dirty::<Marker, Obstacle, OnRemove, CachableObstacle>)
+            .observe(mark_navmeshes_dirty::<Marker, Obstacle, OnRemove, ObstacleRadiusOverride>)
+            .add_systems(
+                PostUpdate,
+                trigger_navmesh_build::<Marker, Obstacle, Frame>
+                    .after(validate_navmesh_settings)
+                    .after(TransformSystem::TransformPropagate),
+            )
+            .add_systems(FixedUpdate, schedule_navmesh_builds::<Marker, Obstacle>)
+            .add_systems(Update, promote_idle_obstacles::<Marker, Frame>);
+        // Systems that are not specific to an obstacle type are added by the first updater plugin only
+        if !app.world().contains_resource::<FrameClock>() {
+            app.init_resource::<FrameClock>()
+                .add_systems(First, advance_frame_clock)
+                .add_systems(PreUpdate, update_navmesh_asset)
+                .add_systems(PostUpdate, validate_navmesh_settings)
+                .add_systems(Update, (prioritize_by_anchors, drop_dead_tasks));
+        }
+        #[cfg(feature = "debug-with-gizmos")]
+        app.add_systems(
+            Update,
+            crate::debug::display_obstacle_polygons::<Marker, Obstacle, Frame>
+                .run_if(resource_exists::<GizmoConfigStore>),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::PrimitiveObstacle, VleueNavigatorPlugin};
+
+    fn spawn_navmesh(app: &mut App) -> Entity {
+        app.world_mut()
+            .spawn((
+                NavMeshBundle {
+                    settings: NavMeshSettings::builder()
+                        .outer_edges(&[
+                            Vec2::new(0.0, 0.0),
+                            Vec2::new(10.0, 0.0),
+                            Vec2::new(10.0, 10.0),
+                            Vec2::new(0.0, 10.0),
+                        ])
+                        .build()
+                        .unwrap(),
+                    update_mode: NavMeshUpdateMode::OnDemand(true),
+                    ..default()
+                },
+                NavMeshUpdateModeBlocking,
+            ))
+            .id()
+    }
+
+    fn spawn_obstacle(app: &mut App, x: f32) -> Entity {
+        app.world_mut()
+            .spawn((
+                PrimitiveObstacle::Rectangle(Rectangle::new(1.0, 1.0)),
+                GlobalTransform::from_translation(Vec3::new(x, 5.0, 0.0)),
+            ))
+            .id()
+    }
+
+    fn move_obstacle(app: &mut App, obstacle: Entity, x: f32) {
+        *app.world_mut()
+            .get_mut::<GlobalTransform>(obstacle)
+            .unwrap() = GlobalTransform::from_translation(Vec3::new(x, 5.0, 0.0));
+    }
+
+    fn rebuild(app: &mut App, entity: Entity) {
+        *app.world_mut()
+            .get_mut::<NavMeshUpdateMode>(entity)
+            .unwrap() = NavMeshUpdateMode::OnDemand(true);
+        // Built in `PostUpdate`, published in the next `PreUpdate`
+        app.update();
+        app.update();
+    }
+
+    #[test]
+    fn handle_is_stable_across_rebuilds() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+        ));
+        let first = spawn_navmesh(&mut app);
+        let second = spawn_navmesh(&mut app);
+        let obstacle = spawn_obstacle(&mut app, 2.0);
+        app.update();
+        app.update();
+
+        let handle = app.world().get::<Handle<NavMesh>>(first).unwrap().clone();
+        assert_ne!(handle.id(), AssetId::default());
+        assert_ne!(
+            app.world().get::<Handle<NavMesh>>(second).unwrap().id(),
+            handle.id()
+        );
+        assert_eq!(
+            app.world()
+                .resource::<Assets<NavMesh>>()
+                .get(&handle)
+                .unwrap()
+                .version(),
+            0
+        );
+
+        move_obstacle(&mut app, obstacle, 4.0);
+        rebuild(&mut app, first);
+        move_obstacle(&mut app, obstacle, 6.0);
+        rebuild(&mut app, first);
+
+        assert_eq!(
+            app.world().get::<Handle<NavMesh>>(first).unwrap().id(),
+            handle.id()
+        );
+        assert_eq!(
+            app.world()
+                .resource::<Assets<NavMesh>>()
+                .get(&handle)
+                .unwrap()
+                .version(),
+            2
+        );
+        assert_eq!(app.world().resource::<Assets<NavMesh>>().len(), 2);
+    }
+
+    #[test]
+    fn unchanged_obstacles_reuse_previous_builds() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+        ));
+        let entity = spawn_navmesh(&mut app);
+        let obstacle = spawn_obstacle(&mut app, 2.0);
+        app.update();
+        app.update();
+        let navmesh = |app: &App| {
+            let handle = app.world().get::<Handle<NavMesh>>(entity).unwrap();
+            app.world()
+                .resource::<Assets<NavMesh>>()
+                .get(handle)
+                .unwrap()
+                .clone()
+        };
+        let built = navmesh(&app);
+
+        // Nothing changed, the navmesh is kept
+        rebuild(&mut app, entity);
+        assert_eq!(navmesh(&app).version(), 0);
+
+        move_obstacle(&mut app, obstacle, 6.0);
+        rebuild(&mut app, entity);
+        let moved = navmesh(&app);
+        assert_eq!(moved.version(), 1);
+        assert!(!Arc::ptr_eq(&moved.mesh, &built.mesh));
+
+        // Back to the first position, the first build is published again
+        move_obstacle(&mut app, obstacle, 2.0);
+        rebuild(&mut app, entity);
+        let back = navmesh(&app);
+        assert_eq!(back.version(), 2);
+        assert!(Arc::ptr_eq(&back.mesh, &built.mesh));
+
+        // The cache is dropped when the settings change
+        app.world_mut()
+            .get_mut::<NavMeshSettings>(entity)
+            .unwrap()
+            .merge_steps = 1;
+        rebuild(&mut app, entity);
+        move_obstacle(&mut app, obstacle, 6.0);
+        rebuild(&mut app, entity);
+        assert!(!Arc::ptr_eq(&navmesh(&app).mesh, &moved.mesh));
+    }
+
+    #[test]
+    fn navmesh_moved_by_its_parent_is_rebuilt() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+        ));
+        let moving = spawn_navmesh(&mut app);
+        let fixed = spawn_navmesh(&mut app);
+        app.world_mut()
+            .entity_mut(fixed)
+            .insert(NavMeshStaticTransform);
+        for entity in [moving, fixed] {
+            app.world_mut()
+                .entity_mut(entity)
+                .insert(GlobalTransform::IDENTITY);
+        }
+        app.update();
+        app.update();
+        for entity in [moving, fixed] {
+            *app.world_mut()
+                .get_mut::<NavMeshUpdateMode>(entity)
+                .unwrap() = NavMeshUpdateMode::Direct;
+            // Only the global transform changes, as when the parent of the navmesh moves
+            *app.world_mut().get_mut::<GlobalTransform>(entity).unwrap() =
+                GlobalTransform::from_xyz(3.0, 0.0, 0.0);
+        }
+        app.update();
+        app.update();
+
+        let translation = |app: &App, entity: Entity| {
+            let handle = app.world().get::<Handle<NavMesh>>(entity).unwrap();
+            app.world()
+                .resource::<Assets<NavMesh>>()
+                .get(handle)
+                .unwrap()
+                .transform()
+                .translation
+        };
+        assert_eq!(translation(&app, moving), Vec3::new(3.0, 0.0, 0.0));
+        assert_eq!(translation(&app, fixed), Vec3::ZERO);
+    }
+
+    #[test]
+    fn obstacle_added_during_a_build_is_applied_after_it() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+        ));
+        let entity = spawn_navmesh(&mut app);
+        app.update();
+        app.update();
+        let has_obstacle = |app: &App| {
+            let handle = app.world().get::<Handle<NavMesh>>(entity).unwrap();
+            !app.world()
+                .resource::<Assets<NavMesh>>()
+                .get(handle)
+                .unwrap()
+                .is_in_mesh(Vec2::new(5.0, 5.0))
+        };
+        assert!(!has_obstacle(&app));
+
+        // A build is running while the obstacle is added
+        app.world_mut().entity_mut(entity).insert((
+            NavMeshUpdateMode::Direct,
+            NavmeshUpdateTask(
+                Arc::new(RwLock::new(None)),
+                Arc::new(AtomicBool::new(false)),
+            ),
+        ));
+        spawn_obstacle(&mut app, 5.0);
+        app.update();
+        app.update();
+        assert!(!has_obstacle(&app));
+
+        // Once the running build is done, the navmesh is built again with the obstacle
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<NavmeshUpdateTask>();
+        app.update();
+        app.update();
+        assert!(has_obstacle(&app));
+    }
+
+    #[test]
+    fn publish_limit_is_shared_by_updater_plugins() {
+        #[derive(Component)]
+        struct OtherMarker;
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+            NavmeshUpdaterPlugin::<PrimitiveObstacle, OtherMarker>::default(),
+        ))
+        .insert_resource(NavMeshPublishLimit {
+            max_per_frame: Some(1),
+        });
+        let entities = [
+            spawn_navmesh(&mut app),
+            spawn_navmesh(&mut app),
+            spawn_navmesh(&mut app),
+        ];
+        let built = |app: &App| {
+            entities
+                .iter()
+                .filter(|entity| {
+                    matches!(
+                        app.world().get::<NavMeshStatus>(**entity).unwrap(),
+                        NavMeshStatus::Built
+                    )
+                })
+                .count()
+        };
+
+        // Built in `PostUpdate`, published one by one from the next `PreUpdate`
+        app.update();
+        assert_eq!(built(&app), 0);
+        for expected in 1..=3 {
+            app.update();
+            assert_eq!(built(&app), expected);
+        }
+    }
+
+    #[test]
+    fn invalid_settings_are_reported_at_spawn() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+        ));
+        let entity = spawn_navmesh(&mut app);
+        *app.world_mut()
+            .get_mut::<NavMeshUpdateMode>(entity)
+            .unwrap() = NavMeshUpdateMode::OnDemand(false);
+        app.world_mut()
+            .get_mut::<NavMeshSettings>(entity)
+            .unwrap()
+            .agent_radius = -1.0;
+        app.update();
+
+        assert!(matches!(
+            app.world().get::<NavMeshStatus>(entity).unwrap(),
+            NavMeshStatus::Invalid
+        ));
+        assert_eq!(
+            app.world().get::<NavMeshSettingsError>(entity),
+            Some(&NavMeshSettingsError::InvalidAgentRadius { radius: -1.0 })
+        );
+
+        // Once fixed, the navmesh can be built again
+        app.world_mut()
+            .get_mut::<NavMeshSettings>(entity)
+            .unwrap()
+            .agent_radius = 0.0;
+        rebuild(&mut app, entity);
+        assert!(matches!(
+            app.world().get::<NavMeshStatus>(entity).unwrap(),
+            NavMeshStatus::Built
+        ));
+        assert!(app.world().get::<NavMeshSettingsError>(entity).is_none());
+    }
+
+    #[test]
+    fn invalid_settings_are_not_built() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+        ));
+        let set_outer_edges = |app: &mut App, entity: Entity, edges: &[Vec2]| {
+            let mut settings = app.world_mut().get_mut::<NavMeshSettings>(entity).unwrap();
+            settings.fixed = Triangulation::from_outer_edges(edges);
+            settings.outer_edges = edges.to_vec();
+        };
+        let degenerate = spawn_navmesh(&mut app);
+        set_outer_edges(
+            &mut app,
+            degenerate,
+            &[Vec2::ZERO, Vec2::X, Vec2::splat(2.0) * Vec2::X],
+        );
+        let empty = spawn_navmesh(&mut app);
+        set_outer_edges(&mut app, empty, &[]);
+        let covered = spawn_navmesh(&mut app);
+        let cover = app
+            .world_mut()
+            .spawn((
+                PrimitiveObstacle::Rectangle(Rectangle::new(20.0, 20.0)),
+                GlobalTransform::from_translation(Vec3::new(5.0, 5.0, 0.0)),
+            ))
+            .id();
+        app.update();
+        app.update();
+
+        for (entity, error) in [
+            (
+                degenerate,
+                NavMeshSettingsError::InvalidOuterEdges { points: 3 },
+            ),
+            (empty, NavMeshSettingsError::InvalidOuterEdges { points: 0 }),
+            (covered, NavMeshSettingsError::EmptyTriangulation),
+        ] {
+            assert!(matches!(
+                app.world().get::<NavMeshStatus>(entity).unwrap(),
+                NavMeshStatus::Invalid
+            ));
+            assert_eq!(
+                app.world().get::<NavMeshSettingsError>(entity),
+                Some(&error)
+            );
+            let handle = app.world().get::<Handle<NavMesh>>(entity).unwrap();
+            assert!(app
+                .world()
+                .resource::<Assets<NavMesh>>()
+                .get(handle)
+                .is_none());
+        }
+        assert_eq!(
+            app.world().get::<NavMeshBuildError>(covered),
+            Some(&NavMeshBuildError::EmptyMesh { agent_radius: 0.0 })
+        );
+
+        // Once fixed, the navmesh can be built again
+        app.world_mut().despawn(cover);
+        set_outer_edges(
+            &mut app,
+            empty,
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+        );
+        rebuild(&mut app, empty);
+        assert!(matches!(
+            app.world().get::<NavMeshStatus>(empty).unwrap(),
+            NavMeshStatus::Built
+        ));
+        assert!(app.world().get::<NavMeshSettingsError>(empty).is_none());
+        assert!(app.world().get::<NavMeshBuildError>(empty).is_none());
+    }
+
+    #[test]
+    fn obstacle_moved_out_of_height_range_is_removed() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            VleueNavigatorPlugin,
+            NavmeshUpdaterPlugin::<PrimitiveObstacle>::default(),
+        ));
+        let entity = spawn_navmesh(&mut app);
+        app.world_mut()
+            .get_mut::<NavMeshSettings>(entity)
+            .unwrap()
+            .obstacle_height_range = Some(-1.0..1.0);
+        *app.world_mut()
+            .get_mut::<NavMeshUpdateMode>(entity)
+            .unwrap() = NavMeshUpdateMode::Direct;
+        let obstacle = spawn_obstacle(&mut app, 5.0);
+        app.update();
+        app.update();
+        let blocked = |app: &App| {
+            let handle = app.world().get::<Handle<NavMesh>>(entity).unwrap();
+            !app.world()
+                .resource::<Assets<NavMesh>>()
+                .get(handle)
+                .unwrap()
+                .is_in_mesh(Vec2::new(5.0, 5.0))
+        };
+        assert!(blocked(&app));
+
+        *app.world_mut()
+            .get_mut::<GlobalTransform>(obstacle)
+            .unwrap() = GlobalTransform::from_translation(Vec3::new(5.0, 5.0, 10.0));
+        app.update();
+        app.update();
+        assert!(!blocked(&app));
+    }
+
+    #[test]
+    fn deterministic_builds_are_identical() {
+        let outer_edges = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(40.0, 0.0),
+            Vec2::new(40.0, 20.0),
+            Vec2::new(0.0, 20.0),
+        ];
+        let settings = NavMeshSettings {
+            fixed: Triangulation::from_outer_edges(&outer_edges),
+            outer_edges,
+            agent_radius: 0.2,
+            agent_radius_variants: vec![0.5, 1.0],
+            deterministic: true,
+            ..default()
+        };
+        let square = |x: f32, y: f32| {
+            vec![
+                Vec2::new(x, y),
+                Vec2::new(x + 1.0, y),
+                Vec2::new(x + 1.0, y + 1.0),
+                Vec2::new(x, y + 1.0),
+            ]
+        };
+        // Pairs of overlapping squares, with room between the pairs for the largest variant
+        let obstacles = (0..100)
+            .map(|i| {
+                let offset = (i / 50) as f32 * 0.5;
+                square(
+                    (i % 10) as f32 * 3.8 + 1.0 + offset,
+                    (i % 50 / 10) as f32 * 3.8 + 1.0 + offset,
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut shuffled = obstacles.clone();
+        shuffled.reverse();
+        shuffled.rotate_left(37);
+
+        let first = build_from_polygons(&obstacles, &settings, Transform::IDENTITY).unwrap();
+        let second = build_from_polygons(&shuffled, &settings, Transform::IDENTITY).unwrap();
+        assert_eq!(first.to_bytes(), second.to_bytes());
+    }
+
+    #[test]
+    fn quantization_larger_than_obstacles_builds_a_valid_mesh() {
+        let outer_edges = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ];
+        let settings = NavMeshSettings {
+            fixed: Triangulation::from_outer_edges(&outer_edges),
+            outer_edges,
+            agent_radius: 0.0,
+            quantization: Some(1.0),
+            ..default()
+        };
+        let obstacles = vec![
+            // Collapses to a point
+            vec![
+                Vec2::new(2.1, 2.1),
+                Vec2::new(2.4, 2.1),
+                Vec2::new(2.4, 2.4),
+                Vec2::new(2.1, 2.4),
+            ],
+            // Collapses to a segment
+            vec![
+                Vec2::new(5.0, 5.1),
+                Vec2::new(9.0, 5.1),
+                Vec2::new(9.0, 5.3),
+                Vec2::new(5.0, 5.3),
+            ],
+            vec![
+                Vec2::new(3.3, 7.2),
+                Vec2::new(6.6, 7.4),
+                Vec2::new(4.9, 8.6),
+            ],
+        ];
+
+        let navmesh = build_from_polygons(&obstacles, &settings, Transform::IDENTITY).unwrap();
+        let mesh = navmesh.get();
+        for polygon in &mesh.polygons {
+            let points = polygon
+                .vertices
+                .iter()
+                .map(|index| mesh.vertices[*index as usize].coords)
+                .collect::<Vec<_>>();
+            assert!(crate::geometry::signed_area(&points) > 0.0);
+        }
+        assert!(!navmesh.is_in_mesh(Vec2::new(5.0, 8.0)));
+        assert!(navmesh.is_in_mesh(Vec2::new(2.2, 2.2)));
+        assert!(navmesh
+            .path(Vec2::new(0.5, 0.5), Vec2::new(9.5, 9.5))
+            .is_some());
+    }
+
+    #[test]
+    fn quantization_keeps_vertices_that_would_flip_polygons() {
+        let mut mesh: polyanya::Mesh = polyanya::Trimesh {
+            vertices: vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(2.0, 0.0),
+                Vec2::new(1.0, 0.4),
+                Vec2::new(2.2, 2.1),
+                Vec2::new(0.0, 2.0),
+            ],
+            triangles: vec![[0, 1, 2], [2, 1, 3], [0, 2, 4], [2, 3, 4]],
+        }
+        .try_into()
+        .unwrap();
+
+        quantize_mesh(&mut mesh, 1.0);
+        // Snapping the third vertex to (1, 0) would collapse the first triangle
+        assert_eq!(mesh.vertices[2].coords, Vec2::new(1.0, 0.4));
+        assert_eq!(mesh.vertices[3].coords, Vec2::new(2.0, 2.0));
+    }
+
+    #[test]
+    fn time_sliced_map_yields_between_chunks() {
+        let clock = FrameClock::default();
+        let cancelled = AtomicBool::new(false);
+        let mut checkpoint = Checkpoint::new(
+            &cancelled,
+            Some(TimeSlice {
+                clock: clock.clone(),
+                budget: Duration::ZERO,
+                frame: None,
+                start: Instant::now(),
+            }),
+            None,
+        );
+        // Chunks are kept for deterministic builds when time-sliced
+        let settings = NavMeshSettings {
+            deterministic: true,
+            ..default()
+        };
+        let items = (0..10).collect::<Vec<u32>>();
+        let mapped = std::sync::atomic::AtomicUsize::new(0);
+        let mut map = Box::pin(checked_map(&items, &settings, 4, &mut checkpoint, |item| {
+            mapped.fetch_add(1, Ordering::Relaxed);
+            item * 2
+        }));
+        let mut poll = || block_on(bevy::tasks::futures_lite::future::poll_once(&mut map));
+
+        // One chunk per frame, as the budget is spent after each chunk
+        assert!(poll().is_none());
+        assert_eq!(mapped.load(Ordering::Relaxed), 4);
+        assert!(poll().is_none());
+        assert_eq!(mapped.load(Ordering::Relaxed), 4);
+        clock.advance();
+        assert!(poll().is_none());
+        assert_eq!(mapped.load(Ordering::Relaxed), 8);
+        clock.advance();
+        let result = poll().unwrap().unwrap();
+        assert_eq!(
+            result,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn par_map_keeps_order_on_the_given_pool() {
+        let pool = bevy::tasks::TaskPoolBuilder::new().num_threads(2).build();
+        let items = (0..100).collect::<Vec<u32>>();
+        let expected = items.iter().map(|item| item * 2).collect::<Vec<_>>();
+
+        assert_eq!(par_map(&items, 8, Some(&pool), |item| item * 2), expected);
+        assert_eq!(par_map(&items, 8, None, |item| item * 2), expected);
+    }
+
+    #[test]
+    fn changed_area_covers_added_and_removed_obstacles() {
+        let square = |x: f32| {
+            vec![
+                Vec2::new(x, 0.0),
+                Vec2::new(x + 1.0, 0.0),
+                Vec2::new(x + 1.0, 1.0),
+                Vec2::new(x, 1.0),
+            ]
+        };
+        let footprints = |xs: &[f32]| {
+            xs.iter()
+                .map(|x| obstacle_footprint(&square(*x), 0.5))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            changed_area(&footprints(&[0.0, 5.0]), &footprints(&[0.0, 5.0])),
+            None
+        );
+        assert_eq!(
+            changed_area(&footprints(&[0.0, 5.0]), &footprints(&[0.0, 10.0])),
+            Some(Rect::new(4.5, -0.5, 11.5, 1.5))
+        );
+    }
+
+    #[test]
+    fn radius_override_is_shifted_for_variants() {
+        let settings = NavMeshSettings {
+            agent_radius: 0.5,
+            agent_radius_variants: vec![1.0],
+            ..default()
+        };
+
+        assert_eq!(inflation_radius(&settings, 0.5, None), 0.5);
+        assert_eq!(inflation_radius(&settings, 1.0, None), 1.0);
+        assert_eq!(inflation_radius(&settings, 0.5, Some(2.0)), 2.0);
+        assert_eq!(inflation_radius(&settings, 1.0, Some(2.0)), 2.5);
+        assert_eq!(inflation_radius(&settings, 0.5, Some(0.0)), 0.0);
+        assert_eq!(inflation_radius(&settings, 1.0, Some(0.0)), 0.5);
     }
 }